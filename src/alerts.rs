@@ -0,0 +1,610 @@
+//! Alerts when the load becomes excessive or a user crosses their fair share.
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use loadrs::{Contact, LoadReport, LoadState};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    ExcessiveLoad,
+    LoadWarning,
+    LoadForecast,
+    FairShareExceeded,
+    UsageSpike,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub event: Event,
+    pub host: String,
+    /// The offending user, for `fair_share_exceeded` and `usage_spike`
+    /// alerts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Human readable summary (also the message shown by Slack).
+    pub text: String,
+    pub timestamp: u64,
+    pub load_average: f64,
+    pub total_cores: usize,
+    pub fair_share: f64,
+    /// The user's system CPU share, for `fair_share_exceeded` and
+    /// `usage_spike` alerts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_cpu_share: Option<f64>,
+    /// How to reach the user, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact: Option<Contact>,
+}
+
+/// Decides when to alert: when the load first enters the warning or
+/// excessive load state, a user first crosses their fair share, or a user's
+/// usage spikes, unless the same alert was sent within the cooldown.
+pub struct Alerter {
+    host: String,
+    cooldown: Duration,
+    /// Alert keys whose condition held in the previous sample.
+    active: HashSet<String>,
+    last_sent: HashMap<String, Instant>,
+}
+
+impl Alerter {
+    pub fn new(cooldown: Duration) -> Self {
+        Self {
+            host: sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string()),
+            cooldown,
+            active: HashSet::new(),
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// The alerts to send for a new sample.
+    pub fn check(&mut self, report: &LoadReport) -> Vec<Alert> {
+        // Each condition that holds, as (key, event, user, text).
+        let mut current = Vec::new();
+        if report.excessive_load {
+            current.push((
+                "load".to_string(),
+                Event::ExcessiveLoad,
+                None,
+                format!(
+                    "Excessive load on {}: {} minute load average {:.2} on {} cores ({} users over fair share)",
+                    self.host,
+                    report.load_window.minutes(),
+                    report.load(),
+                    report.total_cores,
+                    report.offenders.len()
+                ),
+            ));
+        }
+        if report.load_state == LoadState::Warn {
+            current.push((
+                "load-warn".to_string(),
+                Event::LoadWarning,
+                None,
+                format!(
+                    "High load on {}: {} minute load average {:.2} on {} cores",
+                    self.host,
+                    report.load_window.minutes(),
+                    report.load(),
+                    report.total_cores
+                ),
+            ));
+        }
+        if let Some(forecast) = &report.forecast {
+            current.push((
+                "forecast".to_string(),
+                Event::LoadForecast,
+                None,
+                format!(
+                    "Rising load on {}: at current growth (+{:.2} cores/minute), the load will exceed the threshold in {}",
+                    self.host,
+                    forecast.cores_per_minute,
+                    crate::format_minutes(forecast.seconds)
+                ),
+            ));
+        }
+        for offender in &report.offenders {
+            current.push((
+                format!("user:{}", offender.username),
+                Event::FairShareExceeded,
+                Some(offender.username.as_str()),
+                format!(
+                    "{} is using {:.2}% of {}'s CPU, {:.2}% over their fair share of {:.2}%",
+                    offender.username,
+                    offender.system_cpu_share,
+                    self.host,
+                    offender.excess_usage,
+                    offender.fair_share
+                ),
+            ));
+        }
+        for spike in &report.spikes {
+            current.push((
+                format!("spike:{}", spike.username),
+                Event::UsageSpike,
+                Some(spike.username.as_str()),
+                format!(
+                    "{}'s usage on {} jumped to {:.2} cores from a baseline of {:.2}",
+                    spike.username, self.host, spike.equivalent_cores, spike.baseline_cores
+                ),
+            ));
+        }
+
+        let now = Instant::now();
+        let mut alerts = Vec::new();
+        let mut active = HashSet::new();
+        for (key, event, username, text) in current {
+            let crossed = !self.active.contains(&key);
+            let cooled_down = self
+                .last_sent
+                .get(&key)
+                .is_none_or(|sent| now.duration_since(*sent) >= self.cooldown);
+            if crossed && cooled_down {
+                self.last_sent.insert(key.clone(), now);
+                let user =
+                    username.and_then(|u| report.users.iter().find(|user| user.username == u));
+                let contact = user.and_then(|u| u.contact.clone());
+                // So whoever reads the alert knows who to ask.
+                let text = match &contact {
+                    Some(contact) => format!("{} (contact: {})", text, contact),
+                    None => text,
+                };
+                alerts.push(Alert {
+                    event,
+                    host: self.host.clone(),
+                    user: username.map(str::to_string),
+                    text,
+                    timestamp: report.timestamp,
+                    load_average: report.load(),
+                    total_cores: report.total_cores,
+                    fair_share: user.map_or(report.fair_share.value, |u| u.fair_share),
+                    system_cpu_share: user.map(|u| u.system_cpu_share),
+                    contact,
+                });
+            }
+            active.insert(key);
+        }
+        self.active = active;
+        alerts
+    }
+}
+
+/// Somewhere to send alerts. Each notifier decides which alerts concern it,
+/// e.g. terminal notices are only written for `fair_share_exceeded` alerts.
+pub trait Notifier {
+    /// What the notifier sends to, for error messages, e.g. `webhook`.
+    fn name(&self) -> &str;
+
+    fn send(&self, alert: &Alert) -> Result<(), String>;
+}
+
+fn agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(10)))
+        .build()
+        .into()
+}
+
+/// POSTs each alert as JSON to a webhook, e.g. a Slack incoming webhook.
+pub struct Webhook {
+    agent: ureq::Agent,
+    url: String,
+}
+
+impl Webhook {
+    pub fn new(url: &str) -> Self {
+        Self {
+            agent: agent(),
+            url: url.to_string(),
+        }
+    }
+}
+
+impl Notifier for Webhook {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn send(&self, alert: &Alert) -> Result<(), String> {
+        let body = serde_json::to_string(alert).expect("Could not serialize alert.");
+        self.agent
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .send(&body)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// Posts each alert's text to a Matrix room, as the user whose access token
+/// it's given (usually a dedicated bot account that has joined the room).
+pub struct Matrix {
+    agent: ureq::Agent,
+    /// The room's message endpoint, without the transaction ID.
+    url: String,
+    token: String,
+    /// Matrix ignores a repeated transaction ID, so each message needs a new
+    /// one.
+    sent: Cell<u64>,
+}
+
+impl Matrix {
+    /// A notifier for `room`, an ID like `!abc123:example.org` or an alias
+    /// like `#alerts:example.org`, on the homeserver at `homeserver`, e.g.
+    /// `https://matrix.example.org`.
+    pub fn new(homeserver: &str, room: &str, token: &str) -> Self {
+        Self {
+            agent: agent(),
+            url: format!(
+                "{}/_matrix/client/v3/rooms/{}/send/m.room.message",
+                homeserver.trim_end_matches('/'),
+                encode(room)
+            ),
+            token: token.to_string(),
+            sent: Cell::new(0),
+        }
+    }
+}
+
+impl Notifier for Matrix {
+    fn name(&self) -> &str {
+        "Matrix"
+    }
+
+    fn send(&self, alert: &Alert) -> Result<(), String> {
+        let sent = self.sent.get();
+        self.sent.set(sent + 1);
+        let txn = format!("loadrs-{}-{}-{}", std::process::id(), alert.timestamp, sent);
+        // Bots send notices, which clients show less prominently and other
+        // bots don't respond to.
+        let body = serde_json::json!({"msgtype": "m.notice", "body": alert.text});
+        self.agent
+            .put(&format!("{}/{}", self.url, txn))
+            .header("Authorization", &format!("Bearer {}", self.token))
+            .header("Content-Type", "application/json")
+            .send(body.to_string())
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// Percent-encode a URL path segment, e.g. a Matrix room ID.
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Runs a shell command for each alert with the alert's JSON on its stdin,
+/// for chat systems and paging tools without a built-in notifier.
+pub struct Script {
+    command: String,
+}
+
+impl Script {
+    /// How long a command may run before it's killed, so a hung script
+    /// doesn't stall sampling.
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+        }
+    }
+}
+
+impl Notifier for Script {
+    fn name(&self) -> &str {
+        &self.command
+    }
+
+    fn send(&self, alert: &Alert) -> Result<(), String> {
+        let body = serde_json::to_string(alert).expect("Could not serialize alert.");
+        // Its output would garble the table, but errors are worth seeing.
+        let mut child = Command::new("sh")
+            .args(["-c", &self.command])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A script that doesn't read the alert closes the pipe early.
+            match stdin.write_all(body.as_bytes()) {
+                Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.to_string()),
+                _ => {}
+            }
+        }
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+                break status;
+            }
+            if started.elapsed() >= Self::TIMEOUT {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "timed out after {} seconds",
+                    Self::TIMEOUT.as_secs()
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        };
+        if !status.success() {
+            return Err(status.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Shows desktop notifications when the load becomes excessive, or about the
+/// current user's own usage.
+pub struct Desktop {
+    user: Option<String>,
+}
+
+impl Desktop {
+    pub fn new(user: Option<String>) -> Self {
+        Self { user }
+    }
+}
+
+impl Notifier for Desktop {
+    fn name(&self) -> &str {
+        "desktop notification"
+    }
+
+    fn send(&self, alert: &Alert) -> Result<(), String> {
+        let mine = alert.user.is_some() && alert.user == self.user;
+        if alert.event != Event::ExcessiveLoad && !mine {
+            return Ok(());
+        }
+        notify(alert)
+    }
+}
+
+/// Show an alert as a desktop notification, through D-Bus on Linux and the
+/// BSDs, or the notification center on macOS and Windows.
+fn notify(alert: &Alert) -> Result<(), String> {
+    let summary = match alert.event {
+        Event::ExcessiveLoad => format!("Excessive load on {}", alert.host),
+        Event::LoadWarning => format!("High load on {}", alert.host),
+        Event::LoadForecast => format!("Rising load on {}", alert.host),
+        Event::FairShareExceeded => "Over your fair share".to_string(),
+        Event::UsageSpike => "Usage spike".to_string(),
+    };
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .appname("loadrs")
+        .summary(&summary)
+        .body(&alert.text);
+    // macOS has no urgency.
+    #[cfg(not(target_os = "macos"))]
+    if alert.event == Event::ExcessiveLoad {
+        notification.urgency(notify_rust::Urgency::Critical);
+    }
+    notification.show().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Write a polite notice about a `fair_share_exceeded` alert to each of the
+/// offending user's terminals, like `write` does, skipping terminals with
+/// messages turned off (`mesg n`). Returns how many terminals were written
+/// to. Writing to other users' terminals needs root or the `tty` group.
+#[cfg(unix)]
+fn write_ttys(alert: &Alert) -> Result<usize, String> {
+    use std::fs::{self, OpenOptions};
+    use std::os::unix::fs::PermissionsExt;
+
+    let (Some(user), Some(share)) = (&alert.user, alert.system_cpu_share) else {
+        return Ok(0);
+    };
+    let sessions = loadrs::login::sessions()
+        .map_err(|e| e.to_string())?
+        .remove(user)
+        .unwrap_or_default();
+    let cores = |share: f64| share / 100.0 * alert.total_cores as f64;
+    // Terminals may be in raw mode, so end lines with \r\n.
+    let notice = format!(
+        "\r\n\x07Message from loadrs on {}:\r\n\
+         Hi {}, your processes are using {:.1}% of this machine's CPU ({:.1} cores),\r\n\
+         over your fair share of {:.1}% ({:.1} cores) while others are waiting.\r\n\
+         Please consider scaling back, e.g. with fewer parallel jobs or `renice`.\r\n",
+        alert.host,
+        user,
+        share,
+        cores(share),
+        alert.fair_share,
+        cores(alert.fair_share),
+    );
+    let mut written = 0;
+    for session in sessions {
+        // `who` lists terminals relative to /dev, e.g. pts/0.
+        if session.tty.contains("..") {
+            continue;
+        }
+        let path = format!("/dev/{}", session.tty);
+        // `mesg n` clears the group write bit.
+        let accepts = fs::metadata(&path).is_ok_and(|m| m.permissions().mode() & 0o020 != 0);
+        if !accepts {
+            continue;
+        }
+        let mut tty = OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        tty.write_all(notice.as_bytes())
+            .map_err(|e| format!("{}: {}", path, e))?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(not(unix))]
+fn write_ttys(_alert: &Alert) -> Result<usize, String> {
+    Err("writing to terminals is only supported on Unix".to_string())
+}
+
+/// Writes a notice to the terminals of users over their fair share.
+pub struct Terminals;
+
+impl Notifier for Terminals {
+    fn name(&self) -> &str {
+        "terminals"
+    }
+
+    fn send(&self, alert: &Alert) -> Result<(), String> {
+        if alert.event == Event::FairShareExceeded {
+            write_ttys(alert)?;
+        }
+        Ok(())
+    }
+}
+
+/// How to secure the connection to the SMTP server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with STARTTLS (usually port 587)
+    Starttls,
+    /// Connect over TLS (usually port 465)
+    Tls,
+    /// No encryption, e.g. for a local relay on port 25
+    None,
+}
+
+/// SMTP server settings for email alerts.
+pub struct SmtpConfig<'a> {
+    pub server: &'a str,
+    pub port: Option<u16>,
+    pub security: SmtpSecurity,
+    pub username: Option<&'a str>,
+    pub password: Option<&'a str>,
+}
+
+/// Sends alerts by email to admins and, optionally, to offending users.
+pub struct Emailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+    admins: Vec<Mailbox>,
+    /// Email offending users at `user@domain`, or their contact's email.
+    user_domain: Option<String>,
+}
+
+impl Emailer {
+    pub fn new(
+        smtp: &SmtpConfig,
+        from: &str,
+        admins: &[String],
+        user_domain: Option<&str>,
+    ) -> Result<Self, String> {
+        let builder = match smtp.security {
+            SmtpSecurity::Starttls => SmtpTransport::starttls_relay(smtp.server),
+            SmtpSecurity::Tls => SmtpTransport::relay(smtp.server),
+            SmtpSecurity::None => Ok(SmtpTransport::builder_dangerous(smtp.server)),
+        }
+        .map_err(|e| format!("Invalid SMTP server '{}': {}", smtp.server, e))?;
+        let mut builder = builder.timeout(Some(Duration::from_secs(10)));
+        if let Some(port) = smtp.port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = smtp.username {
+            let password = smtp.password.unwrap_or_default();
+            builder =
+                builder.credentials(Credentials::new(username.to_string(), password.to_string()));
+        }
+
+        let mailbox = |address: &str| {
+            address
+                .parse::<Mailbox>()
+                .map_err(|e| format!("Invalid email address '{}': {}", address, e))
+        };
+        Ok(Self {
+            transport: builder.build(),
+            from: mailbox(from)?,
+            admins: admins
+                .iter()
+                .map(|a| mailbox(a))
+                .collect::<Result<_, _>>()?,
+            user_domain: user_domain.map(str::to_string),
+        })
+    }
+}
+
+impl Notifier for Emailer {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    /// Email an alert to the admins, and to the offending user for
+    /// `fair_share_exceeded` alerts if users are emailed.
+    fn send(&self, alert: &Alert) -> Result<(), String> {
+        let user = alert
+            .user
+            .as_ref()
+            .filter(|_| alert.event == Event::FairShareExceeded)
+            .zip(self.user_domain.as_ref());
+        let mut builder = Message::builder().from(self.from.clone());
+        for admin in &self.admins {
+            builder = builder.to(admin.clone());
+        }
+        if let Some((user, domain)) = user {
+            // Their own address, if their contact has one.
+            let address = match alert.contact.as_ref().and_then(|c| c.email.as_ref()) {
+                Some(email) => email.clone(),
+                None => format!("{}@{}", user, domain),
+            };
+            let mailbox = address
+                .parse::<Mailbox>()
+                .map_err(|e| format!("Invalid email address '{}': {}", address, e))?;
+            builder = builder.to(mailbox);
+        }
+        if self.admins.is_empty() && user.is_none() {
+            return Ok(());
+        }
+
+        let subject = match (&alert.event, &alert.user) {
+            (Event::FairShareExceeded, Some(user)) => {
+                format!(
+                    "[loadrs] {} is over their fair share on {}",
+                    user, alert.host
+                )
+            }
+            (Event::UsageSpike, Some(user)) => {
+                format!("[loadrs] Usage spike by {} on {}", user, alert.host)
+            }
+            (Event::LoadWarning, _) => format!("[loadrs] High load on {}", alert.host),
+            (Event::LoadForecast, _) => format!("[loadrs] Rising load on {}", alert.host),
+            _ => format!("[loadrs] Excessive load on {}", alert.host),
+        };
+        let mut body = format!("{}.\n", alert.text);
+        if user.is_some() {
+            body.push_str(
+                "\nThis is an automated message from loadrs, which monitors how a shared \
+                 machine is split among its users. If you can, please reduce the number of \
+                 cores your jobs use so others can keep working. Run `load` to see current \
+                 usage.\n",
+            );
+        }
+        let message = builder
+            .subject(subject)
+            .body(body)
+            .map_err(|e| e.to_string())?;
+        self.transport.send(&message).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
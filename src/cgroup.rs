@@ -0,0 +1,97 @@
+//! Users' CPU limits, from the cgroups of their systemd user slices (Linux
+//! only).
+//!
+//! Sites often cap users on login nodes with e.g. `systemctl set-property
+//! user-1000.slice CPUQuota=400%` or `AllowedCPUs=0-3`, which the kernel
+//! enforces whatever the fair share says.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Where user slices are, under cgroup v2 and then the v1 CPU controllers.
+const SLICES: [&str; 3] = [
+    "/sys/fs/cgroup/user.slice",
+    "/sys/fs/cgroup/cpu,cpuacct/user.slice",
+    "/sys/fs/cgroup/cpu/user.slice",
+];
+
+/// The v1 cpuset controller's user slices.
+const CPUSET_SLICES: &str = "/sys/fs/cgroup/cpuset/user.slice";
+
+/// Each limited user's CPU limit in cores, by UID: the smaller of their
+/// slice's CPU quota and the number of CPUs it's allowed to run on. Users
+/// without a limit are left out.
+pub fn user_cpu_limits() -> HashMap<u32, f64> {
+    let mut limits = HashMap::new();
+    let Some(slices) = SLICES.iter().map(Path::new).find(|dir| dir.is_dir()) else {
+        return limits;
+    };
+    let Ok(entries) = fs::read_dir(slices) else {
+        return limits;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(uid) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("user-"))
+            .and_then(|name| name.strip_suffix(".slice"))
+            .and_then(|uid| uid.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let slice = entry.path();
+        let cpuset = Path::new(CPUSET_SLICES).join(&name);
+        let cpus = [slice.join("cpuset.cpus"), cpuset.join("cpuset.cpus")]
+            .iter()
+            .find_map(|path| fs::read_to_string(path).ok())
+            .and_then(|cpus| count_cpus(&cpus));
+        let limit = match (quota(&slice), cpus) {
+            (Some(quota), Some(cpus)) => Some(quota.min(cpus as f64)),
+            (quota, cpus) => quota.or(cpus.map(|cpus| cpus as f64)),
+        };
+        if let Some(limit) = limit {
+            limits.insert(uid, limit);
+        }
+    }
+    limits
+}
+
+/// A slice's CPU quota in cores, from `cpu.max` (v2), e.g. `400000 100000`,
+/// or `cpu.cfs_quota_us` and `cpu.cfs_period_us` (v1). `None` if unlimited.
+fn quota(slice: &Path) -> Option<f64> {
+    let read = |file: &str| fs::read_to_string(slice.join(file)).ok();
+    let (quota, period) = match read("cpu.max") {
+        Some(max) => {
+            let mut fields = max.split_whitespace();
+            (
+                fields.next()?.parse::<f64>().ok()?,
+                fields.next()?.parse::<f64>().ok()?,
+            )
+        }
+        None => (
+            // -1 when unlimited.
+            read("cpu.cfs_quota_us")?.trim().parse::<f64>().ok()?,
+            read("cpu.cfs_period_us")?.trim().parse::<f64>().ok()?,
+        ),
+    };
+    (quota > 0.0 && period > 0.0).then(|| quota / period)
+}
+
+/// The number of CPUs in a list like `0-3,8,10-11`, or `None` if it's empty
+/// (no restriction).
+fn count_cpus(list: &str) -> Option<usize> {
+    let count = list
+        .trim()
+        .split(',')
+        .filter(|range| !range.is_empty())
+        .map(|range| match range.split_once('-') {
+            Some((first, last)) => match (first.parse::<usize>(), last.parse::<usize>()) {
+                (Ok(first), Ok(last)) if last >= first => last - first + 1,
+                _ => 0,
+            },
+            None => 1,
+        })
+        .sum();
+    (count > 0).then_some(count)
+}
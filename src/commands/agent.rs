@@ -0,0 +1,39 @@
+//! `load agent`: sample continuously and serve the latest report over HTTP,
+//! for `load cluster` to pull from.
+
+use std::io;
+use std::net::TcpListener;
+use std::time::Duration;
+
+use clap::Args;
+use loadrs::Sampler;
+
+use crate::http::{self, Latest, Response};
+
+/// Port agents listen on unless given --listen.
+pub const DEFAULT_PORT: u16 = 7878;
+
+#[derive(Args)]
+pub struct AgentArgs {
+    /// Address to serve reports on
+    #[arg(long, value_name = "ADDR", default_value_t = format!("0.0.0.0:{}", DEFAULT_PORT))]
+    listen: String,
+}
+
+/// Sample every `interval` in the background and serve the latest report as
+/// JSON at `GET /report`.
+pub fn run(args: &AgentArgs, sampler: Sampler, interval: Duration) -> io::Result<()> {
+    let listener = TcpListener::bind(&args.listen)?;
+    let latest = Latest::sample(sampler, interval);
+    eprintln!(
+        "Serving reports on http://{}/report",
+        listener.local_addr()?
+    );
+    http::serve(listener, move |request| {
+        if request.path() != "/report" {
+            return Response::not_found();
+        }
+        let body = serde_json::to_string(&*latest.get()).expect("Could not serialize report.");
+        Response::new("200 OK", "application/json", body)
+    })
+}
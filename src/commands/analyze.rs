@@ -0,0 +1,370 @@
+//! `load analyze`: summarize recorded usage (from `--record`, `--log-file`,
+//! or `--record-session`) per user: totals, when they're busiest, and how
+//! often and how long they were over their fair share.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use flate2::read::MultiGzDecoder;
+use loadrs::history::{History, UserSample};
+use loadrs::LoadReport;
+use prettytable::{Cell, Row};
+use serde::Serialize;
+
+use crate::time::{format_timestamp, parse_time};
+use crate::{format_bytes, format_duration, table, Cli, Format};
+
+/// Busiest hours of the day to list under the table.
+const PEAK_HOURS: usize = 3;
+
+#[derive(Args)]
+pub struct AnalyzeArgs {
+    /// Database written by --record, or files written by --log-file
+    /// (including rotated .csv.gz files) or --record-session
+    #[arg(required = true, value_name = "FILE")]
+    sources: Vec<PathBuf>,
+    /// Only show these users (comma-separated)
+    #[arg(short, long, value_name = "USERS", value_delimiter = ',')]
+    user: Vec<String>,
+    /// Start of the time range, e.g. 7d (ago), 2024-06-01, or a Unix
+    /// timestamp
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    since: Option<u64>,
+    /// End of the time range, in the same formats as --since
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    until: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct Analysis {
+    /// Time of the first and last samples analyzed.
+    first_sample: u64,
+    last_sample: u64,
+    samples: usize,
+    users: Vec<UserAnalysis>,
+    /// Mean cores used by everyone in each hour of the day (UTC) with
+    /// samples, busiest first.
+    hours: Vec<HourUsage>,
+}
+
+#[derive(Serialize)]
+struct UserAnalysis {
+    username: String,
+    /// Number of samples the user appears in.
+    samples: usize,
+    mean_cores: f64,
+    peak_cores: f64,
+    /// Peak resident memory, in bytes.
+    peak_memory: u64,
+    /// CPU time, estimated from cores used and the time between samples.
+    cpu_hours: f64,
+    /// Hour of the day (UTC) in which the user used the most CPU.
+    peak_hour: u8,
+    /// Separate stretches of consecutive samples over the fair share.
+    violations: usize,
+    seconds_over_fair_share: u64,
+    longest_violation_seconds: u64,
+}
+
+#[derive(Serialize)]
+struct HourUsage {
+    hour: u8,
+    mean_cores: f64,
+}
+
+pub fn run(args: &AnalyzeArgs, cli: &Cli) -> io::Result<()> {
+    let samples = read_all(&args.sources, args.since, args.until)?;
+    let Some(analysis) = analyze(&samples, &args.user) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "no samples in the time range",
+        ));
+    };
+
+    if cli.format == Format::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&analysis).expect("Could not serialize analysis.")
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} samples from {} to {} UTC",
+        analysis.samples,
+        format_timestamp(analysis.first_sample),
+        format_timestamp(analysis.last_sample)
+    );
+    let mut table = table::new([
+        "Username",
+        "Samples",
+        "Mean Cores",
+        "Peak Cores",
+        "Peak Memory",
+        "CPU Hours",
+        "Peak Hour (UTC)",
+        "Times Over Fair Share",
+        "Time Over",
+        "Longest Over",
+    ]);
+    for user in &analysis.users {
+        table.add_row(Row::new(vec![
+            Cell::new(&user.username),
+            Cell::new(&user.samples.to_string()),
+            Cell::new(&format!("{:.2}", user.mean_cores)),
+            Cell::new(&format!("{:.2}", user.peak_cores)),
+            Cell::new(&format_bytes(user.peak_memory)),
+            Cell::new(&format!("{:.2}", user.cpu_hours)),
+            Cell::new(&format!("{:02}:00", user.peak_hour)),
+            Cell::new(&user.violations.to_string()),
+            Cell::new(&format_duration(user.seconds_over_fair_share)),
+            Cell::new(&format_duration(user.longest_violation_seconds)),
+        ]));
+    }
+    table::print(&table);
+    let busiest: Vec<String> = analysis
+        .hours
+        .iter()
+        .take(PEAK_HOURS)
+        .map(|h| format!("{:02}:00 ({:.2} cores)", h.hour, h.mean_cores))
+        .collect();
+    println!("\nBusiest hours (UTC): {}", busiest.join(", "));
+    Ok(())
+}
+
+/// Read the users' samples between `since` and `until` from each source, in
+/// order of time.
+pub fn read_all(
+    sources: &[PathBuf],
+    since: Option<u64>,
+    until: Option<u64>,
+) -> io::Result<Vec<UserSample>> {
+    let mut samples = Vec::new();
+    for path in sources {
+        let read = read(path, since, until)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        samples.extend(read);
+    }
+    samples.retain(|s| {
+        since.is_none_or(|since| s.timestamp >= since)
+            && until.is_none_or(|until| s.timestamp <= until)
+    });
+    // Rotated logs may be given in any order.
+    samples.sort_by_key(|s| s.timestamp);
+    Ok(samples)
+}
+
+/// Summarize `samples`, in order of time, for `users` (or everyone), or
+/// `None` if there are none.
+fn analyze(samples: &[UserSample], users: &[String]) -> Option<Analysis> {
+    let first_sample = samples.first()?.timestamp;
+    let last_sample = samples.last()?.timestamp;
+
+    // Each sample stands for the time until the next one, except across gaps
+    // when nothing was recording.
+    let mut timestamps: Vec<u64> = samples.iter().map(|s| s.timestamp).collect();
+    timestamps.dedup();
+    let mut gaps: Vec<u64> = timestamps.windows(2).map(|w| w[1] - w[0]).collect();
+    gaps.sort_unstable();
+    let typical = gaps.get(gaps.len() / 2).copied().unwrap_or(0);
+    let index: HashMap<u64, usize> = timestamps
+        .iter()
+        .enumerate()
+        .map(|(i, &t)| (t, i))
+        .collect();
+    let duration = |i: usize| -> u64 {
+        timestamps
+            .get(i + 1)
+            .map_or(typical, |next| (next - timestamps[i]).min(2 * typical))
+    };
+
+    // Total cores in each sample, and the sum of those by hour of the day.
+    let mut totals: BTreeMap<usize, f64> = BTreeMap::new();
+    for sample in samples {
+        *totals.entry(index[&sample.timestamp]).or_default() += sample.equivalent_cores;
+    }
+    let mut by_hour: BTreeMap<u8, (f64, usize)> = BTreeMap::new();
+    for (i, &timestamp) in timestamps.iter().enumerate() {
+        let hour = by_hour.entry(hour_of_day(timestamp)).or_default();
+        hour.0 += totals.get(&i).copied().unwrap_or(0.0);
+        hour.1 += 1;
+    }
+    let mut hours: Vec<HourUsage> = by_hour
+        .into_iter()
+        .map(|(hour, (cores, count))| HourUsage {
+            hour,
+            mean_cores: cores / count as f64,
+        })
+        .collect();
+    hours.sort_by(|a, b| b.mean_cores.total_cmp(&a.mean_cores));
+
+    let mut by_user: BTreeMap<&str, Vec<&UserSample>> = BTreeMap::new();
+    for sample in samples {
+        if users.is_empty() || users.contains(&sample.username) {
+            by_user.entry(&sample.username).or_default().push(sample);
+        }
+    }
+    let mut analyses: Vec<UserAnalysis> = by_user
+        .into_iter()
+        .map(|(username, samples)| {
+            let mut cpu_seconds = 0.0;
+            let mut by_hour = [0.0; 24];
+            let mut violations = 0;
+            let mut seconds_over = 0;
+            let mut longest = 0;
+            // The sample index and length of the current violation.
+            let mut current: Option<(usize, u64)> = None;
+            for sample in &samples {
+                let i = index[&sample.timestamp];
+                let seconds = duration(i);
+                cpu_seconds += sample.equivalent_cores * seconds as f64;
+                by_hour[hour_of_day(sample.timestamp) as usize] +=
+                    sample.equivalent_cores * seconds as f64;
+                let over = sample
+                    .fair_share
+                    .is_some_and(|fair| sample.system_cpu_share > fair);
+                current = match (over, current) {
+                    // Absent from a sample means idle, which ends a violation.
+                    (true, Some((last, length))) if last + 1 == i => Some((i, length + seconds)),
+                    (true, _) => {
+                        violations += 1;
+                        Some((i, seconds))
+                    }
+                    (false, _) => None,
+                };
+                if let Some((_, length)) = current {
+                    seconds_over += seconds;
+                    longest = longest.max(length);
+                }
+            }
+            let peak_hour = (0..24)
+                .max_by(|&a, &b| by_hour[a].total_cmp(&by_hour[b]).then(b.cmp(&a)))
+                .unwrap_or(0) as u8;
+            UserAnalysis {
+                username: username.to_string(),
+                samples: samples.len(),
+                mean_cores: samples.iter().map(|s| s.equivalent_cores).sum::<f64>()
+                    / samples.len() as f64,
+                peak_cores: samples
+                    .iter()
+                    .map(|s| s.equivalent_cores)
+                    .fold(0.0, f64::max),
+                peak_memory: samples.iter().map(|s| s.memory).max().unwrap_or(0),
+                cpu_hours: cpu_seconds / 3600.0,
+                peak_hour,
+                violations,
+                seconds_over_fair_share: seconds_over,
+                longest_violation_seconds: longest,
+            }
+        })
+        .collect();
+    analyses.sort_by(|a, b| b.cpu_hours.total_cmp(&a.cpu_hours));
+
+    Some(Analysis {
+        first_sample,
+        last_sample,
+        samples: timestamps.len(),
+        users: analyses,
+        hours,
+    })
+}
+
+fn hour_of_day(timestamp: u64) -> u8 {
+    (timestamp % 86400 / 3600) as u8
+}
+
+/// Read the users' samples from a database, a CSV or TSV log (possibly
+/// gzipped), or a session file, telling them apart by their contents.
+fn read(path: &Path, since: Option<u64>, until: Option<u64>) -> io::Result<Vec<UserSample>> {
+    let mut magic = [0; 16];
+    let n = File::open(path)?.read(&mut magic)?;
+    if magic[..n] == *b"SQLite format 3\0" {
+        let history = History::open(path).map_err(io::Error::other)?;
+        return history.user_samples(since, until).map_err(io::Error::other);
+    }
+    let file = File::open(path)?;
+    let mut reader: Box<dyn BufRead> = if magic[..n.min(2)] == [0x1f, 0x8b] {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+    let start = reader.fill_buf()?;
+    let (csv, tsv) = (
+        start.starts_with(b"timestamp,"),
+        start.starts_with(b"timestamp\t"),
+    );
+    if csv || tsv {
+        read_delimited(reader, if csv { b',' } else { b'\t' })
+    } else {
+        read_session(reader)
+    }
+}
+
+/// Read a log written by --log-file or `--format csv` (with a header).
+fn read_delimited(reader: impl Read, delimiter: u8) -> io::Result<Vec<UserSample>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers.iter().position(|h| h == name).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("no {} column", name))
+        })
+    };
+    let columns = [
+        column("timestamp")?,
+        column("username")?,
+        column("equivalent_cores")?,
+        column("system_cpu_share")?,
+        column("memory_bytes")?,
+        column("fair_share")?,
+    ];
+    let mut samples = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record?;
+        let field = |column: usize| record.get(columns[column]).unwrap_or("");
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("row {}: invalid number", i + 2),
+            )
+        };
+        let number = |column: usize| field(column).parse::<f64>().map_err(|_| invalid());
+        samples.push(UserSample {
+            timestamp: field(0).parse().map_err(|_| invalid())?,
+            username: field(1).to_string(),
+            equivalent_cores: number(2)?,
+            system_cpu_share: number(3)?,
+            memory: field(4).parse().map_err(|_| invalid())?,
+            // "inf" when no one was active.
+            fair_share: Some(number(5)?).filter(|v| v.is_finite()),
+        });
+    }
+    Ok(samples)
+}
+
+/// Read a session written by --record-session, one report per line.
+fn read_session(reader: impl BufRead) -> io::Result<Vec<UserSample>> {
+    let mut samples = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let report: LoadReport = serde_json::from_str(&line).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+        })?;
+        samples.extend(report.users.into_iter().map(|user| UserSample {
+            timestamp: report.timestamp,
+            username: user.username,
+            equivalent_cores: user.equivalent_cores,
+            system_cpu_share: user.system_cpu_share,
+            memory: user.memory,
+            fair_share: Some(user.fair_share).filter(|v| v.is_finite()),
+        }));
+    }
+    Ok(samples)
+}
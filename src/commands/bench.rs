@@ -0,0 +1,219 @@
+//! `load bench`: time the stages of taking a sample, and count what they
+//! allocate, to guide optimization for nodes with tens of thousands of
+//! processes.
+//!
+//! Each iteration samples (reading processes and the rest of the system),
+//! aggregates (summing processes by user into the report), and renders (the
+//! report in the selected `--format`, into memory rather than the
+//! terminal).
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use loadrs::alloc::Allocations;
+use loadrs::simulate::{Process, Scenario, User};
+use loadrs::{LoadReport, Stage};
+use prettytable::{Cell, Row};
+
+use crate::{delimited, format_bytes, table, Cli, Format};
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Samples to time, after an untimed first sample
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 10)]
+    iterations: usize,
+    /// Simulate this many processes instead of sampling this machine, e.g.
+    /// 30000, to time aggregation and rendering of a huge process table
+    #[arg(long, value_name = "PROCESSES")]
+    synthetic: Option<usize>,
+    /// Users the --synthetic processes belong to
+    #[arg(
+        long,
+        value_name = "USERS",
+        default_value_t = 100,
+        requires = "synthetic"
+    )]
+    users: usize,
+}
+
+pub fn run(args: &BenchArgs, cli: &Cli) -> io::Result<()> {
+    let scenario = match (args.synthetic, &cli.simulate) {
+        (Some(processes), _) => Some(synthetic(processes, args.users)),
+        (None, Some(path)) => Some(Scenario::load(path)?),
+        (None, None) => None,
+    };
+    let source = match (args.synthetic, &cli.simulate) {
+        (Some(_), _) => "synthetic",
+        (None, Some(_)) => "simulated",
+        (None, None) => "this machine's",
+    };
+    let mut sampler = crate::sampler(cli, scenario).map_err(io::Error::other)?;
+    // The first sample waits out the sampling window, and fills caches that
+    // later samples reuse.
+    let mut report = sampler.sample();
+
+    let iterations = args.iterations.max(1);
+    let (mut sampling, mut aggregation, mut rendering) = (vec![], vec![], vec![]);
+    for _ in 0..iterations {
+        report = sampler.sample();
+        let stages = sampler.stages();
+        sampling.push(stages.sampling);
+        aggregation.push(stages.aggregation);
+
+        let start = Instant::now();
+        let allocations = Allocations::now();
+        render(&report, cli)?;
+        rendering.push(Stage {
+            time: start.elapsed(),
+            allocations: Allocations::now() - allocations,
+        });
+    }
+    let total: Vec<Stage> = (0..iterations)
+        .map(|i| Stage {
+            time: sampling[i].time + aggregation[i].time + rendering[i].time,
+            allocations: Allocations {
+                count: sampling[i].allocations.count
+                    + aggregation[i].allocations.count
+                    + rendering[i].allocations.count,
+                bytes: sampling[i].allocations.bytes
+                    + aggregation[i].allocations.bytes
+                    + rendering[i].allocations.bytes,
+            },
+        })
+        .collect();
+
+    let processes: usize = report.users.iter().map(|u| u.process_count).sum();
+    println!(
+        "{} sample{} of {} {} processes by {} users:",
+        iterations,
+        if iterations == 1 { "" } else { "s" },
+        processes,
+        source,
+        report.users.len()
+    );
+    let mut table = table::new([
+        "Stage",
+        "Mean",
+        "Median",
+        "p95",
+        "Max",
+        "Allocations",
+        "Allocated",
+    ]);
+    let rendering_title = format!("Rendering ({})", format_name(cli));
+    for (name, stages) in [
+        ("Sampling", &sampling),
+        ("Aggregation", &aggregation),
+        (rendering_title.as_str(), &rendering),
+        ("Total", &total),
+    ] {
+        let mut times: Vec<Duration> = stages.iter().map(|s| s.time).collect();
+        times.sort();
+        let mean = times.iter().sum::<Duration>() / iterations as u32;
+        let percentile =
+            |p: f64| times[((p * iterations as f64).ceil() as usize).clamp(1, iterations) - 1];
+        let allocations: u64 = stages.iter().map(|s| s.allocations.count).sum();
+        let bytes: u64 = stages.iter().map(|s| s.allocations.bytes).sum();
+        table.add_row(Row::new(vec![
+            Cell::new(name),
+            Cell::new(&format_time(mean)),
+            Cell::new(&format_time(percentile(0.5))),
+            Cell::new(&format_time(percentile(0.95))),
+            Cell::new(&format_time(times[iterations - 1])),
+            Cell::new(&(allocations / iterations as u64).to_string()),
+            Cell::new(&format_bytes(bytes / iterations as u64)),
+        ]));
+    }
+    table::print(&table);
+    println!("Allocations and bytes allocated are per sample.");
+    Ok(())
+}
+
+/// A machine with `processes` processes, spread over `users` users who each
+/// run a few different commands, at 1% CPU each.
+fn synthetic(processes: usize, users: usize) -> Scenario {
+    let users = users.clamp(1, processes.max(1));
+    let commands = [
+        "python train.py",
+        "bash",
+        "sshd: session",
+        "make -j",
+        "R --vanilla",
+    ];
+    Scenario {
+        hostname: None,
+        uptime: 0,
+        cores: 128,
+        memory_gib: 1024.0,
+        available_memory_gib: None,
+        swap_gib: 0.0,
+        swap_used_gib: 0.0,
+        load_average: None,
+        users: (0..users)
+            .map(|i| {
+                let count = processes / users + usize::from(i < processes % users);
+                User {
+                    name: format!("user{}", i),
+                    groups: Vec::new(),
+                    system: false,
+                    uid: Some(1000 + i as u32),
+                    cpu_limit: None,
+                    processes: commands
+                        .iter()
+                        .enumerate()
+                        .map(|(j, command)| Process {
+                            command: command.to_string(),
+                            cpu: 1.0,
+                            memory_mib: 100.0,
+                            count: count / commands.len() + usize::from(j < count % commands.len()),
+                            threads: 1,
+                            run_time: 3600,
+                            fds: 0,
+                            nice: 0,
+                            zombie: false,
+                            job: None,
+                            container: None,
+                            pod: None,
+                        })
+                        .filter(|process| process.count > 0)
+                        .collect(),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Render the report as `load` would print it, into memory: the users table
+/// for table output, or the report in another format.
+fn render(report: &LoadReport, cli: &Cli) -> io::Result<String> {
+    Ok(match cli.format {
+        Format::Json => serde_json::to_string_pretty(report)?,
+        Format::Ndjson => serde_json::to_string(report)?,
+        Format::Csv | Format::Tsv => {
+            let delimiter = if cli.format == Format::Csv {
+                b','
+            } else {
+                b'\t'
+            };
+            let mut out = Vec::new();
+            delimited::write(&mut out, report, delimiter, true).map_err(io::Error::other)?;
+            String::from_utf8_lossy(&out).into_owned()
+        }
+        Format::Table | Format::Nagios => table::users(report, cli).to_string(),
+    })
+}
+
+fn format_name(cli: &Cli) -> &'static str {
+    match cli.format {
+        Format::Json => "json",
+        Format::Ndjson => "ndjson",
+        Format::Csv => "csv",
+        Format::Tsv => "tsv",
+        Format::Table | Format::Nagios => "table",
+    }
+}
+
+fn format_time(time: Duration) -> String {
+    format!("{:.3} ms", time.as_secs_f64() * 1000.0)
+}
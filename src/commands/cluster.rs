@@ -0,0 +1,161 @@
+//! `load cluster`: pull reports from `load agent`s on several hosts and show
+//! them together.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use clap::Args;
+use colored::*;
+use loadrs::LoadReport;
+use serde::Serialize;
+
+use super::agent::DEFAULT_PORT;
+use crate::table::share_color;
+use crate::{format_bytes, table, Cli, Format};
+
+#[derive(Args)]
+pub struct ClusterArgs {
+    /// File listing one agent per line, as HOST or HOST:PORT (blank lines
+    /// and lines starting with # are ignored)
+    #[arg(long, value_name = "FILE")]
+    hosts: PathBuf,
+    /// Give up on an agent after this many seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 5)]
+    timeout: u64,
+}
+
+/// One agent's latest report.
+#[derive(Serialize)]
+struct HostReport {
+    host: String,
+    #[serde(flatten)]
+    report: LoadReport,
+}
+
+pub fn run(args: &ClusterArgs, cli: &Cli) -> io::Result<()> {
+    let hosts: Vec<String> = fs::read_to_string(&args.hosts)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+
+    let timeout = Duration::from_secs(args.timeout);
+    let handles: Vec<_> = hosts
+        .into_iter()
+        .map(|host| thread::spawn(move || (fetch(&host, timeout), host)))
+        .collect();
+    let mut reports = Vec::new();
+    for handle in handles {
+        match handle.join().expect("Agent request panicked") {
+            (Ok(mut report), host) => {
+                crate::sort_users(&mut report, cli);
+                reports.push(HostReport { host, report });
+            }
+            (Err(e), host) => eprintln!("{}", format!("Warning: {}: {}", host, e).yellow()),
+        }
+    }
+
+    match cli.format {
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&reports).expect("Could not serialize reports.")
+        ),
+        Format::Ndjson => {
+            for report in &reports {
+                println!(
+                    "{}",
+                    serde_json::to_string(report).expect("Could not serialize report.")
+                );
+            }
+        }
+        _ => print_tables(&reports, cli),
+    }
+    Ok(())
+}
+
+fn fetch(host: &str, timeout: Duration) -> Result<LoadReport, String> {
+    let addr = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, DEFAULT_PORT)
+    };
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .build()
+        .into();
+    let body = agent
+        .get(format!("http://{}/report", addr))
+        .call()
+        .map_err(|e| e.to_string())?
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&body).map_err(|e| format!("invalid report: {}", e))
+}
+
+fn print_tables(reports: &[HostReport], cli: &Cli) {
+    let mut hosts = table::new([
+        "Host",
+        "Cores",
+        "Load (1m)",
+        "Load (%)",
+        "Active Users",
+        "Fair Share (%)",
+        "Over Fair Share",
+    ]);
+    for HostReport { host, report } in reports {
+        let cells = [
+            host.clone(),
+            report.total_cores.to_string(),
+            format!("{:.2}", report.load_average.one),
+            format!(
+                "{:.0}",
+                100.0 * report.load_average.one / report.total_cores as f64
+            ),
+            report.fair_share.active_users.to_string(),
+            format!("{:.2}", report.fair_share.value),
+            report.offenders.len().to_string(),
+        ];
+        let color = if report.excessive_load {
+            "red"
+        } else {
+            "green"
+        };
+        hosts.add_row(table::colored_row(cells, color));
+    }
+    table::print(&hosts);
+
+    let mut users = table::new([
+        "Host",
+        "Username",
+        "Total CPU Usage (%)",
+        "Equivalent Cores Used",
+        "System CPU Share (%)",
+        "Memory (RSS)",
+    ]);
+    for HostReport { host, report } in reports {
+        for user in &report.users {
+            let cells = [
+                host.clone(),
+                user.username.clone(),
+                format!("{:.2}", user.total_cpu_usage),
+                format!("{:.2}", user.equivalent_cores),
+                format!("{:.2}", user.system_cpu_share),
+                format_bytes(user.memory),
+            ];
+            let color = share_color(
+                user.system_cpu_share,
+                user.fair_share,
+                cli.warn_ratio,
+                cli.crit_ratio,
+            );
+            users.add_row(table::colored_row(cells, color));
+        }
+    }
+    println!();
+    table::print(&users);
+}
@@ -0,0 +1,20 @@
+//! `load completions`: print a shell completion script.
+
+use std::io;
+
+use clap::{Args, Command};
+use clap_complete::Shell;
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// Shell to complete in
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+/// Print the completion script for `cmd`, e.g. for `load completions bash >
+/// /etc/bash_completion.d/load`.
+pub fn run(args: &CompletionsArgs, cmd: &mut Command) {
+    let name = cmd.get_name().to_string();
+    clap_complete::generate(args.shell, cmd, name, &mut io::stdout());
+}
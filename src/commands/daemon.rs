@@ -0,0 +1,104 @@
+//! `load daemon`: sample continuously and serve the latest report over a
+//! Unix socket, so `load status` can answer instantly.
+
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use clap::Args;
+use loadrs::Sampler;
+
+use crate::log::Logger;
+
+/// Where the daemon listens unless given --socket.
+pub const DEFAULT_SOCKET: &str = "/tmp/loadrs.sock";
+
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// Unix socket to serve reports on
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_SOCKET)]
+    socket: PathBuf,
+}
+
+/// Sample every `interval` and send the latest report, as JSON, to every
+/// client that connects to the socket. With `log`, also log each sample as
+/// with `--log`.
+pub fn run(
+    args: &DaemonArgs,
+    mut sampler: Sampler,
+    interval: Duration,
+    log: bool,
+) -> io::Result<()> {
+    let listener = bind(&args.socket)?;
+    let mut logger = Logger::default();
+    let report = sampler.sample();
+    if log {
+        logger.print(&report);
+    }
+    let latest = Arc::new(Mutex::new(report));
+
+    {
+        let latest = Arc::clone(&latest);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let report = sampler.sample();
+            if log {
+                logger.print(&report);
+            }
+            *latest.lock().unwrap_or_else(PoisonError::into_inner) = report;
+        });
+    }
+
+    let socket = args.socket.clone();
+    if let Err(e) = ctrlc::set_handler(move || {
+        let _ = fs::remove_file(&socket);
+        eprintln!("Received interrupt, exiting...");
+        std::process::exit(0);
+    }) {
+        eprintln!(
+            "Warning: {} won't be removed on exit: {}",
+            args.socket.display(),
+            e
+        );
+    }
+
+    eprintln!("Serving reports on {}", args.socket.display());
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Error accepting connection: {}", e);
+                continue;
+            }
+        };
+        let body = serde_json::to_vec(&*latest.lock().unwrap_or_else(PoisonError::into_inner))
+            .expect("Could not serialize report.");
+        if let Err(e) = stream.write_all(&body) {
+            eprintln!("Error sending report: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Listen on `path`, replacing a stale socket left by a daemon that didn't
+/// shut down cleanly, and let every user connect.
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    if UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("a daemon is already listening on {}", path.display()),
+        ));
+    }
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o666))?;
+    Ok(listener)
+}
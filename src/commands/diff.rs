@@ -0,0 +1,142 @@
+//! `load diff`: compare two snapshots saved with `--format json`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use loadrs::{LoadReport, UserUsage};
+
+use crate::table;
+use crate::time::format_timestamp;
+use crate::{format_bytes, format_duration};
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Earlier snapshot, written by `load --format json`
+    before: PathBuf,
+    /// Later snapshot, written by `load --format json`
+    after: PathBuf,
+}
+
+/// One user's usage in both snapshots.
+struct Delta<'a> {
+    username: &'a str,
+    before: Option<&'a UserUsage>,
+    after: Option<&'a UserUsage>,
+}
+
+impl Delta<'_> {
+    fn cpu(&self) -> f64 {
+        self.after.map_or(0.0, |u| u.total_cpu_usage)
+            - self.before.map_or(0.0, |u| u.total_cpu_usage)
+    }
+
+    fn memory(&self) -> i64 {
+        self.after.map_or(0, |u| u.memory as i64) - self.before.map_or(0, |u| u.memory as i64)
+    }
+
+    fn processes(&self) -> i64 {
+        self.after.map_or(0, |u| u.process_count as i64)
+            - self.before.map_or(0, |u| u.process_count as i64)
+    }
+}
+
+/// Print each user's change in CPU, memory, and processes between two
+/// snapshots, biggest CPU change first.
+pub fn run(args: &DiffArgs) -> io::Result<()> {
+    let before = read_snapshot(&args.before)?;
+    let after = read_snapshot(&args.after)?;
+
+    let usernames: BTreeSet<&str> = before
+        .users
+        .iter()
+        .chain(&after.users)
+        .map(|u| u.username.as_str())
+        .collect();
+    let mut deltas: Vec<Delta> = usernames
+        .into_iter()
+        .map(|username| Delta {
+            username,
+            before: before.users.iter().find(|u| u.username == username),
+            after: after.users.iter().find(|u| u.username == username),
+        })
+        .collect();
+    deltas.sort_by(|a, b| b.cpu().abs().total_cmp(&a.cpu().abs()));
+
+    let mut table = table::new([
+        "Username",
+        "CPU Before (%)",
+        "CPU After (%)",
+        "Change",
+        "Memory Before",
+        "Memory After",
+        "Change",
+        "Processes Before",
+        "Processes After",
+        "Change",
+    ]);
+    for delta in &deltas {
+        let cpu = |u: Option<&UserUsage>| {
+            u.map_or("-".to_string(), |u| format!("{:.2}", u.total_cpu_usage))
+        };
+        let memory = |u: Option<&UserUsage>| u.map_or("-".to_string(), |u| format_bytes(u.memory));
+        let processes =
+            |u: Option<&UserUsage>| u.map_or("-".to_string(), |u| u.process_count.to_string());
+        // Users who cut back are green, and users who took more are red.
+        let color = if delta.cpu() < -1.0 {
+            "green"
+        } else if delta.cpu() > 1.0 {
+            "red"
+        } else {
+            ""
+        };
+        table.add_row(table::colored_row(
+            [
+                delta.username.to_string(),
+                cpu(delta.before),
+                cpu(delta.after),
+                format!("{:+.2}", delta.cpu()),
+                memory(delta.before),
+                memory(delta.after),
+                signed_bytes(delta.memory()),
+                processes(delta.before),
+                processes(delta.after),
+                format!("{:+}", delta.processes()),
+            ],
+            color,
+        ));
+    }
+
+    println!(
+        "From {} to {} UTC ({} apart)",
+        format_timestamp(before.timestamp),
+        format_timestamp(after.timestamp),
+        format_duration(after.timestamp.abs_diff(before.timestamp))
+    );
+    println!(
+        "Load average: {:.2} -> {:.2}; active users: {} -> {}\n",
+        before.load_average.one,
+        after.load_average.one,
+        before.fair_share.active_users,
+        after.fair_share.active_users
+    );
+    table::print(&table);
+    Ok(())
+}
+
+fn read_snapshot(path: &Path) -> io::Result<LoadReport> {
+    let contents = fs::read_to_string(path)?;
+    serde_json::from_str(&contents).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+fn signed_bytes(bytes: i64) -> String {
+    let sign = if bytes < 0 { "-" } else { "+" };
+    format!("{}{}", sign, format_bytes(bytes.unsigned_abs()))
+}
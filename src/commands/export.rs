@@ -0,0 +1,45 @@
+//! `load export`: convert recorded usage (from `--record`, `--log-file`, or
+//! `--record-session`) into a columnar file for dataframe libraries.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::commands::analyze;
+use crate::parquet;
+use crate::time::parse_time;
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Database written by --record, or files written by --log-file
+    /// (including rotated .csv.gz files) or --record-session
+    #[arg(required = true, value_name = "FILE")]
+    sources: Vec<PathBuf>,
+    /// Write a Parquet file, with one row per user per sample
+    #[arg(long, value_name = "FILE", required = true)]
+    parquet: PathBuf,
+    /// Only export these users (comma-separated)
+    #[arg(short, long, value_name = "USERS", value_delimiter = ',')]
+    user: Vec<String>,
+    /// Start of the time range, e.g. 30d (ago), 2024-06-01, or a Unix
+    /// timestamp
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    since: Option<u64>,
+    /// End of the time range, in the same formats as --since
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    until: Option<u64>,
+}
+
+pub fn run(args: &ExportArgs) -> io::Result<()> {
+    let mut samples = analyze::read_all(&args.sources, args.since, args.until)?;
+    if !args.user.is_empty() {
+        samples.retain(|s| args.user.contains(&s.username));
+    }
+    let mut out = BufWriter::new(File::create(&args.parquet)?);
+    parquet::write(&mut out, &samples)?;
+    out.flush()?;
+    println!("Wrote {} rows to {}", samples.len(), args.parquet.display());
+    Ok(())
+}
@@ -0,0 +1,48 @@
+//! `load fairshare`: show the fair-share scores kept with `--fairshare-file`.
+
+use std::io;
+use std::path::PathBuf;
+
+use clap::Args;
+use loadrs::fairshare::Ledger;
+use prettytable::{Cell, Row};
+
+use crate::table;
+use crate::time::{self, format_timestamp};
+
+#[derive(Args)]
+pub struct FairshareArgs {
+    /// File written by --fairshare-file
+    file: PathBuf,
+}
+
+/// Print every user's decayed usage and score, as of now.
+pub fn run(args: &FairshareArgs, half_life: u64) -> io::Result<()> {
+    let mut ledger = Ledger::load(&args.file)?;
+    let updated = ledger.updated;
+    ledger.decay(time::now(), half_life);
+
+    let mut table = table::new([
+        "Username",
+        "Decayed CPU Hours",
+        "Usage Share (%)",
+        "Fair-Share Score",
+    ]);
+    for standing in ledger.standings() {
+        table.add_row(Row::new(vec![
+            Cell::new(&standing.username),
+            Cell::new(&format!("{:.2}", standing.usage / 3600.0)),
+            Cell::new(&format!("{:.2}", 100.0 * standing.usage_share)),
+            Cell::new(&format!("{:.3}", standing.score)),
+        ]));
+    }
+    table::print(&table);
+    if updated > 0 {
+        println!(
+            "\nLast updated {} UTC; usage halves every {}.",
+            format_timestamp(updated),
+            crate::format_duration(half_life)
+        );
+    }
+    Ok(())
+}
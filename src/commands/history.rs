@@ -0,0 +1,63 @@
+//! `load history`: summarize usage recorded with `--record`.
+
+use std::path::PathBuf;
+
+use clap::Args;
+use loadrs::history::History;
+use prettytable::{Cell, Row};
+
+use crate::time::{format_timestamp, parse_time};
+use crate::{format_bytes, table};
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    /// Database written by --record
+    db: PathBuf,
+    /// Only show these users (comma-separated)
+    #[arg(short, long, value_name = "USERS", value_delimiter = ',')]
+    user: Vec<String>,
+    /// Start of the time range, e.g. 7d (ago), 2024-06-01, or a Unix
+    /// timestamp
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    since: Option<u64>,
+    /// End of the time range, in the same formats as --since
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    until: Option<u64>,
+}
+
+pub fn run(args: &HistoryArgs) -> rusqlite::Result<()> {
+    let history = History::open(&args.db)?;
+    let summaries = history.summarize(&args.user, args.since, args.until)?;
+
+    let mut table = table::new([
+        "Username",
+        "Samples",
+        "Mean Cores",
+        "Peak Cores",
+        "Mean CPU Share (%)",
+        "Peak Memory",
+        "CPU Hours",
+        "Over Fair Share (%)",
+        "First Seen (UTC)",
+        "Last Seen (UTC)",
+    ]);
+    for summary in &summaries {
+        table.add_row(Row::new(vec![
+            Cell::new(&summary.username),
+            Cell::new(&summary.samples.to_string()),
+            Cell::new(&format!("{:.2}", summary.mean_cores)),
+            Cell::new(&format!("{:.2}", summary.peak_cores)),
+            Cell::new(&format!("{:.2}", summary.mean_cpu_share)),
+            Cell::new(&format_bytes(summary.peak_memory)),
+            Cell::new(&format!("{:.2}", summary.cpu_hours)),
+            Cell::new(&format!(
+                "{:.1}",
+                100.0 * summary.samples_over_fair_share as f64 / summary.samples as f64
+            )),
+            Cell::new(&format_timestamp(summary.first_seen)),
+            Cell::new(&format_timestamp(summary.last_seen)),
+        ]));
+    }
+    table::print(&table);
+    Ok(())
+}
@@ -0,0 +1,12 @@
+//! `load manpage`: print a man page in roff.
+
+use std::io;
+
+use clap::Command;
+use clap_mangen::Man;
+
+/// Print the man page for `cmd`, e.g. for `load manpage >
+/// /usr/local/share/man/man1/load.1`.
+pub fn run(cmd: Command) -> io::Result<()> {
+    Man::new(cmd).render(&mut io::stdout())
+}
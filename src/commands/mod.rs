@@ -0,0 +1,24 @@
+//! Subcommands of `load`.
+
+pub mod agent;
+pub mod analyze;
+pub mod bench;
+pub mod cluster;
+pub mod completions;
+#[cfg(unix)]
+pub mod daemon;
+pub mod diff;
+pub mod export;
+pub mod fairshare;
+pub mod history;
+pub mod manpage;
+pub mod motd;
+pub mod remote;
+pub mod replay;
+pub mod report;
+pub mod serve;
+#[cfg(unix)]
+pub mod service;
+#[cfg(unix)]
+pub mod status;
+pub mod tree;
@@ -0,0 +1,120 @@
+//! `load motd`: a compact, uncolored usage summary for `/etc/motd`, or for
+//! broadcasting with `wall` when load is critical.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use clap::Args;
+use loadrs::LoadReport;
+
+use crate::format_bytes;
+use crate::time::format_timestamp;
+
+#[derive(Args)]
+pub struct MotdArgs {
+    /// Write the summary to this file (e.g. /etc/motd) instead of stdout
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Also broadcast the summary to every terminal with `wall` when load is
+    /// critical
+    #[arg(long)]
+    wall: bool,
+    /// Number of users to list
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    top: usize,
+}
+
+pub fn run(args: &MotdArgs, report: &LoadReport) -> io::Result<()> {
+    let summary = summary(report, args.top);
+    match &args.output {
+        // Write to a temporary file and rename it, so logins never see a
+        // half-written motd.
+        Some(path) => {
+            let tmp = path.with_extension("loadrs-tmp");
+            fs::write(&tmp, &summary)?;
+            fs::rename(&tmp, path)?;
+        }
+        None => print!("{}", summary),
+    }
+    if args.wall && report.excessive_load {
+        wall(&summary)?;
+    }
+    Ok(())
+}
+
+/// A few lines on the load and the busiest users, e.g.
+///
+/// ```text
+/// Load on server at 2024-06-01 13:00:00 UTC: CRIT, 18.20 on 16 cores
+/// Fair share: 25.00% of CPU for each of 4 active users
+///   alice   9.10 cores  56.9% CPU   12.00 GiB  over fair share
+///   bob     4.00 cores  25.0% CPU    2.00 GiB
+/// ```
+fn summary(report: &LoadReport, top: usize) -> String {
+    let host = sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string());
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Load on {} at {} UTC: {}, {:.2} on {} cores",
+        host,
+        format_timestamp(report.timestamp),
+        report.load_state.name(),
+        report.load_average.one,
+        report.total_cores
+    );
+    if report.fair_share.value.is_finite() {
+        let _ = writeln!(
+            out,
+            "Fair share: {:.2}% of CPU for each of {} active users",
+            report.fair_share.value, report.fair_share.active_users
+        );
+    }
+    let users: Vec<_> = report
+        .users
+        .iter()
+        .filter(|u| u.system_cpu_share > report.fair_share.active_threshold)
+        .take(top)
+        .collect();
+    let width = users.iter().map(|u| u.username.len()).max().unwrap_or(0);
+    for user in users {
+        let over = if report.offenders.iter().any(|o| o.username == user.username) {
+            "  over fair share"
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            out,
+            "  {:width$}  {:6.2} cores  {:5.1}% CPU  {:>10}{}",
+            user.username,
+            user.equivalent_cores,
+            user.system_cpu_share,
+            format_bytes(user.memory),
+            over,
+        );
+    }
+    out
+}
+
+/// Broadcast `message` to every logged-in terminal.
+fn wall(message: &str) -> io::Result<()> {
+    let mut child = Command::new("wall")
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), "wall not found"),
+            _ => e,
+        })?;
+    child
+        .stdin
+        .take()
+        .expect("wall's stdin is piped")
+        .write_all(message.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("wall failed: {}", status)));
+    }
+    Ok(())
+}
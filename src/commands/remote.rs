@@ -0,0 +1,350 @@
+//! `load remote`: sample other machines over SSH and show their reports
+//! here, as if `load` had run on each.
+//!
+//! Each host runs its own `load --format json` if it has one. Otherwise, a
+//! fallback script reads the machine's cores, memory, load, and each
+//! process's CPU time (twice, --sample-window apart) from `/proc` and its
+//! processes from `ps`, and they are reported through the same fair-share
+//! calculation as a simulated scenario.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use colored::*;
+use loadrs::platform;
+use loadrs::simulate::{Process, Scenario, User};
+use loadrs::LoadReport;
+use serde::Serialize;
+
+use crate::{print_report, Cli, Format};
+
+/// The first line of the fallback script's output.
+const FALLBACK: &str = "loadrs-fallback";
+
+/// Prints the machine, each process's CPU time in clock ticks at the
+/// start and end of `$window` seconds (`ps -o pcpu` is the average over a
+/// process's whole life), then one process per line (Linux procps).
+const FALLBACK_SCRIPT: &str = r#"
+echo loadrs-fallback
+echo hostname "$(hostname)"
+echo cores "$(getconf _NPROCESSORS_ONLN)"
+echo clock_ticks "$(getconf CLK_TCK)"
+awk '$1 == "UID_MIN" { print "login_defs", $0 }' /etc/login.defs 2>/dev/null
+cpu_times() {
+    echo uptime "$(cut -d' ' -f1 /proc/uptime)"
+    # The command name in parentheses can hold spaces, so count from after it.
+    cat /proc/[0-9]*/stat 2>/dev/null | awk '{ pid = $1; sub(/.*\) /, ""); print "cpu", pid, $12 + $13 }'
+}
+cpu_times
+sleep "$window"
+cpu_times
+echo loadavg "$(cut -d' ' -f1-3 /proc/loadavg)"
+awk '/^(MemTotal|MemAvailable|SwapTotal|SwapFree):/ { print "meminfo", $1, $2 }' /proc/meminfo
+echo processes
+ps -eo pid=,uid=,user:64=,rss=,nlwp=,etimes=,ni=,stat=,args=
+"#;
+
+const KIB_PER_MIB: f64 = 1024.0;
+const KIB_PER_GIB: f64 = 1024.0 * 1024.0;
+
+#[derive(Args)]
+pub struct RemoteArgs {
+    /// Hosts to sample, as anything `ssh` accepts, e.g. user@login1
+    #[arg(required = true)]
+    hosts: Vec<String>,
+    /// The `load` command on the hosts, run with --format json and ARGS
+    #[arg(long, value_name = "COMMAND", default_value = "load")]
+    remote_command: String,
+    /// Give up on a host that doesn't answer a connection attempt or a
+    /// keepalive for this many seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    timeout: u64,
+    /// Give up on a host that hasn't sent its report after this many
+    /// seconds in all
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    deadline: u64,
+    /// Options for the remote `load`, after --, e.g. -- --fair-share 8c
+    #[arg(last = true, value_name = "ARGS")]
+    args: Vec<String>,
+}
+
+/// One host's report.
+#[derive(Serialize)]
+struct HostReport {
+    host: String,
+    #[serde(flatten)]
+    report: LoadReport,
+}
+
+/// Sample the hosts and print their reports, returning the code to exit
+/// with: the worst host's status for --format nagios, or else 0.
+pub fn run(args: &RemoteArgs, cli: &Cli) -> io::Result<i32> {
+    let script = remote_command(args, Duration::from_millis(cli.sample_window));
+    let handles: Vec<_> = args
+        .hosts
+        .iter()
+        .map(|host| {
+            let (host, script) = (host.clone(), script.clone());
+            let (timeout, deadline) = (args.timeout, Duration::from_secs(args.deadline));
+            thread::spawn(move || (ssh(&host, &script, timeout, deadline), host))
+        })
+        .collect();
+    let mut reports = Vec::new();
+    for handle in handles {
+        let (output, host) = handle.join().expect("SSH thread panicked");
+        match output.and_then(|output| report(&output, cli)) {
+            Ok(mut report) => {
+                crate::sort_users(&mut report, cli);
+                reports.push(HostReport { host, report });
+            }
+            Err(e) => eprintln!("{}", format!("Warning: {}: {}", host, e).yellow()),
+        }
+    }
+
+    let mut status = 0;
+    match cli.format {
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&reports).expect("Could not serialize reports.")
+        ),
+        Format::Ndjson => {
+            for report in &reports {
+                println!(
+                    "{}",
+                    serde_json::to_string(report).expect("Could not serialize report.")
+                );
+            }
+        }
+        _ => {
+            for (i, HostReport { host, report }) in reports.iter().enumerate() {
+                if cli.format == Format::Table {
+                    println!("{}", format!("\n== {} ==", host).bold());
+                }
+                let code = print_report(report, cli, i == 0 && !cli.no_header)
+                    .map_err(io::Error::other)?;
+                status = status.max(code);
+            }
+        }
+    }
+    Ok(status)
+}
+
+/// The script run on each host: its own `load` if it has one, or else the
+/// fallback, measuring CPU usage over `window`.
+fn remote_command(args: &RemoteArgs, window: Duration) -> String {
+    let command = quote(&args.remote_command);
+    let load_args: Vec<String> = args.args.iter().map(|arg| quote(arg)).collect();
+    format!(
+        "if command -v {} >/dev/null 2>&1; then exec {} --format json {}; fi\nwindow={:.3}\n{}",
+        command,
+        command,
+        load_args.join(" "),
+        window.as_secs_f64(),
+        FALLBACK_SCRIPT
+    )
+}
+
+/// Quote `s` for a POSIX shell.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Run `script` on `host` with `sh`, returning its output, or an error if
+/// the connection goes quiet for `timeout` seconds or the whole run takes
+/// longer than `deadline`.
+fn ssh(host: &str, script: &str, timeout: u64, deadline: Duration) -> io::Result<String> {
+    let mut child = Command::new("ssh")
+        // Never prompt for a password, which would hang with no terminal.
+        .args(["-o", "BatchMode=yes", "-o"])
+        .arg(format!("ConnectTimeout={}", timeout))
+        .arg("-o")
+        .arg(format!("ServerAliveInterval={}", timeout))
+        .args(["-o", "ServerAliveCountMax=1"])
+        .args([host, "sh", "-s"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("Stdin was piped.")
+        .write_all(script.as_bytes())?;
+    // Read both pipes as they fill, so a large process list can't block the
+    // remote end while we wait.
+    let stdout = read_to_end(child.stdout.take().expect("Stdout was piped."));
+    let stderr = read_to_end(child.stderr.take().expect("Stderr was piped."));
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no report after {} seconds", deadline.as_secs()),
+            ));
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    let stdout = stdout.join().expect("Reader thread panicked")?;
+    let stderr = stderr.join().expect("Reader thread panicked")?;
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        return Err(io::Error::other(match stderr.trim() {
+            "" => status.to_string(),
+            stderr => stderr.to_string(),
+        }));
+    }
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+/// Read `pipe` to its end on another thread.
+fn read_to_end(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        pipe.read_to_end(&mut buf)?;
+        Ok(buf)
+    })
+}
+
+/// The report in a host's output: the remote `load`'s, or one built from
+/// the fallback script's.
+fn report(output: &str, cli: &Cli) -> io::Result<LoadReport> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    if output.lines().next() != Some(FALLBACK) {
+        return serde_json::from_str(output).map_err(|e| invalid(format!("invalid report: {}", e)));
+    }
+    let scenario = scenario(output).map_err(invalid)?;
+    let mut sampler = crate::sampler(cli, Some(scenario)).map_err(io::Error::other)?;
+    Ok(sampler.sample())
+}
+
+/// A scenario of the machine described by the fallback script's output.
+fn scenario(output: &str) -> Result<Scenario, String> {
+    let mut lines = output.lines().skip(1);
+    let mut scenario = Scenario {
+        hostname: None,
+        uptime: 0,
+        cores: 0,
+        memory_gib: 0.0,
+        available_memory_gib: None,
+        swap_gib: 0.0,
+        swap_used_gib: 0.0,
+        load_average: None,
+        users: Vec::new(),
+    };
+    let number = |s: Option<&str>| -> Result<f64, String> {
+        s.and_then(|s| s.trim().parse().ok())
+            .ok_or_else(|| format!("unexpected fallback output '{}'", s.unwrap_or_default()))
+    };
+    let mut swap_free = 0.0;
+    let mut clock_ticks = 100.0;
+    let mut uid_min = platform::LINUX_UID_MIN;
+    // Each process's CPU time in ticks, at each uptime it was read at.
+    let mut cpu_times: Vec<(f64, HashMap<u32, f64>)> = Vec::new();
+    for line in lines.by_ref() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "hostname" => scenario.hostname = Some(value.to_string()),
+            "cores" => scenario.cores = number(Some(value))? as usize,
+            "clock_ticks" => clock_ticks = number(Some(value))?,
+            "login_defs" => uid_min = platform::uid_min(value).unwrap_or(uid_min),
+            "uptime" => {
+                let uptime = number(Some(value))?;
+                scenario.uptime = uptime as u64;
+                cpu_times.push((uptime, HashMap::new()));
+            }
+            "cpu" => {
+                let (pid, ticks) = value.split_once(' ').unwrap_or((value, ""));
+                if let Some((_, times)) = cpu_times.last_mut() {
+                    times.insert(number(Some(pid))? as u32, number(Some(ticks))?);
+                }
+            }
+            "loadavg" => {
+                let mut loads = value.split_whitespace();
+                scenario.load_average = Some([
+                    number(loads.next())?,
+                    number(loads.next())?,
+                    number(loads.next())?,
+                ]);
+            }
+            "meminfo" => {
+                let (name, kib) = value.split_once(' ').unwrap_or((value, ""));
+                let gib = number(Some(kib))? / KIB_PER_GIB;
+                match name {
+                    "MemTotal:" => scenario.memory_gib = gib,
+                    "MemAvailable:" => scenario.available_memory_gib = Some(gib),
+                    "SwapTotal:" => scenario.swap_gib = gib,
+                    _ => swap_free = gib,
+                }
+            }
+            "processes" => break,
+            _ => {}
+        }
+    }
+    if scenario.cores == 0 {
+        return Err("the fallback script couldn't count the host's cores".to_string());
+    }
+    scenario.swap_used_gib = (scenario.swap_gib - swap_free).max(0.0);
+    let [(start, before), (end, after)] = <[_; 2]>::try_from(cpu_times)
+        .map_err(|_| "the fallback script couldn't read CPU times".to_string())?;
+    // As a percentage of one core, like `ps -o pcpu`. Processes started
+    // during the window used all their CPU time in it.
+    let cpu = |pid: u32| -> f64 {
+        let used =
+            after.get(&pid).copied().unwrap_or(0.0) - before.get(&pid).copied().unwrap_or(0.0);
+        match end - start {
+            elapsed if elapsed > 0.0 => (used / clock_ticks / elapsed * 100.0).max(0.0),
+            _ => 0.0,
+        }
+    };
+
+    let mut users: BTreeMap<String, User> = BTreeMap::new();
+    for line in lines {
+        let mut fields = line.split_whitespace();
+        let (Some(pid), Some(uid), Some(name)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let cpu = cpu(number(Some(pid))? as u32);
+        let uid: u32 = number(Some(uid))? as u32;
+        let memory_mib = number(fields.next())? / KIB_PER_MIB;
+        let threads = number(fields.next())? as usize;
+        let run_time = number(fields.next())? as u64;
+        // Real-time processes have no nice value.
+        let nice = fields.next().and_then(|ni| ni.parse().ok()).unwrap_or(0);
+        let zombie = fields.next().is_some_and(|stat| stat.starts_with('Z'));
+        let command = fields.collect::<Vec<_>>().join(" ");
+        let user = users.entry(name.to_string()).or_insert_with(|| User {
+            name: name.to_string(),
+            groups: Vec::new(),
+            system: platform::is_system_uid(uid, uid_min),
+            uid: Some(uid),
+            cpu_limit: None,
+            processes: Vec::new(),
+        });
+        user.processes.push(Process {
+            command,
+            cpu,
+            memory_mib,
+            count: 1,
+            threads,
+            run_time,
+            fds: 0,
+            nice,
+            zombie,
+            job: None,
+            container: None,
+            pod: None,
+        });
+    }
+    scenario.users = users.into_values().collect();
+    Ok(scenario)
+}
@@ -0,0 +1,79 @@
+//! `load replay`: step through samples saved with `--record-session`.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use clap::Args;
+use loadrs::LoadReport;
+
+use crate::time::{format_timestamp, parse_time};
+use crate::{print_report, Cli, Format};
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    /// Session file written by --record-session
+    file: PathBuf,
+    /// Start at the first sample at or after this time, e.g. 2024-06-01T03:00
+    /// or 6h (ago)
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    from: Option<u64>,
+    /// Seconds to show each sample for
+    #[arg(long, value_name = "SECONDS", default_value_t = 1.0)]
+    delay: f64,
+    /// Wait for enter before showing the next sample, instead of --delay
+    #[arg(long)]
+    step: bool,
+}
+
+pub fn run(args: &ReplayArgs, cli: &Cli) -> io::Result<()> {
+    let mut reports = read_session(&args.file)?;
+    reports.retain(|report| args.from.is_none_or(|from| report.timestamp >= from));
+
+    let stdin = io::stdin();
+    let total = reports.len();
+    for (i, mut report) in reports.into_iter().enumerate() {
+        if cli.format == Format::Table {
+            print!("\x1B[2J\x1B[1;1H");
+            println!(
+                "Sample {}/{} at {} UTC",
+                i + 1,
+                total,
+                format_timestamp(report.timestamp)
+            );
+        }
+        crate::sort_users(&mut report, cli);
+        // --format nagios is refused, so there's no status to return.
+        print_report(&report, cli, i == 0 && !cli.no_header).map_err(io::Error::other)?;
+
+        if i + 1 == total || cli.format != Format::Table {
+            continue;
+        }
+        if args.step {
+            eprint!("Press enter for the next sample...");
+            stdin.lock().read_line(&mut String::new())?;
+        } else {
+            thread::sleep(Duration::from_secs_f64(args.delay.max(0.0)));
+        }
+    }
+    Ok(())
+}
+
+/// Read the samples in a session file written by --record-session.
+pub fn read_session(path: &Path) -> io::Result<Vec<LoadReport>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut reports = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let report = serde_json::from_str(&line).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+        })?;
+        reports.push(report);
+    }
+    Ok(reports)
+}
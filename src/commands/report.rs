@@ -0,0 +1,315 @@
+//! `load report --html`: render usage as a standalone HTML page.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use clap::Args;
+use loadrs::LoadReport;
+
+use crate::format_bytes;
+use crate::time::format_timestamp;
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Write the report to this HTML file
+    #[arg(long, value_name = "FILE")]
+    pub html: PathBuf,
+    /// Report on the samples in this file written by --record-session,
+    /// instead of sampling now
+    #[arg(long, value_name = "FILE")]
+    pub session: Option<PathBuf>,
+}
+
+/// Write an HTML page about `reports`, in order of time: the latest sample's
+/// users, and for several samples, the load over time and each user's mean
+/// usage.
+pub fn run(args: &ReportArgs, reports: &[LoadReport]) -> io::Result<()> {
+    let Some(latest) = reports.last() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "no samples to report on",
+        ));
+    };
+    let host = sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string());
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>loadrs: {host}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>CPU usage on {host}</h1>\n",
+        host = escape(&host),
+    );
+    let _ = writeln!(
+        html,
+        "<p>{} UTC: {} cores, 1 minute load average {:.2} (5 minute {:.2}, 15 minute {:.2}). {} active users, fair share {:.2}%.</p>",
+        format_timestamp(latest.timestamp),
+        latest.total_cores,
+        latest.load_average.one,
+        latest.load_average.five,
+        latest.load_average.fifteen,
+        latest.fair_share.active_users,
+        latest.fair_share.value,
+    );
+
+    if reports.len() > 1 {
+        let first = &reports[0];
+        let _ = writeln!(
+            html,
+            "<h2>Load average</h2>\n<p>{} samples from {} to {} UTC. The dashed line is the excessive load threshold.</p>",
+            reports.len(),
+            format_timestamp(first.timestamp),
+            format_timestamp(latest.timestamp),
+        );
+        html.push_str(&load_chart(reports));
+        html.push_str("<h2>Mean usage over the session</h2>\n");
+        html.push_str(&mean_usage(reports));
+    }
+
+    html.push_str("<h2>Users</h2>\n");
+    html.push_str(&cores_chart(
+        latest
+            .users
+            .iter()
+            .map(|u| (u.username.as_str(), u.equivalent_cores)),
+    ));
+    let rows = latest.users.iter().map(|user| {
+        (
+            user.system_cpu_share > user.fair_share,
+            vec![
+                Value::Text(user.username.clone()),
+                Value::Number(user.total_cpu_usage, format!("{:.2}", user.total_cpu_usage)),
+                Value::Number(
+                    user.equivalent_cores,
+                    format!("{:.2}", user.equivalent_cores),
+                ),
+                Value::Number(
+                    user.system_cpu_share,
+                    format!("{:.2}", user.system_cpu_share),
+                ),
+                Value::Number(user.fair_share, format!("{:.2}", user.fair_share)),
+                Value::Number(user.memory as f64, format_bytes(user.memory)),
+            ],
+        )
+    });
+    html.push_str(&table(
+        &[
+            "Username",
+            "Total CPU Usage (%)",
+            "Equivalent Cores Used",
+            "System CPU Share (%)",
+            "Fair Share (%)",
+            "Memory (RSS)",
+        ],
+        rows,
+    ));
+
+    let _ = write!(
+        html,
+        "<p class=\"note\">Generated by loadrs. Click a column to sort.</p>\n<script>{SCRIPT}</script>\n</body>\n</html>\n"
+    );
+    fs::write(&args.html, html)
+}
+
+/// A table cell, with the number it sorts by if numeric.
+enum Value {
+    Text(String),
+    Number(f64, String),
+}
+
+fn table(titles: &[&str], rows: impl Iterator<Item = (bool, Vec<Value>)>) -> String {
+    let mut html = String::from("<table class=\"sortable\">\n<thead><tr>");
+    for title in titles {
+        let _ = write!(html, "<th>{}</th>", escape(title));
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for (over, cells) in rows {
+        html.push_str(if over { "<tr class=\"over\">" } else { "<tr>" });
+        for cell in cells {
+            match cell {
+                Value::Text(text) => {
+                    let _ = write!(html, "<td>{}</td>", escape(&text));
+                }
+                Value::Number(value, text) => {
+                    // JavaScript's parseFloat reads "Infinity" but not "inf".
+                    let value = match value {
+                        v if v.is_infinite() => "Infinity".to_string(),
+                        v => v.to_string(),
+                    };
+                    let _ = write!(
+                        html,
+                        "<td class=\"num\" data-sort=\"{}\">{}</td>",
+                        value,
+                        escape(&text)
+                    );
+                }
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+    html
+}
+
+/// Each user's mean and peak usage across the samples.
+fn mean_usage(reports: &[LoadReport]) -> String {
+    let mut totals: HashMap<&str, (f64, f64, usize)> = HashMap::new();
+    for report in reports {
+        for user in &report.users {
+            let entry = totals.entry(&user.username).or_default();
+            entry.0 += user.equivalent_cores;
+            entry.1 = entry.1.max(user.equivalent_cores);
+            entry.2 += usize::from(user.system_cpu_share > user.fair_share);
+        }
+    }
+    let samples = reports.len() as f64;
+    let mut users: Vec<_> = totals.into_iter().collect();
+    users.sort_by(|a, b| b.1 .0.total_cmp(&a.1 .0));
+
+    let mut html = cores_chart(
+        users
+            .iter()
+            .map(|(username, (cores, _, _))| (*username, cores / samples)),
+    );
+    let rows = users.iter().map(|(username, (cores, peak, over))| {
+        (
+            false,
+            vec![
+                Value::Text(username.to_string()),
+                Value::Number(cores / samples, format!("{:.2}", cores / samples)),
+                Value::Number(*peak, format!("{:.2}", peak)),
+                Value::Number(
+                    100.0 * *over as f64 / samples,
+                    format!("{:.1}", 100.0 * *over as f64 / samples),
+                ),
+            ],
+        )
+    });
+    html.push_str(&table(
+        &[
+            "Username",
+            "Mean Cores",
+            "Peak Cores",
+            "Samples Over Fair Share (%)",
+        ],
+        rows,
+    ));
+    html
+}
+
+/// A horizontal bar chart of cores used per user.
+fn cores_chart<'a>(users: impl Iterator<Item = (&'a str, f64)>) -> String {
+    let users: Vec<_> = users.filter(|(_, cores)| *cores >= 0.01).take(20).collect();
+    let max = users.iter().map(|(_, cores)| *cores).fold(0.0, f64::max);
+    if max <= 0.0 {
+        return String::new();
+    }
+    let (label, width, bar) = (120.0, 640.0, 18.0);
+    let mut svg = format!(
+        "<svg class=\"chart\" width=\"{}\" height=\"{}\">\n",
+        label + width + 60.0,
+        users.len() as f64 * (bar + 4.0)
+    );
+    for (i, (username, cores)) in users.iter().enumerate() {
+        let y = i as f64 * (bar + 4.0);
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text><rect x=\"{}\" y=\"{}\" width=\"{:.1}\" height=\"{}\"/><text x=\"{:.1}\" y=\"{}\">{:.2}</text>",
+            label - 6.0,
+            y + bar - 4.0,
+            escape(username),
+            label,
+            y,
+            width * cores / max,
+            bar,
+            label + width * cores / max + 4.0,
+            y + bar - 4.0,
+            cores
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A line chart of the 1 minute load average over time.
+fn load_chart(reports: &[LoadReport]) -> String {
+    let (width, height) = (760.0, 200.0);
+    let start = reports[0].timestamp as f64;
+    let span = (reports[reports.len() - 1].timestamp as f64 - start).max(1.0);
+    let threshold = |r: &LoadReport| r.threshold / 100.0 * r.total_cores as f64;
+    let max = reports
+        .iter()
+        .map(|r| r.load_average.one.max(threshold(r)))
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let point = |t: u64, load: f64| {
+        (
+            width * (t as f64 - start) / span,
+            height - height * load / max,
+        )
+    };
+    let points: Vec<String> = reports
+        .iter()
+        .map(|r| {
+            let (x, y) = point(r.timestamp, r.load_average.one);
+            format!("{:.1},{:.1}", x, y)
+        })
+        .collect();
+    let (_, threshold_y) = point(reports[0].timestamp, threshold(&reports[0]));
+    format!(
+        "<svg class=\"chart\" width=\"{w}\" height=\"{h}\" viewBox=\"-40 -10 {vw} {vh}\">\n<text x=\"-6\" y=\"4\" text-anchor=\"end\">{max:.1}</text><text x=\"-6\" y=\"{h0}\" text-anchor=\"end\">0</text>\n<line class=\"axis\" x1=\"0\" y1=\"{h0}\" x2=\"{width}\" y2=\"{h0}\"/>\n<line class=\"threshold\" x1=\"0\" y1=\"{ty:.1}\" x2=\"{width}\" y2=\"{ty:.1}\"/>\n<polyline points=\"{points}\"/>\n</svg>\n",
+        w = width + 50.0,
+        h = height + 20.0,
+        vw = width + 50.0,
+        vh = height + 20.0,
+        max = max,
+        h0 = height,
+        width = width,
+        ty = threshold_y,
+        points = points.join(" "),
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { padding: 0.3em 0.8em; border-bottom: 1px solid #ddd; text-align: left; }
+th { cursor: pointer; background: #f4f4f4; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; }
+tr.over { color: #b00; }
+.chart { display: block; margin: 1em 0; font-size: 12px; }
+.chart rect { fill: #4a7ebb; }
+.chart polyline { fill: none; stroke: #4a7ebb; stroke-width: 2; }
+.chart .axis { stroke: #999; }
+.chart .threshold { stroke: #b00; stroke-dasharray: 4 4; }
+.note { color: #777; font-size: 0.9em; }
+";
+
+/// Sort a table by the clicked column, toggling the direction.
+const SCRIPT: &str = "
+document.querySelectorAll('table.sortable th').forEach((th, column) => {
+  th.addEventListener('click', () => {
+    const tbody = th.closest('table').querySelector('tbody');
+    const ascending = th.dataset.order !== 'asc';
+    th.dataset.order = ascending ? 'asc' : 'desc';
+    const key = row => {
+      const cell = row.children[column];
+      return cell.dataset.sort !== undefined ? parseFloat(cell.dataset.sort) : cell.textContent;
+    };
+    const rows = [...tbody.rows].sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      const order = typeof x === 'number' ? x - y : x.localeCompare(y);
+      return ascending ? order : -order;
+    });
+    rows.forEach(row => tbody.appendChild(row));
+  });
+});
+";
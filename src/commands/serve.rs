@@ -0,0 +1,186 @@
+//! `load serve`: sample continuously and answer queries about the machine
+//! over a small JSON HTTP API, for tooling on other hosts.
+
+use std::fs;
+use std::io;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Args;
+use loadrs::history::History;
+use loadrs::Sampler;
+use serde_json::json;
+
+use crate::http::{self, Latest, Request, Response};
+use crate::time::parse_time;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Address to serve the API on, e.g. 0.0.0.0:7879
+    #[arg(long, value_name = "ADDR")]
+    http: String,
+    /// Require `Authorization: Bearer <token>` on every request, with the
+    /// token read from this file
+    #[arg(long, value_name = "FILE")]
+    token_file: Option<PathBuf>,
+    /// Database written by --record, to answer /history from
+    #[arg(long, value_name = "DB")]
+    history: Option<PathBuf>,
+}
+
+/// What every request needs.
+struct State {
+    latest: Latest,
+    token: Option<String>,
+    history: Option<PathBuf>,
+}
+
+/// A response's status line and JSON body.
+type Reply = (&'static str, serde_json::Value);
+
+/// Sample every `interval` in the background, and serve:
+///
+/// - `GET /report`: the latest report
+/// - `GET /users/<name>`: one user's usage from the latest report
+/// - `GET /history?since=&until=&user=`: per-user usage summarized from
+///   the --history database, with times as `load history` takes them
+pub fn run(args: &ServeArgs, sampler: Sampler, interval: Duration) -> io::Result<()> {
+    let token = match &args.token_file {
+        Some(path) => {
+            let token = fs::read_to_string(path)?.trim().to_string();
+            if token.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} is empty", path.display()),
+                ));
+            }
+            Some(token)
+        }
+        None => None,
+    };
+    let listener = TcpListener::bind(&args.http)?;
+    let state = State {
+        latest: Latest::sample(sampler, interval),
+        token,
+        history: args.history.clone(),
+    };
+    eprintln!("Serving the API on http://{}", listener.local_addr()?);
+    http::serve(listener, move |request| respond(request, &state))
+}
+
+fn respond(request: &Request, state: &State) -> Response {
+    let (status, body) = if !authorized(state.token.as_deref(), request.header("authorization")) {
+        (
+            "401 Unauthorized",
+            json!({ "error": "missing or invalid token" }),
+        )
+    } else if request.method != "GET" {
+        (
+            "405 Method Not Allowed",
+            json!({ "error": "only GET is supported" }),
+        )
+    } else {
+        route(request, state)
+    };
+    let mut response = Response::new(status, "application/json", body.to_string());
+    if status.starts_with("401") {
+        response
+            .headers
+            .push(("WWW-Authenticate", "Bearer".to_string()));
+    }
+    response
+}
+
+fn route(request: &Request, state: &State) -> Reply {
+    let path = request.path();
+    if path == "/report" {
+        return ("200 OK", json!(*state.latest.get()));
+    }
+    if let Some(name) = path.strip_prefix("/users/") {
+        let name = decode(name);
+        let report = state.latest.get();
+        return match report.users.iter().find(|u| u.username == name) {
+            Some(user) => ("200 OK", json!(user)),
+            None => (
+                "404 Not Found",
+                json!({ "error": format!("no processes for user {}", name) }),
+            ),
+        };
+    }
+    if path == "/history" {
+        return history(request.query(), state)
+            .unwrap_or_else(|e| ("400 Bad Request", json!({ "error": e })));
+    }
+    (
+        "404 Not Found",
+        json!({ "error": format!("no such endpoint {}", path) }),
+    )
+}
+
+fn history(query: &str, state: &State) -> Result<Reply, String> {
+    let Some(path) = &state.history else {
+        return Ok((
+            "404 Not Found",
+            json!({ "error": "no history database (start with --history)" }),
+        ));
+    };
+    let mut users = Vec::new();
+    let (mut since, mut until) = (None, None);
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode(value);
+        match key {
+            "since" => since = Some(parse_time(&value)?),
+            "until" => until = Some(parse_time(&value)?),
+            "user" => users.extend(value.split(',').map(str::to_string)),
+            _ => return Err(format!("unknown parameter {}", key)),
+        }
+    }
+    let summaries = History::open(path)
+        .and_then(|history| history.summarize(&users, since, until))
+        .map_err(|e| format!("could not read history: {}", e))?;
+    Ok(("200 OK", json!(summaries)))
+}
+
+/// Whether the request's Authorization header carries the token, if one is
+/// required. Compares in constant time, so the token can't be guessed a
+/// byte at a time from response times.
+fn authorized(token: Option<&str>, authorization: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return true;
+    };
+    let Some(given) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Decode `%XX` escapes and `+` in a URL path segment or query value.
+fn decode(s: &str) -> String {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match byte {
+            b'%' if tail.len() >= 2 => {
+                match u8::from_str_radix(&String::from_utf8_lossy(&tail[..2]), 16) {
+                    Ok(decoded) => {
+                        bytes.push(decoded);
+                        rest = &tail[2..];
+                        continue;
+                    }
+                    Err(_) => bytes.push(byte),
+                }
+            }
+            b'+' => bytes.push(b' '),
+            _ => bytes.push(byte),
+        }
+        rest = tail;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
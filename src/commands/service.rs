@@ -0,0 +1,43 @@
+//! `load install-service`: print a systemd unit that runs `load daemon`.
+
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+
+use super::daemon::DEFAULT_SOCKET;
+
+#[derive(Args)]
+pub struct ServiceArgs {
+    /// Socket the service's daemon serves reports on
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_SOCKET)]
+    socket: PathBuf,
+}
+
+/// Print the unit, e.g. for `load install-service | sudo tee
+/// /etc/systemd/system/loadrs.service`. The daemon is run with `config`, if
+/// given, and logs with `--log`.
+pub fn run(args: &ServiceArgs, config: Option<&Path>) -> io::Result<()> {
+    let exe = env::current_exe()?;
+    let mut exec = format!("{} --log", exe.display());
+    if let Some(config) = config {
+        exec.push_str(&format!(" --config {}", config.display()));
+    }
+    exec.push_str(&format!(" daemon --socket {}", args.socket.display()));
+    print!(
+        "[Unit]
+Description=loadrs per-user CPU usage daemon
+After=network.target
+
+[Service]
+ExecStart={exec}
+Restart=on-failure
+RestartSec=5
+
+[Install]
+WantedBy=multi-user.target
+"
+    );
+    Ok(())
+}
@@ -0,0 +1,36 @@
+//! `load status`: print the latest report from a running `load daemon`.
+
+use std::io::{self, Read};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Args;
+use loadrs::LoadReport;
+
+use super::daemon::DEFAULT_SOCKET;
+
+#[derive(Args)]
+pub struct StatusArgs {
+    /// Socket the daemon is serving reports on
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_SOCKET)]
+    socket: PathBuf,
+}
+
+/// Fetch the daemon's latest report.
+pub fn run(args: &StatusArgs) -> io::Result<LoadReport> {
+    let mut stream = UnixStream::connect(&args.socket).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "could not connect to {} ({}); is `load daemon` running?",
+                args.socket.display(),
+                e
+            ),
+        )
+    })?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut body = String::new();
+    stream.read_to_string(&mut body)?;
+    serde_json::from_str(&body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,135 @@
+//! `load tree`: one user's processes as a tree, with each branch's CPU usage
+//! summed, to find which step of a pipeline is the hog.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+
+use clap::Args;
+use loadrs::{LoadReport, ProcessUsage};
+use prettytable::{Attr, Cell, Row};
+
+use crate::{format_bytes, format_duration, shorten, table};
+
+#[derive(Args)]
+pub struct TreeArgs {
+    /// User whose processes to show
+    #[arg(short, long)]
+    pub user: String,
+}
+
+/// A process and everything under it.
+struct Branch<'a> {
+    process: &'a ProcessUsage,
+    /// CPU usage of the process and all its descendants.
+    cpu: f64,
+    children: Vec<Branch<'a>>,
+}
+
+/// Print the user's processes from `report`, which must list all of them.
+pub fn run(args: &TreeArgs, report: &LoadReport) -> io::Result<()> {
+    let Some(user) = report.users.iter().find(|u| u.username == args.user) else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no processes for user {}", args.user),
+        ));
+    };
+
+    let pids: HashMap<u32, &ProcessUsage> = user.processes.iter().map(|p| (p.pid, p)).collect();
+    let mut children: HashMap<u32, Vec<&ProcessUsage>> = HashMap::new();
+    let mut roots = Vec::new();
+    for process in &user.processes {
+        // Processes whose parent belongs to someone else (e.g. sshd or
+        // slurmstepd) start their own trees.
+        match process.parent.filter(|parent| pids.contains_key(parent)) {
+            Some(parent) => children.entry(parent).or_default().push(process),
+            None => roots.push(process),
+        }
+    }
+    let mut roots: Vec<Branch> = roots
+        .into_iter()
+        .map(|root| branch(root, &children))
+        .collect();
+    roots.sort_by(busiest_first);
+
+    println!(
+        "{}: {} processes, {:.2} cores, {}",
+        user.username,
+        user.process_count,
+        user.equivalent_cores,
+        format_bytes(user.memory)
+    );
+    let mut table = table::new([
+        "PID",
+        "CPU Usage (%)",
+        "Branch CPU Usage (%)",
+        "Memory (RSS)",
+        "Runtime",
+        "Command",
+    ]);
+    for root in &roots {
+        add_rows(&mut table, root, "", "");
+    }
+    table::print(&table);
+    Ok(())
+}
+
+/// The branch under `process`, with children busiest first.
+fn branch<'a>(
+    process: &'a ProcessUsage,
+    children: &HashMap<u32, Vec<&'a ProcessUsage>>,
+) -> Branch<'a> {
+    let mut branches: Vec<Branch> = children
+        .get(&process.pid)
+        .into_iter()
+        .flatten()
+        .map(|child| branch(child, children))
+        .collect();
+    branches.sort_by(busiest_first);
+    Branch {
+        process,
+        cpu: process.cpu_usage + branches.iter().map(|b| b.cpu).sum::<f64>(),
+        children: branches,
+    }
+}
+
+/// Order branches by descending CPU usage, then by PID.
+fn busiest_first(a: &Branch, b: &Branch) -> Ordering {
+    b.cpu
+        .total_cmp(&a.cpu)
+        .then(a.process.pid.cmp(&b.process.pid))
+}
+
+/// Add a row for the branch's process, with its command drawn under its
+/// parent's (`prefix` for the process itself, `indent` for its children's
+/// lines), then rows for its children.
+fn add_rows(table: &mut prettytable::Table, branch: &Branch, prefix: &str, indent: &str) {
+    let process = branch.process;
+    let branch_cpu = Cell::new(&format!("{:.2}", branch.cpu));
+    table.add_row(Row::new(vec![
+        Cell::new(&process.pid.to_string()),
+        Cell::new(&format!("{:.2}", process.cpu_usage)),
+        if branch.children.is_empty() {
+            branch_cpu
+        } else {
+            branch_cpu.with_style(Attr::Bold)
+        },
+        Cell::new(&format_bytes(process.memory)),
+        Cell::new(&format_duration(process.run_time)),
+        Cell::new(&format!("{}{}", prefix, shorten(&process.command, 60))),
+    ]));
+    for (i, child) in branch.children.iter().enumerate() {
+        let last = i + 1 == branch.children.len();
+        let (branch_prefix, branch_indent) = if last {
+            ("└─ ", "   ")
+        } else {
+            ("├─ ", "│  ")
+        };
+        add_rows(
+            table,
+            child,
+            &format!("{}{}", indent, branch_prefix),
+            &format!("{}{}", indent, branch_indent),
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Defaults from a TOML config file, `~/.config/loadrs/config.toml` or the
+//! path given with `--config`.
+//!
+//! Any command line option can be set using its long name with underscores,
+//! e.g. `active_threshold = 5`, `format = "json"`, or `exempt_users = ["root"]`.
+//! Options sharing a prefix can also be grouped into a section, so
+//! `webhook = "..."` under `[alert]` sets `--alert-webhook`. A section named
+//! after an option that takes `NAME=VALUE` pairs is a table of those pairs,
+//! e.g. `alice = 2` under `[user_weights]`. Options given on the command line
+//! override the config file, which overrides the `--profile` preset.
+
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use clap::{Command, ValueEnum};
+use toml::Value;
+
+use crate::profile::Profile;
+
+/// Set the defaults of `cmd`'s arguments from the profile given with
+/// `--profile` or in the config file, and then from the config file, if any.
+pub fn apply(mut cmd: Command) -> Result<Command, String> {
+    let config = read()?;
+    let profile = match arg_value("--profile") {
+        Some(name) => Some(name.to_string_lossy().into_owned()),
+        None => config
+            .as_ref()
+            .and_then(|(_, table)| match table.get("profile") {
+                Some(Value::String(name)) => Some(name.clone()),
+                _ => None,
+            }),
+    };
+    if let Some(name) = profile {
+        let profile = Profile::from_str(&name, true).map_err(|_| {
+            let names: Vec<String> = Profile::value_variants().iter().map(|p| p.name()).collect();
+            format!(
+                "Unknown profile '{}' (expected one of {})",
+                name,
+                names.join(", ")
+            )
+        })?;
+        let table = profile
+            .defaults()
+            .parse()
+            .expect("Could not parse profile defaults.");
+        cmd = set_defaults(cmd, table, &format!("the {} profile", profile.name()))?;
+    }
+    if let Some((path, table)) = config {
+        cmd = set_defaults(cmd, table, &path.display().to_string())?;
+    }
+    Ok(cmd)
+}
+
+/// The config file's path and contents, if there is one.
+fn read() -> Result<Option<(PathBuf, toml::Table)>, String> {
+    let (path, required) = match arg_value("--config") {
+        Some(path) => (PathBuf::from(path), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(None),
+        },
+    };
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if !required && e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Could not read {}: {}", path.display(), e)),
+    };
+    let table = contents
+        .parse()
+        .map_err(|e| format!("Could not parse {}: {}", path.display(), e))?;
+    Ok(Some((path, table)))
+}
+
+/// Set the defaults of `cmd`'s arguments from `table`, which came from
+/// `source` (for error messages).
+fn set_defaults(mut cmd: Command, table: toml::Table, source: &str) -> Result<Command, String> {
+    let ids: HashSet<String> = cmd
+        .get_arguments()
+        .map(|arg| arg.get_id().to_string())
+        .filter(|id| id != "config")
+        .collect();
+    let known = |key: &str| ids.contains(key);
+    for (key, value) in flatten(table, None, &known) {
+        if !known(&key) {
+            return Err(format!("Unknown option '{}' in {}", key, source));
+        }
+        let values = match value {
+            Value::Array(items) => items.iter().map(scalar).collect::<Option<Vec<_>>>(),
+            value => scalar(&value).map(|v| vec![v]),
+        }
+        .ok_or_else(|| format!("Invalid value for '{}' in {}", key, source))?;
+        cmd = cmd.mut_arg(key, |arg| arg.default_values(values));
+    }
+    Ok(cmd)
+}
+
+/// Flatten sections into `section_key` option names. Sections that are
+/// themselves options become a list of `key=value` pairs.
+fn flatten(
+    table: toml::Table,
+    prefix: Option<&str>,
+    is_option: &dyn Fn(&str) -> bool,
+) -> Vec<(String, Value)> {
+    let mut options = Vec::new();
+    for (key, value) in table {
+        let key = match prefix {
+            Some(prefix) => format!("{}_{}", prefix, key),
+            None => key,
+        };
+        match value {
+            Value::Table(section) if is_option(&key) => {
+                let pairs = section
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let value = scalar(&value).unwrap_or_default();
+                        Value::String(format!("{}={}", name, value))
+                    })
+                    .collect();
+                options.push((key, Value::Array(pairs)));
+            }
+            Value::Table(section) => options.extend(flatten(section, Some(&key), is_option)),
+            value => options.push((key, value)),
+        }
+    }
+    options
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Float(f) => Some(f.to_string()),
+        Value::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Find the value of an option like `--config <path>` before the command
+/// line is parsed, since it determines the defaults the command line is
+/// parsed with.
+fn arg_value(name: &str) -> Option<OsString> {
+    let mut args = env::args_os().skip(1);
+    let prefix = format!("{}=", name);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == name {
+            return args.next();
+        }
+        if let Some(value) = arg.to_str().and_then(|a| a.strip_prefix(&prefix)) {
+            return Some(value.into());
+        }
+    }
+    None
+}
+
+fn default_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("loadrs").join("config.toml"))
+}
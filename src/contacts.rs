@@ -0,0 +1,117 @@
+//! Who users are and how to reach them, so admins don't have to look up who
+//! `xq291` is before pinging them.
+//!
+//! Contacts come from a TOML file with a table per user:
+//!
+//! ```toml
+//! [xq291]
+//! name = "Xiao Qing"
+//! email = "xq291@example.edu"
+//! slack = "@xqing"
+//! ```
+//!
+//! or from users' GECOS fields in the password database, whose first field
+//! is usually their full name, and which sometimes hold an email address.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::platform;
+
+/// How to reach a user. Every field is optional.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Contact {
+    /// Full name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Slack handle, e.g. `@xqing`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slack: Option<String>,
+}
+
+impl fmt::Display for Contact {
+    /// E.g. `Xiao Qing <xq291@example.edu>, @xqing`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let person = match (&self.name, &self.email) {
+            (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+            (name, email) => name.clone().or_else(|| email.clone()),
+        };
+        let parts: Vec<&str> = person
+            .iter()
+            .chain(&self.slack)
+            .map(String::as_str)
+            .collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Contacts by username, from a file, GECOS fields, or both.
+#[derive(Debug, Clone, Default)]
+pub struct Contacts {
+    pub users: HashMap<String, Contact>,
+    /// Whether to read users missing from `users` from their GECOS fields.
+    pub gecos: bool,
+}
+
+impl Contacts {
+    /// Read a contacts file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        Self::parse(&fs::read_to_string(path)?).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let users = toml::from_str(contents).map_err(|e| e.to_string())?;
+        Ok(Self {
+            users,
+            gecos: false,
+        })
+    }
+
+    /// A user's contact, from the file if they're in it, or else their GECOS
+    /// field.
+    pub fn lookup(&self, username: &str) -> Option<Contact> {
+        if let Some(contact) = self.users.get(username) {
+            return Some(contact.clone());
+        }
+        if !self.gecos {
+            return None;
+        }
+        platform::gecos(username).and_then(|gecos| parse_gecos(&gecos))
+    }
+}
+
+/// The full name and email address in a GECOS field, whose fields are
+/// separated by commas: full name, room, work phone, home phone, and other.
+fn parse_gecos(gecos: &str) -> Option<Contact> {
+    let name = gecos
+        .split(',')
+        .next()
+        .map(str::trim)
+        .filter(|name| !name.is_empty() && !name.contains('@'))
+        .map(str::to_string);
+    let email = gecos
+        .split(',')
+        .map(str::trim)
+        .find(|field| field.contains('@') && !field.contains(char::is_whitespace))
+        .map(str::to_string);
+    let contact = Contact {
+        name,
+        email,
+        slack: None,
+    };
+    (contact != Contact::default()).then_some(contact)
+}
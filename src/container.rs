@@ -0,0 +1,58 @@
+//! Attributing processes to containers, from their cgroups.
+
+use std::fs;
+
+/// The container a process runs in, if any, like `docker:0123456789ab` or
+/// `lxc:web`.
+///
+/// Container runtimes put each container's processes in its own cgroup, like
+/// `/system.slice/docker-<id>.scope` or `/docker/<id>` (Docker),
+/// `/machine.slice/libpod-<id>.scope` (Podman), `cri-containerd-<id>.scope`
+/// (containerd, under Kubernetes), or `/lxc.payload.<name>` or `/lxc/<name>`
+/// (LXC), so the container can be read from `/proc/<pid>/cgroup` without
+/// asking the runtime. Container IDs are shortened to 12 characters, as
+/// `docker ps` shows them.
+pub fn container(pid: u32) -> Option<String> {
+    let cgroup = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    cgroup.lines().find_map(|line| {
+        // Each line is `hierarchy-ID:controllers:path`.
+        let path = line.splitn(3, ':').nth(2)?;
+        let mut parts = path.split('/');
+        while let Some(part) = parts.next() {
+            let scope = part.strip_suffix(".scope").unwrap_or(part);
+            for (prefix, runtime) in [
+                ("docker-", "docker"),
+                ("libpod-", "podman"),
+                ("cri-containerd-", "containerd"),
+                ("crio-", "cri-o"),
+            ] {
+                if let Some(id) = scope.strip_prefix(prefix).filter(|id| is_id(id)) {
+                    return Some(format!("{}:{}", runtime, &id[..12]));
+                }
+            }
+            if let Some(name) = part.strip_prefix("lxc.payload.") {
+                return Some(format!("lxc:{}", name));
+            }
+            match part {
+                "docker" => {
+                    if let Some(id) = parts.clone().next().filter(|id| is_id(id)) {
+                        return Some(format!("docker:{}", &id[..12]));
+                    }
+                }
+                "lxc" => {
+                    if let Some(name) = parts.clone().next().filter(|name| !name.is_empty()) {
+                        return Some(format!("lxc:{}", name));
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    })
+}
+
+/// Whether `s` looks like a container ID (64 hex digits), rather than e.g.
+/// the `libpod-conmon-<id>` scope of Podman's monitor process.
+fn is_id(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
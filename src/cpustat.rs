@@ -0,0 +1,54 @@
+//! System-wide CPU time spent waiting on I/O and stolen by the hypervisor,
+//! read from `/proc/stat` (Linux only).
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+/// Cumulative CPU time counters, in clock ticks.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Counters {
+    total: u64,
+    iowait: u64,
+    steal: u64,
+}
+
+/// Percent of CPU time between two samples that was idle waiting on I/O or
+/// stolen by the hypervisor.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CpuWait {
+    /// Idle with I/O outstanding, e.g. on a slow or failing disk.
+    pub iowait: f64,
+    /// Taken by the hypervisor for other virtual machines.
+    pub steal: f64,
+}
+
+/// Read the aggregate `cpu` line of `/proc/stat`, whose fields are user,
+/// nice, system, idle, iowait, irq, softirq, steal, guest, and guest_nice.
+pub(crate) fn counters() -> Option<Counters> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    let fields: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .map(|field| field.parse().unwrap_or(0))
+        .collect();
+    // Guest time is already included in user and nice time.
+    Some(Counters {
+        total: fields.iter().take(8).sum(),
+        iowait: *fields.get(4)?,
+        steal: fields.get(7).copied().unwrap_or(0),
+    })
+}
+
+/// The wait between two readings of the counters.
+pub(crate) fn wait(previous: Counters, current: Counters) -> Option<CpuWait> {
+    let total = current
+        .total
+        .checked_sub(previous.total)
+        .filter(|t| *t > 0)? as f64;
+    Some(CpuWait {
+        iowait: 100.0 * current.iowait.saturating_sub(previous.iowait) as f64 / total,
+        steal: 100.0 * current.steal.saturating_sub(previous.steal) as f64 / total,
+    })
+}
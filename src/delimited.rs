@@ -0,0 +1,101 @@
+//! CSV and TSV output, with one row per user per sample.
+
+use std::io;
+
+use loadrs::LoadReport;
+
+/// The column schema. Append new columns at the end, since scripts and
+/// dataframes loading old logs may rely on column positions.
+const HEADER: [&str; 29] = [
+    "timestamp",
+    "username",
+    "total_cpu_usage",
+    "equivalent_cores",
+    "system_cpu_share",
+    "memory_bytes",
+    "fair_share",
+    "over_fair_share",
+    "active_users",
+    "total_cores",
+    "load_average_1m",
+    "load_average_5m",
+    "load_average_15m",
+    "gpu_utilization",
+    "gpu_memory_bytes",
+    "disk_read_bytes",
+    "disk_written_bytes",
+    "seconds_over_fair_share",
+    "memory_share",
+    "raw_cpu_usage",
+    "processes",
+    "threads",
+    "cpu_seconds",
+    "cpu_time_seconds",
+    "net_sent_bytes",
+    "net_received_bytes",
+    "zombies",
+    "swap_bytes",
+    "open_fds",
+];
+
+/// Write a report's users to stdout, separated by `delimiter`.
+pub fn print(report: &LoadReport, delimiter: u8, header: bool) -> csv::Result<()> {
+    write(io::stdout().lock(), report, delimiter, header)
+}
+
+/// Write a report's users to `out`, separated by `delimiter`.
+pub fn write(
+    out: impl io::Write,
+    report: &LoadReport,
+    delimiter: u8,
+    header: bool,
+) -> csv::Result<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(out);
+    if header {
+        writer.write_record(HEADER)?;
+    }
+    for user in &report.users {
+        writer.write_record([
+            report.timestamp.to_string(),
+            user.username.clone(),
+            format!("{:.2}", user.total_cpu_usage),
+            format!("{:.2}", user.equivalent_cores),
+            format!("{:.2}", user.system_cpu_share),
+            user.memory.to_string(),
+            format!("{:.2}", user.fair_share),
+            (user.system_cpu_share > user.fair_share).to_string(),
+            report.fair_share.active_users.to_string(),
+            report.total_cores.to_string(),
+            format!("{:.2}", report.load_average.one),
+            format!("{:.2}", report.load_average.five),
+            format!("{:.2}", report.load_average.fifteen),
+            user.gpu
+                .map_or(String::new(), |gpu| format!("{:.2}", gpu.utilization)),
+            user.gpu.map_or(String::new(), |gpu| gpu.memory.to_string()),
+            user.disk
+                .map_or(String::new(), |disk| disk.read_bytes.to_string()),
+            user.disk
+                .map_or(String::new(), |disk| disk.written_bytes.to_string()),
+            user.time_over_fair_share
+                .map_or(String::new(), |t| t.to_string()),
+            format!("{:.2}", user.memory_share),
+            user.raw_cpu_usage
+                .map_or(String::new(), |cpu| format!("{:.2}", cpu)),
+            user.process_count.to_string(),
+            user.thread_count.to_string(),
+            format!("{:.2}", user.cpu_seconds),
+            user.cpu_time.map_or(String::new(), |t| format!("{:.2}", t)),
+            user.net
+                .map_or(String::new(), |net| net.sent_bytes.to_string()),
+            user.net
+                .map_or(String::new(), |net| net.received_bytes.to_string()),
+            user.zombies.to_string(),
+            user.swap.map_or(String::new(), |swap| swap.to_string()),
+            user.fds.map_or(String::new(), |fds| fds.to_string()),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
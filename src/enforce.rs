@@ -0,0 +1,234 @@
+//! Lowering the priority of processes of users over their fair share, with
+//! `--suggest-renice` and `--enforce`, or capping their CPU with
+//! `--enforce-cgroup`.
+
+use std::collections::HashMap;
+use std::io;
+use std::process::Command;
+
+use loadrs::nice::priority;
+use loadrs::platform;
+use loadrs::{LoadReport, ProcessUsage};
+
+/// An offender's process to lower the priority of.
+pub struct Target<'a> {
+    pub username: &'a str,
+    pub process: &'a ProcessUsage,
+}
+
+/// The busiest processes of each offender (as many as the sampler kept).
+pub fn targets(report: &LoadReport) -> Vec<Target<'_>> {
+    report
+        .offenders
+        .iter()
+        .filter_map(|offender| {
+            report
+                .users
+                .iter()
+                .find(|u| u.username == offender.username)
+        })
+        .flat_map(|user| {
+            user.processes
+                .iter()
+                .filter(|p| p.cpu_usage > 0.0)
+                .map(move |process| Target {
+                    username: &user.username,
+                    process,
+                })
+        })
+        .collect()
+}
+
+/// Heavy processes below the batch nice value, from
+/// [`Sampler::batch_nice`](loadrs::Sampler::batch_nice).
+pub fn unniced(report: &LoadReport) -> Vec<Target<'_>> {
+    report
+        .unniced
+        .iter()
+        .flat_map(|user| {
+            user.processes.iter().map(move |process| Target {
+                username: &user.username,
+                process,
+            })
+        })
+        .collect()
+}
+
+/// Print the `renice` and `ionice` commands that would lower each target's
+/// CPU and I/O priority, under a heading.
+pub fn suggest(heading: &str, targets: &[Target], nice: i32) {
+    println!("\n{}", heading);
+    for target in targets {
+        println!(
+            "  renice -n {} -p {}  # {}: {} ({:.0}% CPU)",
+            nice,
+            target.process.pid,
+            target.username,
+            crate::shorten(&target.process.command, 40),
+            target.process.cpu_usage
+        );
+        println!("  ionice -c 3 -p {}", target.process.pid);
+    }
+}
+
+/// Renice each target to `nice`, unless it is already at least that nice,
+/// reporting what was done on stderr.
+pub fn enforce(targets: &[Target], nice: i32) {
+    for target in targets {
+        let pid = target.process.pid;
+        match priority(pid) {
+            Ok(current) if current >= nice => continue,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Error reading priority of PID {}: {}", pid, e);
+                continue;
+            }
+        }
+        match set_priority(pid, nice) {
+            Ok(()) => eprintln!(
+                "Reniced PID {} ({}: {}) to {}",
+                pid,
+                target.username,
+                crate::shorten(&target.process.command, 40),
+                nice
+            ),
+            Err(e) => eprintln!("Error renicing PID {}: {}", pid, e),
+        }
+    }
+}
+
+/// Once a limited user's share is below this fraction of their fair share,
+/// they have stopped pushing against their limit, and it is lifted.
+const LIFT_BELOW: f64 = 0.8;
+
+/// How far, as a fraction of the limit, a limited user's fair share can move
+/// before their limit is updated to match.
+const RESET_ABOVE: f64 = 0.1;
+
+/// Caps the CPU of users over their fair share at their fair share, by
+/// setting `CPUQuota` on their systemd user slice, and lifts the cap once
+/// they fall back under it. Limits are set with `--runtime`, so they don't
+/// outlive a reboot, and [`Throttler::release`] lifts any left on exit.
+#[derive(Default)]
+pub struct Throttler {
+    /// Each limited user's UID and limit, in cores.
+    limited: HashMap<String, (u32, f64)>,
+}
+
+impl Throttler {
+    /// Limit new offenders, and update or lift the limits of users already
+    /// limited, reporting what was done on stderr.
+    pub fn update(&mut self, report: &LoadReport) {
+        let cores = report.total_cores as f64;
+        let user = |username: &str| {
+            report
+                .users
+                .iter()
+                .find(|u| u.username == username && u.fair_share.is_finite())
+        };
+
+        let limited: Vec<String> = self.limited.keys().cloned().collect();
+        for username in limited {
+            let (uid, limit) = self.limited[&username];
+            match user(&username) {
+                Some(u) if u.system_cpu_share >= LIFT_BELOW * u.fair_share => {
+                    let fair = u.fair_share / 100.0 * cores;
+                    if (fair - limit).abs() > RESET_ABOVE * limit {
+                        self.limit(&username, uid, fair);
+                    }
+                }
+                _ => self.lift(&username),
+            }
+        }
+
+        for offender in &report.offenders {
+            if self.limited.contains_key(&offender.username) {
+                continue;
+            }
+            let fair = offender.fair_share / 100.0 * cores;
+            match platform::uid(&offender.username) {
+                Some(uid) => self.limit(&offender.username, uid, fair),
+                None => eprintln!("Error limiting {}: unknown user", offender.username),
+            }
+        }
+    }
+
+    /// Lift every limit set, e.g. on exit.
+    pub fn release(&mut self) {
+        let limited: Vec<String> = self.limited.keys().cloned().collect();
+        for username in limited {
+            self.lift(&username);
+        }
+    }
+
+    fn limit(&mut self, username: &str, uid: u32, cores: f64) {
+        // systemd's smallest quota is 1% of a core.
+        let percent = (100.0 * cores).round().max(1.0);
+        match set_quota(uid, &format!("{}%", percent)) {
+            Ok(()) => {
+                eprintln!(
+                    "Limited {} (UID {}) to {:.2} cores",
+                    username,
+                    uid,
+                    percent / 100.0
+                );
+                self.limited.insert(username.to_string(), (uid, cores));
+            }
+            Err(e) => eprintln!("Error limiting {}: {}", username, e),
+        }
+    }
+
+    fn lift(&mut self, username: &str) {
+        let Some((uid, _)) = self.limited.remove(username) else {
+            return;
+        };
+        match set_quota(uid, "") {
+            Ok(()) => eprintln!("Lifted CPU limit of {} (UID {})", username, uid),
+            Err(e) => eprintln!("Error lifting CPU limit of {}: {}", username, e),
+        }
+    }
+}
+
+/// Set the `CPUQuota` of a user's slice until the next reboot, e.g. to
+/// `250%`, or remove it with an empty quota.
+fn set_quota(uid: u32, quota: &str) -> io::Result<()> {
+    let output = Command::new("systemctl")
+        .args(["set-property", "--runtime"])
+        .arg(format!("user-{}.slice", uid))
+        .arg(format!("CPUQuota={}", quota))
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Whether we can renice other users' processes.
+#[cfg(unix)]
+pub fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Windows has no nice values, so processes are never reniced.
+#[cfg(not(unix))]
+pub fn is_root() -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn set_priority(pid: u32, nice: i32) -> io::Result<()> {
+    // SAFETY: setpriority only reads its arguments.
+    match unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, nice) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_priority(_pid: u32, _nice: i32) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
@@ -0,0 +1,66 @@
+//! Errors that stop `load`, with what it was doing when they happened.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Something failed while `load` was doing what `context` says.
+    Failed {
+        context: String,
+        source: Box<dyn StdError + Send + Sync>,
+    },
+    /// The command line or a file it names asks for something `load` can't
+    /// do, e.g. options that don't work together.
+    Usage(String),
+}
+
+impl Error {
+    pub fn usage(message: impl Into<String>) -> Self {
+        Self::Usage(message.into())
+    }
+
+    /// The code to exit with: 2 for usage errors, as clap exits with for
+    /// its own, and 1 for failures.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Failed { .. } => 1,
+            Self::Usage(_) => 2,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Failed { context, source } => write!(f, "{}: {}", context, source),
+            Self::Usage(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Failed { source, .. } => Some(&**source),
+            Self::Usage(_) => None,
+        }
+    }
+}
+
+/// Attach what was being done to an error, e.g. "Could not read history".
+pub trait Context<T> {
+    fn context(self, context: &str) -> Result<T, Error>;
+}
+
+impl<T, E> Context<T> for Result<T, E>
+where
+    E: Into<Box<dyn StdError + Send + Sync>>,
+{
+    fn context(self, context: &str) -> Result<T, Error> {
+        self.map_err(|e| Error::Failed {
+            context: context.to_string(),
+            source: e.into(),
+        })
+    }
+}
@@ -0,0 +1,106 @@
+//! Long-running fair-share scores, from each user's CPU time decayed with a
+//! half-life, as Slurm's multifactor priority plugin computes them.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::report::LoadReport;
+
+/// Each user's decayed CPU time, persisted between runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ledger {
+    /// When usage was last decayed and added to, in seconds since the Unix
+    /// epoch.
+    pub updated: u64,
+    /// Decayed CPU time per user, in seconds.
+    pub usage: HashMap<String, f64>,
+}
+
+/// A user's standing in the [`Ledger`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Standing {
+    pub username: String,
+    /// Decayed CPU time, in seconds.
+    pub usage: f64,
+    /// Fraction of everyone's decayed CPU time.
+    pub usage_share: f64,
+    /// `2^(-usage_share / share)`, where every user has an equal share: 1
+    /// for no usage, 0.5 for exactly a fair share, and towards 0 the more a
+    /// user has used.
+    pub score: f64,
+}
+
+impl Ledger {
+    /// Read a ledger, or start an empty one if `path` doesn't exist.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string(self).expect("Could not serialize ledger.");
+        fs::write(path, contents)
+    }
+
+    /// Decay everyone's usage to `now`, halving it every `half_life`
+    /// seconds.
+    pub fn decay(&mut self, now: u64, half_life: u64) {
+        if self.updated > 0 && now > self.updated {
+            let factor = 0.5f64.powf((now - self.updated) as f64 / half_life.max(1) as f64);
+            for usage in self.usage.values_mut() {
+                *usage *= factor;
+            }
+            // Forget users whose usage has decayed to nothing.
+            self.usage.retain(|_, usage| *usage >= 0.001);
+        }
+        self.updated = self.updated.max(now);
+    }
+
+    /// Add a sample's CPU time, and set each of its users' scores.
+    pub fn record(&mut self, report: &mut LoadReport, half_life: u64) {
+        self.decay(report.timestamp, half_life);
+        for user in report.users.iter().filter(|u| u.cpu_seconds > 0.0) {
+            *self.usage.entry(user.username.clone()).or_default() += user.cpu_seconds;
+        }
+        for user in &mut report.users {
+            user.fairshare_score = Some(self.standing(&user.username).score);
+        }
+    }
+
+    /// A user's standing, who needn't be in the ledger.
+    pub fn standing(&self, username: &str) -> Standing {
+        let total: f64 = self.usage.values().sum();
+        let usage = self.usage.get(username).copied().unwrap_or(0.0);
+        let usage_share = if total > 0.0 { usage / total } else { 0.0 };
+        let share = 1.0 / self.usage.len().max(1) as f64;
+        Standing {
+            username: username.to_string(),
+            usage,
+            usage_share,
+            score: 2f64.powf(-usage_share / share),
+        }
+    }
+
+    /// Every user's standing, lowest score (heaviest usage) first.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self
+            .usage
+            .keys()
+            .map(|username| self.standing(username))
+            .collect();
+        standings.sort_by(|a, b| a.score.total_cmp(&b.score));
+        standings
+    }
+}
@@ -0,0 +1,50 @@
+//! Open file descriptors, per process and system-wide, read from `/proc`
+//! (Linux only).
+//!
+//! One user holding hundreds of thousands of descriptors (often on NFS) can
+//! exhaust the kernel's file table for everyone, at no CPU or memory cost.
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+/// The kernel's file table, from `/proc/sys/fs/file-nr`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FileHandles {
+    /// File handles in use.
+    pub allocated: u64,
+    /// Most file handles the kernel will allocate (`fs.file-max`).
+    pub max: u64,
+}
+
+impl FileHandles {
+    /// Percent of the file table in use.
+    pub fn used_percent(&self) -> f64 {
+        100.0 * self.allocated as f64 / self.max.max(1) as f64
+    }
+}
+
+/// How many descriptors a process has open, or `None` if they can't be
+/// listed (e.g. off Linux, or another user's process without root).
+pub fn open(pid: u32) -> Option<u64> {
+    let entries = fs::read_dir(format!("/proc/{}/fd", pid)).ok()?;
+    Some(entries.count() as u64)
+}
+
+/// The system's file handle usage and limit.
+pub fn file_handles() -> Option<FileHandles> {
+    let file_nr = fs::read_to_string("/proc/sys/fs/file-nr").ok()?;
+    // e.g. "2496	0	9223372036854775807": allocated, allocated but
+    // unused (always 0 since Linux 2.6), and the maximum.
+    let fields: Vec<u64> = file_nr
+        .split_whitespace()
+        .filter_map(|field| field.parse().ok())
+        .collect();
+    let [allocated, unused, max] = fields[..] else {
+        return None;
+    };
+    Some(FileHandles {
+        allocated: allocated.saturating_sub(unused),
+        max,
+    })
+}
@@ -0,0 +1,76 @@
+//! Per-process NVIDIA GPU usage, read from `nvidia-smi`.
+
+use std::collections::HashMap;
+use std::io;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+/// GPU usage of a process, or summed over a user's processes.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct GpuUsage {
+    /// Streaming multiprocessor utilization summed over GPUs, where 100% is
+    /// one fully used GPU.
+    pub utilization: f64,
+    /// GPU memory used, in bytes.
+    pub memory: u64,
+}
+
+impl std::ops::AddAssign for GpuUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.utilization += other.utilization;
+        self.memory += other.memory;
+    }
+}
+
+/// GPU usage of every process using an NVIDIA GPU, by PID.
+pub fn processes() -> io::Result<HashMap<u32, GpuUsage>> {
+    let mut usage: HashMap<u32, GpuUsage> = HashMap::new();
+
+    // Memory per process and GPU, e.g. "12345, 2048" (MiB).
+    let apps = nvidia_smi(&[
+        "--query-compute-apps=pid,used_memory",
+        "--format=csv,noheader,nounits",
+    ])?;
+    for line in apps.lines() {
+        let mut fields = line.split(',').map(str::trim);
+        let (Some(pid), Some(memory)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if let (Ok(pid), Ok(mib)) = (pid.parse(), memory.parse::<u64>()) {
+            usage.entry(pid).or_default().memory += mib * 1024 * 1024;
+        }
+    }
+
+    // Utilization per process and GPU, with columns
+    // "gpu pid type sm mem enc dec ... command". Idle values are "-".
+    let pmon = nvidia_smi(&["pmon", "--count", "1", "--select", "u"])?;
+    for line in pmon.lines().filter(|line| !line.starts_with('#')) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 4 {
+            continue;
+        }
+        if let (Ok(pid), Ok(sm)) = (fields[1].parse(), fields[3].parse::<f64>()) {
+            usage.entry(pid).or_default().utilization += sm;
+        }
+    }
+
+    Ok(usage)
+}
+
+fn nvidia_smi(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("nvidia-smi")
+        .args(args)
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), "nvidia-smi not found"),
+            _ => e,
+        })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "nvidia-smi failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,174 @@
+//! `--graphite`: push each sample's metrics over TCP in Graphite's plaintext
+//! protocol, for monitoring stacks built on Graphite or Carbon.
+
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use loadrs::LoadReport;
+
+use crate::statsd::segment;
+
+/// How long to wait connecting or sending before giving up on a sample.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Graphite {
+    addr: String,
+    prefix: String,
+    host: String,
+    /// Kept open between samples, and reconnected when it breaks, e.g. when
+    /// Carbon restarts.
+    stream: Option<TcpStream>,
+}
+
+impl Graphite {
+    /// A client sending to the Carbon server at `addr`, e.g.
+    /// `localhost:2003`, with metric paths under `prefix`.
+    pub fn connect(addr: &str, prefix: &str) -> io::Result<Self> {
+        let mut graphite = Self {
+            addr: addr.to_string(),
+            prefix: prefix.trim_matches('.').to_string(),
+            host: segment(&sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string())),
+            stream: None,
+        };
+        graphite.stream = Some(graphite.open()?);
+        Ok(graphite)
+    }
+
+    fn open(&self) -> io::Result<TcpStream> {
+        let mut last_error = None;
+        for addr in self.addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_write_timeout(Some(TIMEOUT))?;
+                    return Ok(stream);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no address for {}", self.addr),
+            )
+        }))
+    }
+
+    /// Send the report's metrics: the load averages, fair share, active
+    /// users and offender count, and each user's CPU share, cores, and
+    /// memory, e.g. `loadrs.node1.user.alice.cores 2.5 1718000000`.
+    pub fn send(&mut self, report: &LoadReport) -> io::Result<()> {
+        let lines = self.lines(report);
+        // Reconnect once if the connection broke since the last sample.
+        let stream = match self.stream.take() {
+            Some(stream) => stream,
+            None => self.open()?,
+        };
+        let stream = match send(stream, &lines) {
+            Ok(stream) => stream,
+            Err(_) => send(self.open()?, &lines)?,
+        };
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    /// The report's metric lines, each ending with a newline.
+    fn lines(&self, report: &LoadReport) -> String {
+        let load = &report.load_average;
+        let mut lines = String::new();
+        for (name, value) in [
+            ("load.1m", load.one),
+            ("load.5m", load.five),
+            ("load.15m", load.fifteen),
+            ("cores", report.total_cores as f64),
+            ("fair_share", report.fair_share.value),
+            ("active_users", report.fair_share.active_users as f64),
+            ("offenders", report.offenders.len() as f64),
+        ] {
+            self.line(&mut lines, name, value, report.timestamp);
+        }
+        for user in &report.users {
+            let user_path = format!("user.{}", segment(&user.username));
+            for (name, value) in [
+                ("cpu_share", user.system_cpu_share),
+                ("cores", user.equivalent_cores),
+                ("memory_bytes", user.memory as f64),
+                ("memory_share", user.memory_share),
+            ] {
+                let name = format!("{}.{}", user_path, name);
+                self.line(&mut lines, &name, value, report.timestamp);
+            }
+        }
+        lines
+    }
+
+    /// Append a metric's line, skipping values Graphite can't represent,
+    /// like an infinite fair share.
+    fn line(&self, lines: &mut String, name: &str, value: f64, timestamp: u64) {
+        if !value.is_finite() {
+            return;
+        }
+        // Without float noise like 14.010000000000014.
+        let value = (value * 1e4).round() / 1e4;
+        let path = [self.prefix.as_str(), &self.host, name]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(".");
+        lines.push_str(&format!("{} {} {}\n", path, value, timestamp));
+    }
+}
+
+fn send(mut stream: TcpStream, lines: &str) -> io::Result<TcpStream> {
+    stream.write_all(lines.as_bytes())?;
+    stream.flush()?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::example_report;
+
+    fn graphite(prefix: &str) -> Graphite {
+        Graphite {
+            addr: "localhost:2003".to_string(),
+            prefix: prefix.to_string(),
+            host: segment("node1.example.com"),
+            stream: None,
+        }
+    }
+
+    #[test]
+    fn lines_have_paths_values_and_timestamps() {
+        assert_eq!(
+            graphite("site").lines(&example_report()),
+            "\
+site.node1_example_com.load.1m 2 1718000000
+site.node1_example_com.load.5m 2 1718000000
+site.node1_example_com.load.15m 2 1718000000
+site.node1_example_com.cores 4 1718000000
+site.node1_example_com.fair_share 50 1718000000
+site.node1_example_com.active_users 2 1718000000
+site.node1_example_com.offenders 0 1718000000
+site.node1_example_com.user.alice_smith.cpu_share 37.5 1718000000
+site.node1_example_com.user.alice_smith.cores 1.5 1718000000
+site.node1_example_com.user.alice_smith.memory_bytes 1073741824 1718000000
+site.node1_example_com.user.alice_smith.memory_share 6.25 1718000000
+site.node1_example_com.user.o_brien.cpu_share 12.5 1718000000
+site.node1_example_com.user.o_brien.cores 0.5 1718000000
+site.node1_example_com.user.o_brien.memory_bytes 536870912 1718000000
+site.node1_example_com.user.o_brien.memory_share 3.125 1718000000
+"
+        );
+    }
+
+    #[test]
+    fn lines_skip_an_empty_prefix_and_unrepresentable_values() {
+        let graphite = graphite("");
+        let mut lines = String::new();
+        graphite.line(&mut lines, "fair_share", f64::INFINITY, 1718000000);
+        graphite.line(&mut lines, "fair_share", 100.0 / 3.0, 1718000000);
+        assert_eq!(lines, "node1_example_com.fair_share 33.3333 1718000000\n");
+    }
+}
@@ -0,0 +1,241 @@
+//! Recording samples to a SQLite database and summarizing them later.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::report::LoadReport;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS samples (
+    id INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    total_cores INTEGER NOT NULL,
+    fair_share REAL,
+    load_average_1m REAL NOT NULL,
+    load_average_5m REAL NOT NULL,
+    load_average_15m REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS samples_timestamp ON samples (timestamp);
+CREATE TABLE IF NOT EXISTS user_samples (
+    sample_id INTEGER NOT NULL REFERENCES samples (id),
+    username TEXT NOT NULL,
+    total_cpu_usage REAL NOT NULL,
+    equivalent_cores REAL NOT NULL,
+    system_cpu_share REAL NOT NULL,
+    memory INTEGER NOT NULL,
+    fair_share REAL,
+    cpu_seconds REAL
+);
+CREATE INDEX IF NOT EXISTS user_samples_sample_id ON user_samples (sample_id);
+CREATE INDEX IF NOT EXISTS user_samples_username ON user_samples (username);
+";
+
+/// A user's usage summarized over a range of recorded samples.
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageSummary {
+    pub username: String,
+    /// Number of samples the user appears in.
+    pub samples: u64,
+    pub mean_cores: f64,
+    pub peak_cores: f64,
+    pub mean_cpu_share: f64,
+    /// Peak resident memory, in bytes.
+    pub peak_memory: u64,
+    /// Total CPU time over the samples, in hours (from samples recorded
+    /// since CPU time was recorded).
+    pub cpu_hours: f64,
+    /// Number of samples in which the user exceeded the fair share.
+    pub samples_over_fair_share: u64,
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+/// One user's usage in one recorded sample.
+#[derive(Debug, Clone)]
+pub struct UserSample {
+    pub timestamp: u64,
+    pub username: String,
+    pub equivalent_cores: f64,
+    pub system_cpu_share: f64,
+    /// Resident memory, in bytes.
+    pub memory: u64,
+    /// The user's fair share, or `None` if there were no active users.
+    pub fair_share: Option<f64>,
+}
+
+/// A SQLite database of recorded samples.
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    /// Open (or create) the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        migrate(&conn)?;
+        Ok(Self { conn })
+    }
+
+    /// Append a sample.
+    pub fn record(&mut self, report: &LoadReport) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO samples (timestamp, total_cores, fair_share, load_average_1m, load_average_5m, load_average_15m)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                report.timestamp as i64,
+                report.total_cores as i64,
+                // SQLite has no infinity, so store no active users as NULL.
+                Some(report.fair_share.value).filter(|v| v.is_finite()),
+                report.load_average.one,
+                report.load_average.five,
+                report.load_average.fifteen,
+            ],
+        )?;
+        let sample_id = tx.last_insert_rowid();
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO user_samples (sample_id, username, total_cpu_usage, equivalent_cores, system_cpu_share, memory, fair_share, cpu_seconds)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for user in &report.users {
+                insert.execute(params![
+                    sample_id,
+                    user.username,
+                    user.total_cpu_usage,
+                    user.equivalent_cores,
+                    user.system_cpu_share,
+                    user.memory as i64,
+                    Some(user.fair_share).filter(|v| v.is_finite()),
+                    user.cpu_seconds,
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Summarize each user's usage between `since` and `until` (inclusive,
+    /// in seconds since the Unix epoch), optionally only for `users`.
+    /// Summaries are sorted by descending mean cores.
+    pub fn summarize(
+        &self,
+        users: &[String],
+        since: Option<u64>,
+        until: Option<u64>,
+    ) -> rusqlite::Result<Vec<UsageSummary>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT u.username, COUNT(*), AVG(u.equivalent_cores), MAX(u.equivalent_cores),
+                    AVG(u.system_cpu_share), MAX(u.memory),
+                    SUM(COALESCE(u.fair_share, s.fair_share) IS NOT NULL
+                        AND u.system_cpu_share > COALESCE(u.fair_share, s.fair_share)),
+                    MIN(s.timestamp), MAX(s.timestamp), TOTAL(u.cpu_seconds) / 3600
+             FROM user_samples u JOIN samples s ON s.id = u.sample_id
+             WHERE s.timestamp >= ?1 AND s.timestamp <= ?2
+             GROUP BY u.username
+             ORDER BY AVG(u.equivalent_cores) DESC",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                since.unwrap_or(0) as i64,
+                until.map_or(i64::MAX, |t| t as i64)
+            ],
+            |row| {
+                Ok(UsageSummary {
+                    username: row.get(0)?,
+                    samples: row.get::<_, i64>(1)? as u64,
+                    mean_cores: row.get(2)?,
+                    peak_cores: row.get(3)?,
+                    mean_cpu_share: row.get(4)?,
+                    peak_memory: row.get::<_, i64>(5)? as u64,
+                    samples_over_fair_share: row.get::<_, i64>(6)? as u64,
+                    first_seen: row.get::<_, i64>(7)? as u64,
+                    last_seen: row.get::<_, i64>(8)? as u64,
+                    cpu_hours: row.get(9)?,
+                })
+            },
+        )?;
+        rows.filter(|summary| {
+            summary
+                .as_ref()
+                .map_or(true, |s| users.is_empty() || users.contains(&s.username))
+        })
+        .collect()
+    }
+
+    /// Every user's usage in the samples between `since` and `until`
+    /// (inclusive, in seconds since the Unix epoch), in order of time.
+    pub fn user_samples(
+        &self,
+        since: Option<u64>,
+        until: Option<u64>,
+    ) -> rusqlite::Result<Vec<UserSample>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.timestamp, u.username, u.equivalent_cores, u.system_cpu_share, u.memory,
+                    COALESCE(u.fair_share, s.fair_share)
+             FROM user_samples u JOIN samples s ON s.id = u.sample_id
+             WHERE s.timestamp >= ?1 AND s.timestamp <= ?2
+             ORDER BY s.timestamp",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                since.unwrap_or(0) as i64,
+                until.map_or(i64::MAX, |t| t as i64)
+            ],
+            |row| {
+                Ok(UserSample {
+                    timestamp: row.get::<_, i64>(0)? as u64,
+                    username: row.get(1)?,
+                    equivalent_cores: row.get(2)?,
+                    system_cpu_share: row.get(3)?,
+                    memory: row.get::<_, i64>(4)? as u64,
+                    fair_share: row.get(5)?,
+                })
+            },
+        )?;
+        rows.collect()
+    }
+
+    /// Each user's mean cores used over every sample since `since` (in
+    /// seconds since the Unix epoch), counting samples they're missing from
+    /// as idle, or `None` if there are no samples since then.
+    pub fn usual_cores(&self, since: u64) -> rusqlite::Result<Option<HashMap<String, f64>>> {
+        let samples: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM samples WHERE timestamp >= ?1",
+            [since as i64],
+            |row| row.get(0),
+        )?;
+        if samples == 0 {
+            return Ok(None);
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT u.username, TOTAL(u.equivalent_cores)
+             FROM user_samples u JOIN samples s ON s.id = u.sample_id
+             WHERE s.timestamp >= ?1
+             GROUP BY u.username",
+        )?;
+        let rows = stmt.query_map([since as i64], |row| {
+            Ok((row.get(0)?, row.get::<_, f64>(1)? / samples as f64))
+        })?;
+        rows.collect::<rusqlite::Result<_>>().map(Some)
+    }
+}
+
+/// Add columns introduced after a database was created.
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    for column in ["fair_share", "cpu_seconds"] {
+        let exists = conn
+            .prepare("SELECT 1 FROM pragma_table_info('user_samples') WHERE name = ?1")?
+            .exists([column])?;
+        if !exists {
+            conn.execute(
+                &format!("ALTER TABLE user_samples ADD COLUMN {} REAL", column),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
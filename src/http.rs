@@ -0,0 +1,298 @@
+//! A small HTTP/1.1 server for the endpoints that serve reports:
+//! `--serve-metrics`, `load agent`, and `load serve`.
+//!
+//! Each connection gets its own thread, so a slow client (or a port
+//! scanner) only holds up itself, and requests are cut off after
+//! [`DEADLINE`] or [`MAX_HEADER_BYTES`], whichever comes first.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use loadrs::{LoadReport, Sampler};
+
+/// How long a client has to send its request line and headers.
+const DEADLINE: Duration = Duration::from_secs(5);
+
+/// The most a request line and headers can take up.
+const MAX_HEADER_BYTES: u64 = 16 * 1024;
+
+/// Connections served at once. More are turned away with a 503 rather than
+/// piling up threads.
+const MAX_CONNECTIONS: usize = 64;
+
+/// The latest report from a sampler sampling in the background.
+#[derive(Clone)]
+pub struct Latest(Arc<Mutex<LoadReport>>);
+
+impl Latest {
+    /// Take a first sample, then sample every `interval` in the background.
+    pub fn sample(mut sampler: Sampler, interval: Duration) -> Self {
+        let latest = Self(Arc::new(Mutex::new(sampler.sample())));
+        let shared = latest.clone();
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let report = sampler.sample();
+            *shared.get() = report;
+        });
+        latest
+    }
+
+    pub fn get(&self) -> MutexGuard<'_, LoadReport> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A request's method, target, and headers. Bodies are never read.
+pub struct Request {
+    pub method: String,
+    /// The path and query, e.g. `/history?since=1h`.
+    pub target: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn path(&self) -> &str {
+        self.target
+            .split_once('?')
+            .map_or(&self.target, |(path, _)| path)
+    }
+
+    pub fn query(&self) -> &str {
+        self.target.split_once('?').map_or("", |(_, query)| query)
+    }
+
+    /// A header's value, by case-insensitive name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct Response {
+    /// The status code and reason, e.g. `200 OK`.
+    pub status: &'static str,
+    pub content_type: &'static str,
+    /// Headers besides the content type and length.
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn new(status: &'static str, content_type: &'static str, body: String) -> Self {
+        Self {
+            status,
+            content_type,
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::new("404 Not Found", "text/plain", String::new())
+    }
+
+    fn write(&self, stream: &mut impl Write) -> io::Result<()> {
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        )?;
+        for (name, value) in &self.headers {
+            write!(stream, "{}: {}\r\n", name, value)?;
+        }
+        write!(stream, "Connection: close\r\n\r\n{}", self.body)?;
+        stream.flush()
+    }
+}
+
+/// Answer requests on `listener` with `handler`, one thread per connection,
+/// until accepting fails for good.
+pub fn serve<F>(listener: TcpListener, handler: F) -> io::Result<()>
+where
+    F: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Error accepting connection: {}", e);
+                continue;
+            }
+        };
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            let busy = Response::new("503 Service Unavailable", "text/plain", String::new());
+            let _ = stream.set_write_timeout(Some(DEADLINE));
+            let _ = busy.write(&mut stream);
+            continue;
+        }
+        let handler = Arc::clone(&handler);
+        let connections = Arc::clone(&connections);
+        thread::spawn(move || {
+            if let Err(e) = respond(stream, &*handler) {
+                eprintln!("Error serving request: {}", e);
+            }
+            connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+fn respond(stream: TcpStream, handler: &dyn Fn(&Request) -> Response) -> io::Result<()> {
+    stream.set_write_timeout(Some(DEADLINE))?;
+    let mut writer = stream.try_clone()?;
+    let reader = Deadline {
+        stream,
+        deadline: Instant::now() + DEADLINE,
+    };
+    let response = match read_request(BufReader::new(reader.take(MAX_HEADER_BYTES))) {
+        Ok(Some(request)) => handler(&request),
+        Ok(None) => Response::new(
+            "431 Request Header Fields Too Large",
+            "text/plain",
+            String::new(),
+        ),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            Response::new("400 Bad Request", "text/plain", String::new())
+        }
+        // Read timeouts are WouldBlock on Unix and TimedOut on Windows.
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            Response::new("408 Request Timeout", "text/plain", String::new())
+        }
+        // Connected and hung up without asking for anything.
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    response.write(&mut writer)
+}
+
+/// Read a request line and headers, or `None` if they're cut short, by the
+/// reader's limit or the client hanging up.
+fn read_request(mut reader: impl BufRead) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    let mut lines = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || !line.ends_with('\n') {
+            // The client hung up, or the limit cut the line short.
+            return match lines.is_empty() && line.is_empty() {
+                true => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed before a request",
+                )),
+                false => Ok(None),
+            };
+        }
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if trimmed.is_empty() {
+            break;
+        }
+        lines.push(trimmed.to_string());
+    }
+    let mut lines = lines.into_iter();
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("malformed request line '{}'", request_line),
+        ));
+    };
+    Ok(Some(Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        headers: lines
+            .filter_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                Some((name.trim().to_string(), value.trim().to_string()))
+            })
+            .collect(),
+    }))
+}
+
+/// A stream whose reads all have to finish by `deadline`, however slowly
+/// the client trickles bytes in.
+struct Deadline {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request took too long",
+            ));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(request: &str, limit: u64) -> io::Result<Option<Request>> {
+        read_request(BufReader::new(request.as_bytes().take(limit)))
+    }
+
+    #[test]
+    fn requests_are_parsed() {
+        let request = read(
+            "GET /history?since=1h HTTP/1.1\r\nHost: node1\r\nAuthorization: Bearer abc\r\n\r\n",
+            MAX_HEADER_BYTES,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path(), "/history");
+        assert_eq!(request.query(), "since=1h");
+        assert_eq!(request.header("authorization"), Some("Bearer abc"));
+        assert_eq!(request.header("cookie"), None);
+    }
+
+    #[test]
+    fn oversized_headers_are_refused() {
+        let request = format!("GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(100));
+        assert!(read(&request, 64).unwrap().is_none());
+        assert!(read(&request, 1024).unwrap().is_some());
+    }
+
+    #[test]
+    fn malformed_requests_are_errors() {
+        let error = read("nonsense\r\n\r\n", MAX_HEADER_BYTES).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = read("", MAX_HEADER_BYTES).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn responses_have_lengths_and_headers() {
+        let mut response = Response::new("401 Unauthorized", "application/json", "{}".into());
+        response.headers.push(("WWW-Authenticate", "Bearer".into()));
+        let mut out = Vec::new();
+        response.write(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.1 401 Unauthorized\r\nContent-Type: application/json\r\nContent-Length: 2\r\nWWW-Authenticate: Bearer\r\nConnection: close\r\n\r\n{}"
+        );
+    }
+}
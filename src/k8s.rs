@@ -0,0 +1,146 @@
+//! Attributing processes to Kubernetes pods, from their cgroups, for nodes
+//! shared by many tenants.
+
+use std::collections::HashMap;
+use std::fs;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Where kubelet pod log directories are, named `<namespace>_<pod>_<uid>`.
+const POD_LOGS: &str = "/var/log/pods";
+
+/// The service account token of a pod running `load`, e.g. as a DaemonSet.
+pub const SERVICE_ACCOUNT_TOKEN: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+
+/// A pod, by namespace and name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Pod {
+    pub namespace: String,
+    pub name: String,
+}
+
+/// Usage of one pod.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PodUsage {
+    pub namespace: String,
+    pub pod: String,
+    /// Sum of process CPU usage, where 100% is one fully used core.
+    pub total_cpu_usage: f64,
+    pub equivalent_cores: f64,
+    /// Percent of total system CPU capacity.
+    pub system_cpu_share: f64,
+    /// Sum of process resident set sizes, in bytes.
+    pub memory: u64,
+    pub processes: usize,
+    /// Users the pod's processes run as.
+    pub users: Vec<String>,
+}
+
+/// The kubelet API, for pods whose log directories can't be read.
+#[derive(Debug, Clone)]
+pub struct Kubelet {
+    /// The pod list endpoint, e.g. `https://localhost:10250/pods`.
+    pub url: String,
+    /// Bearer token for the kubelet's authenticated port.
+    pub token: Option<String>,
+}
+
+/// The UID of the pod a process runs in, if any.
+///
+/// The kubelet puts each pod's containers in a cgroup like
+/// `/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod<uid>.slice/...`
+/// (with the systemd cgroup driver, and `_` for `-` in the UID) or
+/// `/kubepods/burstable/pod<uid>/<container>` (with the cgroupfs driver).
+pub fn pod_uid(pid: u32) -> Option<String> {
+    let cgroup = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    cgroup
+        .lines()
+        .filter(|line| line.contains("kubepods"))
+        .find_map(|line| {
+            line.split('/').find_map(|part| {
+                let part = part.strip_suffix(".slice").unwrap_or(part);
+                let uid = match part.rsplit_once("-pod") {
+                    Some((_, uid)) => uid,
+                    None => part.strip_prefix("pod")?,
+                };
+                let uid = uid.replace('_', "-");
+                is_uid(&uid).then_some(uid)
+            })
+        })
+}
+
+/// Whether `s` looks like a pod UID, e.g. `0d5f6a7e-1b2c-4d3e-8f9a-0b1c2d3e4f5a`.
+fn is_uid(s: &str) -> bool {
+    s.len() == 36 && s.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+}
+
+/// Pods on this node by UID, from the kubelet's pod log directories, which
+/// need no credentials to list.
+pub fn logged_pods() -> HashMap<String, Pod> {
+    let Ok(entries) = fs::read_dir(POD_LOGS) else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            // Namespaces and pod names can't contain `_`.
+            let mut parts = name.splitn(3, '_');
+            let (namespace, pod, uid) = (parts.next()?, parts.next()?, parts.next()?);
+            let pod = Pod {
+                namespace: namespace.to_string(),
+                name: pod.to_string(),
+            };
+            Some((uid.to_string(), pod))
+        })
+        .collect()
+}
+
+/// Pods on this node by UID, from the kubelet API. The kubelet's serving
+/// certificate is usually self-signed, so it isn't verified.
+pub fn kubelet_pods(kubelet: &Kubelet) -> Result<HashMap<String, Pod>, String> {
+    #[derive(Deserialize)]
+    struct PodList {
+        items: Vec<Item>,
+    }
+    #[derive(Deserialize)]
+    struct Item {
+        metadata: Metadata,
+    }
+    #[derive(Deserialize)]
+    struct Metadata {
+        name: String,
+        namespace: String,
+        uid: String,
+    }
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(5)))
+        .tls_config(
+            ureq::tls::TlsConfig::builder()
+                .disable_verification(true)
+                .build(),
+        )
+        .build()
+        .into();
+    let mut request = agent.get(&kubelet.url);
+    if let Some(token) = &kubelet.token {
+        request = request.header("Authorization", &format!("Bearer {}", token));
+    }
+    let body = request
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .map_err(|e| e.to_string())?;
+    let list: PodList = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    Ok(list
+        .items
+        .into_iter()
+        .map(|item| {
+            let pod = Pod {
+                namespace: item.metadata.namespace,
+                name: item.metadata.name,
+            };
+            (item.metadata.uid, pod)
+        })
+        .collect())
+}
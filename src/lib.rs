@@ -0,0 +1,36 @@
+//! Per-user CPU accounting and fair share calculation.
+//!
+//! The `load` binary is a thin wrapper around [`Sampler`], which takes
+//! snapshots of the system and returns them as [`LoadReport`]s.
+
+pub mod alloc;
+pub mod cgroup;
+pub mod contacts;
+pub mod container;
+pub mod cpustat;
+pub mod fairshare;
+pub mod fds;
+pub mod gpu;
+pub mod history;
+pub mod k8s;
+pub mod login;
+pub mod net;
+pub mod nice;
+pub mod platform;
+pub mod policy;
+pub mod pressure;
+pub mod report;
+pub mod sampler;
+pub mod simulate;
+pub mod slurm;
+pub mod swap;
+pub mod thermal;
+
+pub use contacts::{Contact, Contacts};
+pub use policy::{FairSharePolicy, PolicyKind, Quota, Quotas, Weights};
+pub use report::{
+    DiskUsage, FairShare, Forecast, GroupBy, Host, LimitMismatch, LoadAverages, LoadReport,
+    LoadState, LoadWindow, LongRunning, MemoryOffender, NewHeavy, Offender, ProcessUsage, SortBy,
+    Spike, SwapHog, SystemMemory, Unniced, UserUsage, Zombies,
+};
+pub use sampler::{Sampler, Stage, Stages, UidRange};
@@ -0,0 +1,376 @@
+//! `--log`: one timestamped, uncolored line per sample and per event, for
+//! journald and other log collectors.
+
+use std::collections::HashSet;
+use std::env;
+
+use loadrs::{LoadReport, LoadState};
+
+/// Syslog priorities, which journald reads from a `<N>` prefix.
+#[derive(Clone, Copy)]
+enum Level {
+    Err = 3,
+    Warning = 4,
+    Info = 6,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Err => "error",
+            Level::Warning => "warning",
+            Level::Info => "info",
+        }
+    }
+}
+
+/// Logs samples, remembering what was already logged so that offenders,
+/// memory offenders, zombies, long-running processes, unniced batch work,
+/// cgroup limit mismatches, swap hogs, thermal throttling, load forecasts, and load states are only logged when they change.
+#[derive(Default)]
+pub struct Logger {
+    offenders: HashSet<String>,
+    memory_offenders: HashSet<String>,
+    zombies: HashSet<String>,
+    long_running: HashSet<u32>,
+    unniced: HashSet<u32>,
+    limit_mismatches: HashSet<String>,
+    swap_hog: Option<String>,
+    swapping: bool,
+    throttled: bool,
+    forecast: bool,
+}
+
+impl Logger {
+    /// Log a summary line for the sample, followed by a line for each change
+    /// in load state, user crossing or dropping back below their fair share,
+    /// spike, and warning, as `key=value` pairs.
+    pub fn print(&mut self, report: &LoadReport) {
+        let level = match report.load_state {
+            LoadState::Ok => Level::Info,
+            _ => Level::Warning,
+        };
+        let mut fields = vec![
+            ("load1", format!("{:.2}", report.load_average.one)),
+            ("load5", format!("{:.2}", report.load_average.five)),
+            ("load15", format!("{:.2}", report.load_average.fifteen)),
+            ("cores", report.total_cores.to_string()),
+            ("state", state(report.load_state)),
+            ("active_users", report.fair_share.active_users.to_string()),
+            ("fair_share", format!("{:.2}", report.fair_share.value)),
+            ("offenders", report.offenders.len().to_string()),
+        ];
+        if let Some(host) = &report.host {
+            fields.extend([
+                ("host", host.hostname.clone()),
+                ("uptime_seconds", host.uptime.to_string()),
+            ]);
+        }
+        line(report.timestamp, level, "sample", &fields);
+        if let Some(previous) = report.previous_load_state {
+            let level = match report.load_state {
+                LoadState::Ok => Level::Info,
+                LoadState::Warn => Level::Warning,
+                LoadState::Crit => Level::Err,
+            };
+            line(
+                report.timestamp,
+                level,
+                "load_state",
+                &[
+                    ("from", state(previous)),
+                    ("to", state(report.load_state)),
+                    ("load1", format!("{:.2}", report.load_average.one)),
+                ],
+            );
+        }
+
+        if let Some(forecast) = &report.forecast {
+            if !self.forecast {
+                line(
+                    report.timestamp,
+                    Level::Warning,
+                    "load_forecast",
+                    &[
+                        (
+                            "cores_per_minute",
+                            format!("{:.2}", forecast.cores_per_minute),
+                        ),
+                        ("seconds_to_threshold", forecast.seconds.to_string()),
+                    ],
+                );
+            }
+        }
+        self.forecast = report.forecast.is_some();
+
+        for offender in &report.offenders {
+            if self.offenders.contains(&offender.username) {
+                continue;
+            }
+            line(
+                report.timestamp,
+                Level::Warning,
+                "fair_share_exceeded",
+                &[
+                    ("user", offender.username.clone()),
+                    ("cpu_share", format!("{:.2}", offender.system_cpu_share)),
+                    ("fair_share", format!("{:.2}", offender.fair_share)),
+                    ("excess", format!("{:.2}", offender.excess_usage)),
+                    ("seconds_over", offender.time_over_fair_share.to_string()),
+                ],
+            );
+        }
+        let offenders: HashSet<String> = report
+            .offenders
+            .iter()
+            .map(|o| o.username.clone())
+            .collect();
+        for username in self.offenders.difference(&offenders) {
+            line(
+                report.timestamp,
+                Level::Info,
+                "fair_share_restored",
+                &[("user", username.clone())],
+            );
+        }
+        self.offenders = offenders;
+
+        for offender in &report.memory_offenders {
+            if self.memory_offenders.contains(&offender.username) {
+                continue;
+            }
+            line(
+                report.timestamp,
+                Level::Warning,
+                "memory_fair_share_exceeded",
+                &[
+                    ("user", offender.username.clone()),
+                    ("memory_bytes", offender.memory.to_string()),
+                    ("memory_share", format!("{:.2}", offender.memory_share)),
+                    ("fair_share", format!("{:.2}", offender.fair_share)),
+                ],
+            );
+        }
+        self.memory_offenders = report
+            .memory_offenders
+            .iter()
+            .map(|o| o.username.clone())
+            .collect();
+
+        for spike in &report.spikes {
+            line(
+                report.timestamp,
+                Level::Warning,
+                "usage_spike",
+                &[
+                    ("user", spike.username.clone()),
+                    ("cores", format!("{:.2}", spike.equivalent_cores)),
+                    ("baseline_cores", format!("{:.2}", spike.baseline_cores)),
+                ],
+            );
+        }
+        for zombies in &report.zombies {
+            if self.zombies.contains(&zombies.username) {
+                continue;
+            }
+            let parents: Vec<String> = zombies.parent_pids.iter().map(u32::to_string).collect();
+            line(
+                report.timestamp,
+                Level::Warning,
+                "zombies",
+                &[
+                    ("user", zombies.username.clone()),
+                    ("count", zombies.count.to_string()),
+                    ("parent_pids", parents.join(",")),
+                ],
+            );
+        }
+        self.zombies = report.zombies.iter().map(|z| z.username.clone()).collect();
+
+        for user in &report.long_running {
+            for process in &user.processes {
+                if self.long_running.contains(&process.pid) {
+                    continue;
+                }
+                line(
+                    report.timestamp,
+                    Level::Warning,
+                    "long_running",
+                    &[
+                        ("user", user.username.clone()),
+                        ("pid", process.pid.to_string()),
+                        ("runtime_seconds", process.run_time.to_string()),
+                        ("command", process.command.clone()),
+                    ],
+                );
+            }
+        }
+        self.long_running = report
+            .long_running
+            .iter()
+            .flat_map(|user| user.processes.iter().map(|p| p.pid))
+            .collect();
+
+        // New processes are only reported once, in the sample after they
+        // start.
+        for user in &report.new_heavy {
+            for process in &user.processes {
+                line(
+                    report.timestamp,
+                    Level::Warning,
+                    "new_heavy",
+                    &[
+                        ("user", user.username.clone()),
+                        ("pid", process.pid.to_string()),
+                        ("cpu_usage", format!("{:.2}", process.cpu_usage)),
+                        ("command", process.command.clone()),
+                    ],
+                );
+            }
+        }
+
+        for user in &report.unniced {
+            for process in &user.processes {
+                if self.unniced.contains(&process.pid) {
+                    continue;
+                }
+                line(
+                    report.timestamp,
+                    Level::Warning,
+                    "unniced",
+                    &[
+                        ("user", user.username.clone()),
+                        ("pid", process.pid.to_string()),
+                        ("nice", process.nice.unwrap_or_default().to_string()),
+                        ("cpu_usage", format!("{:.2}", process.cpu_usage)),
+                        ("command", process.command.clone()),
+                    ],
+                );
+            }
+        }
+        self.unniced = report
+            .unniced
+            .iter()
+            .flat_map(|user| user.processes.iter().map(|p| p.pid))
+            .collect();
+
+        for mismatch in &report.limit_mismatches {
+            if self.limit_mismatches.contains(&mismatch.username) {
+                continue;
+            }
+            line(
+                report.timestamp,
+                Level::Warning,
+                "limit_mismatch",
+                &[
+                    ("user", mismatch.username.clone()),
+                    ("cpu_limit", format!("{:.2}", mismatch.cpu_limit)),
+                    (
+                        "fair_share_cores",
+                        format!("{:.2}", mismatch.fair_share_cores),
+                    ),
+                ],
+            );
+        }
+        self.limit_mismatches = report
+            .limit_mismatches
+            .iter()
+            .map(|m| m.username.clone())
+            .collect();
+
+        if report.memory.swapping && !self.swapping {
+            line(
+                report.timestamp,
+                Level::Err,
+                "swapping",
+                &[
+                    (
+                        "memory_available_bytes",
+                        report.memory.available.to_string(),
+                    ),
+                    ("swap_used_bytes", report.memory.swap_used.to_string()),
+                ],
+            );
+        }
+        self.swapping = report.memory.swapping;
+        if let Some(hog) = &report.swap_hog {
+            if self.swap_hog.as_ref() != Some(&hog.username) {
+                line(
+                    report.timestamp,
+                    Level::Warning,
+                    "swap_hog",
+                    &[
+                        ("user", hog.username.clone()),
+                        ("swap_bytes", hog.swap.to_string()),
+                        ("swap_share", format!("{:.2}", hog.swap_share)),
+                    ],
+                );
+            }
+        }
+        self.swap_hog = report.swap_hog.as_ref().map(|hog| hog.username.clone());
+        let throttled = report.thermal.as_ref().filter(|t| t.throttled());
+        if let Some(thermal) = throttled.filter(|_| !self.throttled) {
+            let mut fields = Vec::new();
+            if let Some(hottest) = thermal
+                .packages
+                .iter()
+                .max_by(|a, b| a.celsius.total_cmp(&b.celsius))
+            {
+                fields.push(("celsius", format!("{:.0}", hottest.celsius)));
+            }
+            if let Some(busy) = thermal.busy_frequency_mhz {
+                fields.push(("busy_mhz", format!("{:.0}", busy)));
+            }
+            if let Some(events) = thermal.throttle_events {
+                fields.push(("throttle_events", events.to_string()));
+            }
+            line(
+                report.timestamp,
+                Level::Warning,
+                "thermal_throttling",
+                &fields,
+            );
+        }
+        self.throttled = throttled.is_some();
+        for warning in &report.warnings {
+            line(
+                report.timestamp,
+                Level::Warning,
+                "warning",
+                &[("message", warning.clone())],
+            );
+        }
+    }
+}
+
+fn state(state: LoadState) -> String {
+    state.name().to_lowercase()
+}
+
+fn line(timestamp: u64, level: Level, event: &str, fields: &[(&str, String)]) {
+    let mut out = String::new();
+    // Under systemd, stdout is connected to the journal, which sets
+    // JOURNAL_STREAM and strips the priority prefix.
+    if env::var_os("JOURNAL_STREAM").is_some() {
+        out.push_str(&format!("<{}>", level as u8));
+    }
+    out.push_str(&format!(
+        "time={} level={} event={}",
+        crate::time::format_timestamp(timestamp).replacen(' ', "T", 1) + "Z",
+        level.name(),
+        event
+    ));
+    for (key, value) in fields {
+        out.push_str(&format!(" {}={}", key, quote(value)));
+    }
+    println!("{}", out);
+}
+
+/// Quote values with spaces, quotes, or `=` in them, logfmt style.
+fn quote(value: &str) -> String {
+    if value.is_empty() || value.contains([' ', '"', '=']) {
+        format!("{:?}", value)
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,197 @@
+//! `--log-file`: append each sample's users to a CSV file, rotated by size
+//! and age with old files gzipped, for weeks of lightweight history on nodes
+//! without a database or collector.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use loadrs::LoadReport;
+
+use crate::delimited;
+use crate::time::{format_timestamp, now};
+
+pub struct RotatingLog {
+    path: PathBuf,
+    max_size: u64,
+    max_age: u64,
+    /// Rotated files to keep, or 0 to keep them all.
+    keep: usize,
+    file: File,
+    /// Timestamp of the current file's first sample.
+    started: u64,
+}
+
+impl RotatingLog {
+    /// Open the log at `path` for appending, rotating it once it reaches
+    /// `max_size` bytes or its first sample is `max_age` seconds old.
+    pub fn open(path: &Path, max_size: u64, max_age: u64, keep: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            max_age,
+            keep,
+            file,
+            started: first_timestamp(path).unwrap_or_else(now),
+        })
+    }
+
+    /// Append the report's users, with a header row if the file is new.
+    pub fn append(&mut self, report: &LoadReport) -> io::Result<()> {
+        let size = self.file.metadata()?.len();
+        if size > 0 && (size >= self.max_size || report.timestamp >= self.started + self.max_age) {
+            self.rotate(report.timestamp)?;
+        }
+        let header = self.file.metadata()?.len() == 0;
+        if header {
+            self.started = report.timestamp;
+        }
+        // Write each sample whole, so a full disk doesn't leave half a row.
+        let mut rows = Vec::new();
+        delimited::write(&mut rows, report, b',', header)?;
+        self.file.write_all(&rows)
+    }
+
+    /// Move the current file aside as e.g. `load.2024-06-01T130000.csv.gz`,
+    /// named for its first sample, and start a new one.
+    fn rotate(&mut self, timestamp: u64) -> io::Result<()> {
+        let stamp = format_timestamp(self.started)
+            .replace(' ', "T")
+            .replace(':', "");
+        let rotated = self.path.with_file_name(match self.path.extension() {
+            Some(extension) => format!(
+                "{}.{}.{}",
+                stem(&self.path),
+                stamp,
+                extension.to_string_lossy()
+            ),
+            None => format!("{}.{}", stem(&self.path), stamp),
+        });
+        fs::rename(&self.path, &rotated)?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.started = timestamp;
+        compress(&rotated)?;
+        self.prune()
+    }
+
+    /// Remove the oldest rotated files beyond the number to keep.
+    fn prune(&self) -> io::Result<()> {
+        if self.keep == 0 {
+            return Ok(());
+        }
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let prefix = format!("{}.", stem(&self.path));
+        let mut rotated: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".gz"))
+            })
+            .collect();
+        // The timestamps in the names sort oldest first.
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(self.keep);
+        for path in &rotated[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// The file name without its extension, e.g. `load` for `/var/log/load.csv`.
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+}
+
+/// Gzip `path` to `path.gz` and remove the original.
+fn compress(path: &Path) -> io::Result<()> {
+    let mut gz = path.as_os_str().to_owned();
+    gz.push(".gz");
+    let mut encoder = GzEncoder::new(File::create(&gz)?, Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)
+}
+
+/// The timestamp of the first sample in an existing log, so restarting
+/// `load` doesn't postpone rotation.
+fn first_timestamp(path: &Path) -> Option<u64> {
+    let mut lines = BufReader::new(File::open(path).ok()?).lines();
+    let _header = lines.next()?;
+    lines.next()?.ok()?.split(',').next()?.parse().ok()
+}
+
+/// Parse a size like `100M`, `2G`, or `512K` (or plain bytes) into bytes.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&s[..i], c.to_ascii_uppercase()),
+        _ => (s, 'B'),
+    };
+    let multiplier: u64 = match unit {
+        'B' => 1,
+        'K' => 1 << 10,
+        'M' => 1 << 20,
+        'G' => 1 << 30,
+        _ => {
+            return Err(format!(
+                "invalid size '{}' (expected e.g. 512K, 100M, 2G)",
+                s
+            ))
+        }
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size '{}' (expected e.g. 512K, 100M, 2G)", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_are_parsed() {
+        for (size, bytes) in [
+            ("512K", 512 << 10),
+            ("100M", 100 << 20),
+            ("2G", 2 << 30),
+            ("2g", 2 << 30),
+            ("4096", 4096),
+            ("4096B", 4096),
+            ("10 M", 10 << 20),
+            (" 1G ", 1 << 30),
+        ] {
+            assert_eq!(parse_size(size), Ok(bytes), "{}", size);
+        }
+    }
+
+    #[test]
+    fn bad_sizes_are_errors() {
+        for size in [
+            "",
+            "M",
+            "1.5G",
+            "-1M",
+            "2T",
+            "2GB",
+            "lots",
+            "99999999999999G",
+        ] {
+            assert!(parse_size(size).is_err(), "{}", size);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Users' login sessions, read from `who`.
+
+use std::collections::HashMap;
+use std::io;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+/// A login session, as listed by `who -u`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Login {
+    /// The session's terminal, e.g. `pts/0`.
+    pub tty: String,
+    /// When the user logged in, in the server's local time, e.g.
+    /// `2024-06-01 13:00` (or `Jun 1 13:00` on macOS).
+    pub login_time: String,
+    /// Seconds since the terminal last saw input (rounded to minutes), or
+    /// `None` if it is over a day.
+    pub idle: Option<u64>,
+    /// The remote host the user logged in from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+}
+
+/// Every user's login sessions, by username.
+pub fn sessions() -> io::Result<HashMap<String, Vec<Login>>> {
+    let output = Command::new("who")
+        .arg("-u")
+        // Other locales can format the login time with spaces.
+        .env("LC_ALL", "C")
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), "who not found"),
+            _ => e,
+        })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "who failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let mut sessions: HashMap<String, Vec<Login>> = HashMap::new();
+    // Lines are "user tty date time idle pid [(host)]", where idle is "."
+    // (active in the last minute), "old" (over a day), or "HH:MM". On macOS
+    // and the BSDs, the date is a month and day ("Jun  1") instead.
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let mut fields: Vec<&str> = line.split_whitespace().collect();
+        let date = if fields.len() > 3 && fields[2].chars().all(|c| c.is_ascii_alphabetic()) {
+            let day = fields.remove(3);
+            format!("{} {}", fields[2], day)
+        } else {
+            fields.get(2).unwrap_or(&"").to_string()
+        };
+        if fields.len() < 6 {
+            continue;
+        }
+        let idle = match fields[4] {
+            "." => Some(0),
+            "old" => None,
+            idle => idle.split_once(':').and_then(|(h, m)| {
+                Some(h.parse::<u64>().ok()? * 3600 + m.parse::<u64>().ok()? * 60)
+            }),
+        };
+        let host = fields
+            .get(6)
+            .map(|host| host.trim_start_matches('(').trim_end_matches(')'))
+            .filter(|host| !host.is_empty())
+            .map(str::to_string);
+        sessions
+            .entry(fields[0].to_string())
+            .or_default()
+            .push(Login {
+                tty: fields[1].to_string(),
+                login_time: format!("{} {}", date, fields[3]),
+                idle,
+                host,
+            });
+    }
+    Ok(sessions)
+}
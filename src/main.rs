@@ -1,187 +1,1427 @@
-use clap::Parser;
+use alerts::{Alerter, Emailer, Notifier, SmtpConfig, SmtpSecurity};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use colored::*;
+use error::{Context, Error};
+use loadrs::cpustat::CpuWait;
+use loadrs::fairshare::Ledger;
+use loadrs::fds::FileHandles;
+use loadrs::history::History;
+use loadrs::k8s::{self, Kubelet, PodUsage};
+use loadrs::pressure::Pressure;
+use loadrs::simulate::Scenario;
+use loadrs::slurm::JobUsage;
+use loadrs::thermal::Thermal;
+use loadrs::{
+    Contact, Contacts, FairSharePolicy, GroupBy, Host, LoadReport, LoadState, LoadWindow, Offender,
+    PolicyKind, Quota, Quotas, Sampler, SortBy, UidRange, Weights,
+};
 use prettytable::color;
-use prettytable::{format, Attr};
-use prettytable::{Cell, Row, Table};
+use prettytable::Attr;
+use prettytable::{Cell, Row};
+use regex::Regex;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::time::{Duration, Instant};
-use sysinfo::{System, Users};
+use table::Column;
+
+mod alerts;
+mod commands;
+mod config;
+mod delimited;
+mod enforce;
+mod error;
+mod graphite;
+mod http;
+mod log;
+mod logfile;
+mod metrics;
+mod otel;
+mod pace;
+mod parquet;
+mod profile;
+mod statsd;
+mod table;
+mod time;
+mod tui;
+
+/// Counts allocations, for `load bench`.
+#[global_allocator]
+static ALLOCATOR: loadrs::alloc::Counting = loadrs::alloc::Counting;
+
+/// Number of 1 minute load averages kept for the --live load history.
+const LOAD_HISTORY: usize = 60;
+
+/// How often to recompute users' usual usage from the --record database.
+const BASELINE_REFRESH: Duration = Duration::from_secs(15 * 60);
+
+/// Percent of the kernel's file table in use at which --fds warns.
+const FILE_HANDLE_WARNING: f64 = 90.0;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// Excessive usage warning threshold (e.g. 80 = 80% usage)
-    #[arg(short, long, default_value_t = 100.)]
-    threshold: f64,
+    #[command(subcommand)]
+    subcommand: Option<Command>,
+    /// Config file with defaults for any of these options (default
+    /// ~/.config/loadrs/config.toml)
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Preset thresholds, grace periods, runtime limits, and checks for a
+    /// class of machine, which the config file and command line override
+    #[arg(long, value_enum)]
+    profile: Option<profile::Profile>,
+    /// Excessive load threshold, as a percent of total cores (e.g. 80 or
+    /// 80%) or a number of cores (e.g. 12c)
+    #[arg(short, long, value_name = "PERCENT|CORES", default_value = "100", value_parser = parse_amount)]
+    threshold: Quota,
+    /// Load warning threshold, as a percent of total cores or a number of
+    /// cores (default 80% of --threshold)
+    #[arg(long, value_name = "PERCENT|CORES", value_parser = parse_amount)]
+    warn_threshold: Option<Quota>,
+    /// Percent of total cores the load has to drop below a threshold to
+    /// leave the warning or excessive load state it entered
+    #[arg(long, value_name = "PERCENT", default_value_t = 5.0)]
+    hysteresis: f64,
+    /// Which load average (in minutes) decides whether load is excessive;
+    /// 15 ignores brief spikes
+    #[arg(long, value_name = "MINUTES", default_value = "1")]
+    load_window: Window,
+    /// In --live mode, stay in a load state (OK, WARN, or CRIT) for at least
+    /// this long before changing to another, e.g. 2m
+    #[arg(long, value_name = "DURATION", default_value = "0s", value_parser = time::parse_duration)]
+    min_dwell: u64,
     /// When calculating fraction of active users, they are those with
     /// this *percent* usage (default is 1%).
     #[arg(short, long, default_value_t = 1.0)]
     active_threshold: f64,
-    /// The user's fair share proportion (by default, calculated based
-    /// total / number of active users, where an active user is set by
-    /// --active-threshold.
-    #[arg(short, long)]
-    fair_share: Option<f64>,
+    /// Every user's fair share, as a percent of total cores (e.g. 20) or a
+    /// number of cores (e.g. 8c), instead of splitting the machine among
+    /// active users (those above --active-threshold)
+    #[arg(short, long, value_name = "PERCENT|CORES", value_parser = parse_amount)]
+    fair_share: Option<Quota>,
+    /// How to split the machine among active users (default equal, or
+    /// fixed if --fair-share is given)
+    #[arg(long, value_enum)]
+    policy: Option<Policy>,
+    /// Weights for the weighted policy, e.g. alice=2,bob=0.5
+    /// (comma-separated; unlisted users have weight 1)
+    #[arg(long, value_name = "USER=WEIGHT", value_delimiter = ',', value_parser = parse_weight)]
+    user_weights: Vec<(String, f64)>,
+    /// Like --user-weights, for all members of these groups, e.g. lab=2.
+    /// User weights take precedence.
+    #[arg(long, value_name = "GROUP=WEIGHT", value_delimiter = ',', value_parser = parse_weight)]
+    group_weights: Vec<(String, f64)>,
+    /// File of per-user entitlements that replace their fair share, one
+    /// `alice = 16 cores` or `bob = 25%` per line. Active users' quotas are
+    /// set aside, and everyone else splits the rest.
+    #[arg(long, value_name = "FILE")]
+    quotas: Option<PathBuf>,
+    /// File of users' contacts, a TOML table per user with their name,
+    /// email, and slack handle, shown with offenders and in alerts
+    #[arg(long, value_name = "FILE")]
+    contacts: Option<PathBuf>,
+    /// Read the names and emails of users missing from --contacts from
+    /// their GECOS fields in the password database (Unix)
+    #[arg(long)]
+    gecos: bool,
+    /// Users who are never flagged for exceeding their fair share
+    /// (comma-separated)
+    #[arg(long, value_name = "USERS", value_delimiter = ',')]
+    exempt_users: Vec<String>,
+    /// Users to leave out of the report and the active user count, e.g.
+    /// system daemons (comma-separated)
+    #[arg(long, value_name = "USERS", value_delimiter = ',')]
+    exclude_users: Vec<String>,
+    /// Like --exclude-users, for all members of these groups
+    /// (comma-separated)
+    #[arg(long, value_name = "GROUPS", value_delimiter = ',')]
+    exclude_groups: Vec<String>,
+    /// Only report these users, e.g. `--user $USER` to watch your own
+    /// usage (repeatable or comma-separated; implies --processes)
+    #[arg(short, long, value_name = "USER", value_delimiter = ',')]
+    user: Vec<String>,
+    /// In --live mode, smooth each user's CPU usage with an exponentially
+    /// weighted moving average, where ALPHA (0 to 1) is the weight of the
+    /// newest sample, e.g. 0.3
+    #[arg(long, value_name = "ALPHA", value_parser = parse_alpha)]
+    ewma: Option<f64>,
+    /// Only flag users once they have been over their fair share for this
+    /// long in --live mode, e.g. 5m, so short spikes aren't flagged
+    #[arg(long, value_name = "DURATION", default_value = "0s", value_parser = time::parse_duration)]
+    grace_period: u64,
+    /// In --live mode, flag users whose usage suddenly jumps above their
+    /// baseline over the last 30 samples (and alert, if alerts are set up)
+    #[arg(long)]
+    spikes: bool,
+    /// In --live mode, warn when the load will exceed --threshold within
+    /// the hour if it keeps growing at its rate over the last 30 samples
+    /// (and alert, if alerts are set up)
+    #[arg(long)]
+    forecast: bool,
+    /// Standard deviations above baseline a jump needs to count as a spike
+    #[arg(long, value_name = "SIGMAS", default_value_t = 3.0)]
+    spike_sigmas: f64,
+    /// Cores above baseline a jump needs to count as a spike
+    #[arg(long, value_name = "CORES", default_value_t = 1.0)]
+    spike_cores: f64,
+    /// Print renice and ionice commands for the busiest processes of users
+    /// over their fair share
+    #[arg(long)]
+    suggest_renice: bool,
+    /// Renice the busiest processes of users over their fair share (after
+    /// --grace-period) to --renice-to (requires root)
+    #[arg(long)]
+    enforce: bool,
+    /// In --live mode, cap the CPU of users over their fair share (after
+    /// --grace-period) at their fair share, with CPUQuota on their systemd
+    /// user slice, and lift the cap once they fall back under it or `load`
+    /// exits (Linux with systemd, requires root)
+    #[arg(long, requires = "live")]
+    enforce_cgroup: bool,
+    /// Nice value for --suggest-renice and --enforce
+    #[arg(long, value_name = "NICE", default_value_t = 10, value_parser = clap::value_parser!(i32).range(-20..=19))]
+    renice_to: i32,
+    /// Only account for processes whose command line matches this regular
+    /// expression, e.g. 'python|R'
+    #[arg(long, value_name = "REGEX")]
+    command: Option<Regex>,
+    /// Leave out processes whose command line matches this regular
+    /// expression, e.g. known-good daemons like '^/opt/backup/agent' or
+    /// 'z_scrub' (repeatable)
+    #[arg(long, value_name = "REGEX")]
+    ignore_command: Vec<Regex>,
     /// Update interval in seconds
     #[arg(short, long, default_value_t = 5)]
     interval: u64,
+    /// Shortest update interval in seconds in --live mode
+    #[arg(long, value_name = "SECONDS", default_value_t = 1)]
+    min_interval: u64,
+    /// Longest update interval in seconds in --live mode, which the
+    /// interval backs off towards while sampling takes over a tenth of it
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    max_interval: u64,
+    /// Milliseconds between the two measurements CPU usage is computed
+    /// from (at least 200). Longer windows smooth out bursty processes.
+    #[arg(long, value_name = "MS", default_value_t = 200)]
+    sample_window: u64,
+    /// Average this many measurements (each over --sample-window) into the
+    /// report, to smooth out bursty workloads in a single report
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    samples: u64,
+    /// Milliseconds between the start of each of --samples measurements
+    #[arg(long, value_name = "MS", default_value_t = 1000)]
+    sample_interval: u64,
     /// Run in loop mode
     #[arg(short, long)]
     live: bool,
+    /// Run in an interactive full-screen view
+    #[arg(long, conflicts_with_all = ["live", "format"])]
+    tui: bool,
+    /// Serve Prometheus metrics on this address (e.g. 0.0.0.0:9100)
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["live", "format", "tui"])]
+    serve_metrics: Option<String>,
+    /// Push gauges for each sample (load averages, offender count, and each
+    /// user's CPU share, cores, and memory) over UDP to this StatsD server,
+    /// e.g. localhost:8125
+    #[arg(long, value_name = "HOST:PORT")]
+    statsd: Option<String>,
+    /// StatsD dialect for --statsd
+    #[arg(long, value_enum, default_value_t = statsd::Flavor::Dogstatsd)]
+    statsd_flavor: statsd::Flavor,
+    /// Push the same metrics as --statsd for each sample over TCP to this
+    /// Graphite (Carbon) server in its plaintext protocol, e.g.
+    /// localhost:2003
+    #[arg(long, value_name = "HOST:PORT")]
+    graphite: Option<String>,
+    /// Start of --graphite metric paths, before the host name
+    #[arg(long, value_name = "PREFIX", default_value = "loadrs")]
+    graphite_prefix: String,
+    /// Push each sample's system and per-user metrics to this
+    /// OpenTelemetry collector over OTLP/HTTP, e.g. http://localhost:4318
+    #[arg(long, value_name = "URL")]
+    otel_endpoint: Option<String>,
+    /// Add this header to --otel-endpoint requests, e.g.
+    /// "Authorization=Bearer TOKEN" (repeatable)
+    #[arg(long, value_name = "NAME=VALUE", value_parser = parse_header)]
+    otel_header: Vec<(String, String)>,
+    /// Print a one-line summary and exit non-zero if load is excessive or
+    /// any user exceeds their fair share (e.g. for cron or monitoring)
+    #[arg(long, conflicts_with_all = ["live", "tui", "serve_metrics"])]
+    check: bool,
+    /// Exit code for --check when the load exceeds --threshold
+    #[arg(long, value_name = "CODE", default_value_t = 2)]
+    load_exit_code: i32,
+    /// Exit code for --check when users exceed fair share, but the load
+    /// is not excessive
+    #[arg(long, value_name = "CODE", default_value_t = 1)]
+    fair_share_exit_code: i32,
+    /// POST a JSON alert to this URL (e.g. a Slack incoming webhook) when
+    /// the load becomes excessive or a user crosses their fair share
+    #[arg(long, value_name = "URL")]
+    alert_webhook: Option<String>,
+    /// Email alerts to these addresses (comma-separated; requires
+    /// --alert-smtp-server)
+    #[arg(
+        long,
+        value_name = "ADDRESSES",
+        value_delimiter = ',',
+        requires = "alert_smtp_server"
+    )]
+    alert_email: Vec<String>,
+    /// Also email users over their fair share, at USER@DOMAIN or their
+    /// email from --contacts (requires --alert-smtp-server)
+    #[arg(long, value_name = "DOMAIN", requires = "alert_smtp_server")]
+    alert_email_users: Option<String>,
+    /// Sender address for email alerts (default loadrs@<hostname>)
+    #[arg(long, value_name = "ADDRESS")]
+    alert_from: Option<String>,
+    /// SMTP server to send email alerts through
+    #[arg(long, value_name = "HOST")]
+    alert_smtp_server: Option<String>,
+    /// SMTP port (default depends on --alert-smtp-security)
+    #[arg(long, value_name = "PORT")]
+    alert_smtp_port: Option<u16>,
+    /// How to secure the SMTP connection
+    #[arg(long, value_enum, default_value_t = SmtpSecurity::Starttls)]
+    alert_smtp_security: SmtpSecurity,
+    /// SMTP username, if the server requires authentication
+    #[arg(long, value_name = "USER")]
+    alert_smtp_username: Option<String>,
+    /// SMTP password (best set in the config file rather than on the
+    /// command line)
+    #[arg(long, value_name = "PASSWORD")]
+    alert_smtp_password: Option<String>,
+    /// Post alerts to this Matrix room, e.g. '!abc123:example.org' (requires
+    /// --alert-matrix-homeserver and --alert-matrix-token)
+    #[arg(
+        long,
+        value_name = "ROOM",
+        requires_all = ["alert_matrix_homeserver", "alert_matrix_token"]
+    )]
+    alert_matrix_room: Option<String>,
+    /// Matrix homeserver to post alerts through, e.g.
+    /// https://matrix.example.org
+    #[arg(long, value_name = "URL")]
+    alert_matrix_homeserver: Option<String>,
+    /// Access token of the Matrix account posting alerts (best set in the
+    /// config file rather than on the command line)
+    #[arg(long, value_name = "TOKEN")]
+    alert_matrix_token: Option<String>,
+    /// Run this shell command for each alert, with the alert as JSON on its
+    /// stdin (can be repeated)
+    #[arg(long, value_name = "COMMAND")]
+    alert_command: Vec<String>,
+    /// Show a desktop notification when the load becomes excessive or your
+    /// own usage crosses your fair share
+    #[arg(long)]
+    notify: bool,
+    /// Write a polite notice to the terminals of users over their fair share
+    /// past the grace period, with their share and fair share, like `write`
+    /// (needs root or the tty group; skips terminals with `mesg n`)
+    #[arg(long)]
+    alert_tty: bool,
+    /// Don't repeat the same alert within this long, e.g. 30m or 1h
+    #[arg(long, value_name = "DURATION", default_value = "15m", value_parser = time::parse_duration)]
+    alert_cooldown: u64,
+    /// Append each sample to this SQLite database (see `load history`)
+    #[arg(long, value_name = "DB")]
+    record: Option<PathBuf>,
+    /// With --record, compare each user's cores with their mean over this
+    /// long, e.g. 7d or 24h
+    #[arg(long, value_name = "DURATION", default_value = "7d", value_parser = time::parse_duration)]
+    baseline: u64,
+    /// Keep each user's CPU time, decayed with --half-life, in this file,
+    /// and show their long-running fair-share score (see `load fairshare`)
+    #[arg(long, value_name = "FILE")]
+    fairshare_file: Option<PathBuf>,
+    /// How long it takes usage in --fairshare-file to count half as much,
+    /// e.g. 1d
+    #[arg(long, value_name = "DURATION", default_value = "7d", value_parser = time::parse_duration)]
+    half_life: u64,
+    /// Warn when tasks were stalled on CPU, memory, or I/O for more than
+    /// this percent of the last 10 seconds (Linux pressure stall information)
+    #[arg(long, value_name = "PERCENT", default_value_t = 20.0)]
+    pressure_threshold: f64,
+    /// Flag processes running longer than this, e.g. 2h on login nodes where
+    /// long jobs are forbidden (exempt users' processes are never flagged)
+    #[arg(long, value_name = "DURATION", value_parser = time::parse_duration)]
+    max_runtime: Option<u64>,
+    /// Flag processes using at least --batch-cores cores at a nice value
+    /// below this, where site policy says batch work must be niced, e.g. 10
+    /// (exempt users' processes are never flagged)
+    #[arg(long, value_name = "NICE", value_parser = clap::value_parser!(i32).range(-20..=19))]
+    batch_nice: Option<i32>,
+    /// Cores a process has to use to count as batch work for --batch-nice
+    #[arg(long, value_name = "CORES", default_value_t = 1.0)]
+    batch_cores: f64,
+    /// In --live mode, flag processes that started since the previous sample
+    /// and already use at least this many cores, e.g. 2, to catch jobs at
+    /// launch (exempt users' processes are never flagged)
+    #[arg(long, value_name = "CORES")]
+    new_heavy: Option<f64>,
+    /// Warn about users with more than this many zombie (defunct) processes
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    zombie_threshold: usize,
+    /// Color a user's row yellow once their CPU share is above this
+    /// fraction of their fair share
+    #[arg(long, value_name = "RATIO", default_value_t = 0.5)]
+    warn_ratio: f64,
+    /// Color a user's row red once their CPU share is above this fraction
+    /// of their fair share
+    #[arg(long, value_name = "RATIO", default_value_t = 1.0)]
+    crit_ratio: f64,
+    /// When to use colors (auto: only on a terminal, and unless NO_COLOR is
+    /// set)
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    /// Append every sample to this file, as one JSON object per line (see
+    /// `load replay`)
+    #[arg(long, value_name = "FILE")]
+    record_session: Option<PathBuf>,
+    /// Replace this file with each sample's JSON report, atomically, so
+    /// prompts, motd generators, and status bars can read the current state
+    /// without sampling themselves, e.g. /run/loadrs/state.json
+    #[arg(long, value_name = "FILE")]
+    state_file: Option<PathBuf>,
+    /// Append each sample's users to this CSV file, rotating it by size
+    /// and age and gzipping the old ones, e.g. /var/log/load.csv
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+    /// Rotate --log-file once it's this big, e.g. 512K, 100M, or 2G
+    #[arg(long, value_name = "SIZE", default_value = "100M", value_parser = logfile::parse_size)]
+    log_rotate_size: u64,
+    /// Rotate --log-file once its first sample is this old, e.g. 1d or 12h
+    #[arg(long, value_name = "DURATION", default_value = "1d", value_parser = time::parse_duration)]
+    log_rotate_every: u64,
+    /// Keep this many rotated --log-file files, deleting the oldest (0 keeps
+    /// them all)
+    #[arg(long, value_name = "N", default_value_t = 30)]
+    log_keep: usize,
+    /// Report on the synthetic machine and processes described in this TOML
+    /// file instead of this machine's, e.g. for demos. Its processes
+    /// aren't real, so nothing is done to or sent to their owners
+    #[arg(
+        long,
+        value_name = "SCENARIO",
+        conflicts_with_all = [
+            "enforce",
+            "enforce_cgroup",
+            "suggest_renice",
+            "alert_tty",
+            "alert_email_users",
+        ]
+    )]
+    simulate: Option<PathBuf>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    /// Print one timestamped, uncolored line per sample and per offender,
+    /// e.g. for journald, instead of redrawing tables
+    #[arg(long, conflicts_with_all = ["tui", "format", "check", "serve_metrics"])]
+    log: bool,
+    /// Only print tables when the load is excessive or users exceed their
+    /// fair share, and then only those (e.g. for cron)
+    #[arg(short, long)]
+    quiet: bool,
+    /// Show step by step how each offender's usage, fair share, and excess
+    /// were computed, from the raw sums, whether or not the load is
+    /// excessive (table output)
+    #[arg(long, conflicts_with_all = ["log", "tui"])]
+    explain: bool,
+    /// Don't print a header row in CSV and TSV output
+    #[arg(long)]
+    no_header: bool,
+    /// Start reports with the hostname, sample time, uptime, OS, and kernel,
+    /// so shared screenshots and logs describe themselves (also added to
+    /// JSON output and --log sample lines)
+    #[arg(long)]
+    header: bool,
+    /// Column to sort users by
+    #[arg(long, value_enum, default_value_t = SortColumn::Cpu)]
+    sort_by: SortColumn,
+    /// Reverse the order of --sort-by
+    #[arg(long)]
+    reverse: bool,
+    /// Columns of the users table to show, in order (default: the ones
+    /// relevant to the other options)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "COLUMN,...")]
+    columns: Vec<table::Column>,
+    /// Show every user in the users table, rather than folding idle users
+    /// and system accounts into one "others/system" row
+    #[arg(long)]
+    all: bool,
+    /// Abbreviate the users table to fit narrow terminals and tmux panes:
+    /// short titles and usernames, cores rather than CPU percentages (also
+    /// used whenever the full table is wider than the terminal)
+    #[arg(long)]
+    compact: bool,
+    /// Print only ASCII, without colors, bars, or box drawing, e.g. for
+    /// email, ticket systems, and old terminals
+    #[arg(long, conflicts_with = "tui")]
+    ascii: bool,
+    /// Width to fit the users table in, instead of the terminal's
+    #[arg(long, value_name = "COLUMNS")]
+    width: Option<usize>,
+    /// Group usage by containers or Unix groups instead of users, with a
+    /// fair share per group
+    #[arg(long, value_enum, default_value_t = Grouping::User)]
+    group_by: Grouping,
+    /// With --group-by group, put users in these groups instead of their
+    /// primary groups, e.g. alice=smithlab (comma-separated)
+    #[arg(long, value_name = "USER=GROUP", value_delimiter = ',', value_parser = parse_mapping)]
+    group_map: Vec<(String, String)>,
+    /// With --group-by uid-range, the ranges to group UIDs into, in order
+    /// (comma-separated; default system=0-999,humans=1000-59999,service=60000-)
+    #[arg(long, value_name = "NAME=FIRST-LAST", value_delimiter = ',')]
+    uid_ranges: Vec<UidRange>,
+    /// List each user's top N processes by CPU usage (default 5)
+    #[arg(short, long, value_name = "N", num_args = 0..=1, default_missing_value = "5")]
+    processes: Option<usize>,
+    /// Show per-user NVIDIA GPU utilization and memory (requires nvidia-smi)
+    #[arg(long)]
+    gpu: bool,
+    /// Show per-user disk read and write rates
+    #[arg(long)]
+    io: bool,
+    /// Show per-user TCP send and receive rates (Linux, requires ss; other
+    /// users' sockets are only visible to root)
+    #[arg(long)]
+    net: bool,
+    /// Show how much of each user's memory is swapped out, and warn when one
+    /// user holds most of it (Linux)
+    #[arg(long)]
+    swap: bool,
+    /// Show how many file descriptors each user has open, and warn when the
+    /// system's file table is nearly full (Linux; other users' descriptors
+    /// need root)
+    #[arg(long)]
+    fds: bool,
+    /// Show the mean nice value of each user's processes, weighted by their
+    /// CPU usage (Unix)
+    #[arg(long)]
+    nice: bool,
+    /// Show each user's CPU limit from the cgroup of their systemd user
+    /// slice and their usage relative to it, and flag limits more than 10%
+    /// off their fair share (Linux)
+    #[arg(long)]
+    cgroup_limits: bool,
+    /// Show the utilization of each CPU core
+    #[arg(long)]
+    per_core: bool,
+    /// Show each user's CPU time summed over the session, in --live mode,
+    /// rather than just their current usage
+    #[arg(long)]
+    cumulative: bool,
+    /// Show which users are logged in (with their terminal, login time,
+    /// and idle time) and which are only running detached jobs (requires
+    /// who)
+    #[arg(long)]
+    logins: bool,
+    /// Group usage by Slurm job and flag users running outside any job
+    #[arg(long)]
+    slurm: bool,
+    /// Also show usage per Kubernetes pod and namespace, on a Kubernetes
+    /// node
+    #[arg(long)]
+    k8s: bool,
+    /// Kubelet pod list endpoint, asked for pod names with --k8s when the
+    /// pod log directories can't be read
+    #[arg(
+        long,
+        value_name = "URL",
+        default_value = "https://localhost:10250/pods"
+    )]
+    kubelet_url: String,
+    /// File with a bearer token for the kubelet (by default, the service
+    /// account token, when running in a pod)
+    #[arg(long, value_name = "FILE")]
+    kubelet_token_file: Option<PathBuf>,
+    /// Look up UIDs missing from the local user list through NSS (as
+    /// `getent passwd` does), e.g. LDAP or SSSD users
+    #[arg(long)]
+    resolve_uids: bool,
+    /// Combine processes of UIDs that can't be resolved into one "unknown"
+    /// row
+    #[arg(long)]
+    merge_unknown: bool,
 }
 
-fn main() {
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum Command {
+    /// Summarize per-user usage recorded with --record
+    History(commands::history::HistoryArgs),
+    /// Summarize per-user totals, peak hours, and fair-share violations
+    /// from a --record database or --log-file or --record-session files
+    Analyze(commands::analyze::AnalyzeArgs),
+    /// Export a --record database or --log-file or --record-session files to
+    /// Parquet, for analysis in polars, pandas, arrow, or DuckDB
+    Export(commands::export::ExportArgs),
+    /// Sample continuously and serve the latest report to `load status`
+    #[cfg(unix)]
+    Daemon(commands::daemon::DaemonArgs),
+    /// Print the latest report from `load daemon`, without waiting to sample
+    #[cfg(unix)]
+    Status(commands::status::StatusArgs),
+    /// Sample continuously and serve the latest report over HTTP to
+    /// `load cluster`
+    Agent(commands::agent::AgentArgs),
+    /// Sample continuously and answer JSON API queries over HTTP
+    Serve(commands::serve::ServeArgs),
+    /// Show the latest reports from `load agent` on several hosts
+    Cluster(commands::cluster::ClusterArgs),
+    /// Sample other hosts over SSH, with their own `load` or a fallback
+    /// script, and show their reports here
+    Remote(commands::remote::RemoteArgs),
+    /// Step through samples saved with --record-session
+    Replay(commands::replay::ReplayArgs),
+    /// Show per-user changes between two snapshots saved with --format json
+    Diff(commands::diff::DiffArgs),
+    /// Write an HTML page of the current usage, or of a recorded session
+    Report(commands::report::ReportArgs),
+    /// Print a compact, uncolored usage summary for /etc/motd or `wall`
+    Motd(commands::motd::MotdArgs),
+    /// Show a user's processes as a tree, with CPU usage summed per branch
+    Tree(commands::tree::TreeArgs),
+    /// Show the fair-share scores kept with --fairshare-file
+    Fairshare(commands::fairshare::FairshareArgs),
+    /// Print a systemd unit that runs `load daemon`
+    #[cfg(unix)]
+    InstallService(commands::service::ServiceArgs),
+    /// Time sampling, aggregation, and rendering, and count their
+    /// allocations, e.g. on nodes with huge process tables
+    Bench(commands::bench::BenchArgs),
+    /// Print a shell completion script
+    Completions(commands::completions::CompletionsArgs),
+    /// Print a man page
+    Manpage,
+}
 
-    let (tx, rx) = mpsc::channel();
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Colored tables for the terminal
+    Table,
+    /// Structured JSON, e.g. for piping into jq
+    Json,
+    /// One compact JSON object per line, per sample in --live mode (e.g.
+    /// for log shippers)
+    Ndjson,
+    /// Comma-separated values, one row per user
+    Csv,
+    /// Tab-separated values, one row per user
+    Tsv,
+    /// A Nagios/Icinga plugin status line with perfdata, exiting 0 (OK),
+    /// 1 (WARNING: users over fair share), or 2 (CRITICAL: excessive load)
+    Nagios,
+}
 
-    ctrlc::set_handler(move || {
-        tx.send(()).expect("Could not send signal on channel.");
-    })
-    .expect("Error setting Ctrl-C handler");
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
 
-    loop {
-        if cli.live {
-            print!("\x1B[2J\x1B[1;1H");
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Policy {
+    /// 100% / number of active users
+    Equal,
+    /// The percent given with --fair-share
+    Fixed,
+    /// Active users split 100% in proportion to --user-weights and
+    /// --group-weights
+    Weighted,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortColumn {
+    /// Total CPU usage
+    Cpu,
+    /// Resident memory
+    Mem,
+    /// Username, alphabetically
+    User,
+    /// Share of system CPU
+    Share,
+    /// How far over fair share
+    Excess,
+    /// Disk bytes read and written (implies --io)
+    Io,
+    /// Network bytes sent and received (implies --net)
+    Net,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Window {
+    #[value(name = "1")]
+    One,
+    #[value(name = "5")]
+    Five,
+    #[value(name = "15")]
+    Fifteen,
+}
+
+impl From<Window> for LoadWindow {
+    fn from(window: Window) -> Self {
+        match window {
+            Window::One => LoadWindow::One,
+            Window::Five => LoadWindow::Five,
+            Window::Fifteen => LoadWindow::Fifteen,
         }
+    }
+}
 
-        let start_time = Instant::now();
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Grouping {
+    /// Users
+    User,
+    /// Docker, Podman, containerd, and LXC containers, read from each
+    /// process's cgroup (processes outside any container are grouped by
+    /// user)
+    Container,
+    /// Primary Unix groups, or the groups given with --group-map
+    Group,
+    /// Ranges of UIDs given with --uid-ranges, by default system accounts,
+    /// people, and service accounts, for a quick look at who the load is
+    /// from
+    UidRange,
+}
 
-        let mut sys = System::new_all();
-        sys.refresh_all();
-        let cpus = sys.cpus().len() as f64;
+impl From<Grouping> for GroupBy {
+    fn from(grouping: Grouping) -> Self {
+        match grouping {
+            Grouping::User => GroupBy::User,
+            Grouping::Container => GroupBy::Container,
+            Grouping::Group => GroupBy::Group,
+            Grouping::UidRange => GroupBy::UidRange,
+        }
+    }
+}
 
-        // Create a mapping of user IDs to usernames
-        let users = Users::new_with_refreshed_list();
-        let uid_to_name: std::collections::HashMap<_, _> = users
-            .iter()
-            .map(|user| (user.id().to_string(), user.name().to_string()))
-            .collect();
+impl From<SortColumn> for SortBy {
+    fn from(column: SortColumn) -> Self {
+        match column {
+            SortColumn::Cpu => SortBy::Cpu,
+            SortColumn::Mem => SortBy::Mem,
+            SortColumn::User => SortBy::User,
+            SortColumn::Share => SortBy::Share,
+            SortColumn::Excess => SortBy::Excess,
+            SortColumn::Io => SortBy::Io,
+            SortColumn::Net => SortBy::Net,
+        }
+    }
+}
 
-        let mut user_cpu_usage: Vec<(String, f64)> = sys
-            .processes()
-            .values()
-            .filter_map(|p| {
-                let username = p
-                    .user_id()
-                    .and_then(|uid| uid_to_name.get(&uid.to_string()).cloned())
-                    .unwrap_or_else(|| {
-                        format!(
-                            "UID:{}",
-                            p.user_id()
-                                .map_or("Unknown".to_string(), |uid| uid.to_string())
-                        )
-                    });
-                Some((username, p.cpu_usage()))
-            })
-            .fold(
-                std::collections::HashMap::new(),
-                |mut acc, (username, usage)| {
-                    *acc.entry(username).or_insert(0.0) += usage as f64;
-                    acc
-                },
-            )
-            .into_iter()
-            .collect();
+/// Parse a percent of total cores, e.g. `80` or `80%`, or a number of cores,
+/// e.g. `12c` or `12 cores`.
+fn parse_amount(s: &str) -> Result<Quota, String> {
+    match s.trim().parse::<f64>() {
+        Ok(percent) if percent.is_finite() && percent >= 0.0 => Ok(Quota::Percent(percent)),
+        Ok(_) => Err(format!("must be non-negative, got {}", s)),
+        Err(_) => s.parse(),
+    }
+}
 
-        user_cpu_usage.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+/// Parse a `user=group` pair.
+fn parse_mapping(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((user, group)) if !user.trim().is_empty() && !group.trim().is_empty() => {
+            Ok((user.trim().to_string(), group.trim().to_string()))
+        }
+        _ => Err(format!("expected USER=GROUP, got '{}'", s)),
+    }
+}
 
-        let active_users = user_cpu_usage
-            .iter()
-            .filter(|(_, usage)| *usage / cpus > cli.active_threshold)
-            .count() as f64;
-        let fair_share = cli.fair_share.unwrap_or(100.0 / active_users);
-
-        // Print fair share information
-        println!("\nFair Share Calculation:");
-        if cli.fair_share.is_some() {
-            println!("Using user-specified fair share: {:.2}%", fair_share);
-        } else {
-            println!("Using active users calculation:");
-            println!(
-                "  Active users (usage > {:.2}%): {}",
-                cli.active_threshold, active_users
-            );
-            println!(
-                "  Fair share = 100% / {} = {:.2}%\n",
-                active_users, fair_share
-            );
+/// Parse a `name=value` HTTP header.
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
         }
+        _ => Err(format!("expected NAME=VALUE, got '{}'", s)),
+    }
+}
 
-        let mut table = Table::new();
-        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-        table.set_titles(Row::new(vec![
-            Cell::new("Username"),
-            Cell::new("Total CPU Usage (%)"),
-            Cell::new("Equivalent Cores Used"),
-            Cell::new("System CPU Share (%)"),
-        ]));
+/// Parse a `name=weight` pair. Weights must be positive: a zero weight
+/// gets a share of nothing, and if every active user had one, the share per
+/// unit of weight would be 100% / 0.
+fn parse_weight(s: &str) -> Result<(String, f64), String> {
+    let (name, weight) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=WEIGHT, got '{}'", s))?;
+    let weight: f64 = weight
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid weight '{}'", weight))?;
+    if !(weight.is_finite() && weight > 0.0) {
+        return Err(format!("weight must be positive, got {}", weight));
+    }
+    Ok((name.trim().to_string(), weight))
+}
 
-        for (user, sum) in &user_cpu_usage {
-            if sum > &0.0 {
-                let cpu_share = sum / cpus;
-                let row_color = if cpu_share > fair_share {
-                    "red".to_string()
-                } else if cpu_share > fair_share * 0.5 {
-                    "yellow".to_string()
-                } else {
-                    "green".to_string()
-                };
+/// Parse an EWMA weight, which must be in (0, 1].
+fn parse_alpha(s: &str) -> Result<f64, String> {
+    let alpha: f64 = s.parse().map_err(|_| format!("invalid number '{}'", s))?;
+    if alpha > 0.0 && alpha <= 1.0 {
+        Ok(alpha)
+    } else {
+        Err(format!(
+            "must be between 0 (exclusive) and 1, got {}",
+            alpha
+        ))
+    }
+}
+
+/// A sampler configured by the command line, reporting on `scenario` if
+/// given instead of this machine.
+fn sampler(cli: &Cli, scenario: Option<Scenario>) -> Result<Sampler, Error> {
+    // Thresholds and fair shares given in cores are converted to percents
+    // of the machine's cores.
+    let cores = scenario.as_ref().map_or_else(
+        || {
+            use sysinfo::{CpuRefreshKind, RefreshKind, System};
+            System::new_with_specifics(RefreshKind::new().with_cpu(CpuRefreshKind::new()))
+                .cpus()
+                .len()
+        },
+        |scenario| scenario.cores,
+    ) as f64;
+    let policy = policy(cli, cores).map_err(Error::usage)?;
+    let quotas = cli
+        .quotas
+        .as_ref()
+        .map(Quotas::load)
+        .transpose()
+        .context("Could not read quotas file")?
+        .unwrap_or_default();
+    quotas.fit(cores).map_err(Error::usage)?;
+    let columns = &cli.columns;
+    let mut sampler = Sampler::new()
+        .threshold(cli.threshold.share(cores))
+        .hysteresis(cli.hysteresis)
+        .load_window(cli.load_window.into())
+        .min_dwell(Duration::from_secs(cli.min_dwell))
+        .active_threshold(cli.active_threshold)
+        .policy(policy)
+        .quotas(quotas)
+        .exempt_users(cli.exempt_users.iter().cloned())
+        .exclude_users(cli.exclude_users.iter().cloned())
+        .exclude_groups(cli.exclude_groups.iter().cloned())
+        .only_users(cli.user.iter().cloned())
+        .group_by(cli.group_by.into())
+        .group_map(cli.group_map.iter().cloned())
+        .grace_period(Duration::from_secs(cli.grace_period))
+        .window(Duration::from_millis(cli.sample_window))
+        .samples(
+            cli.samples as usize,
+            Duration::from_millis(cli.sample_interval),
+        )
+        .processes(cli.processes.unwrap_or(
+            if cli.user.is_empty() && !cli.suggest_renice && !cli.enforce {
+                0
+            } else {
+                5
+            },
+        ))
+        .gpu(
+            cli.gpu
+                || columns
+                    .iter()
+                    .any(|c| matches!(c, Column::Gpu | Column::GpuMem)),
+        )
+        .slurm(cli.slurm)
+        .k8s(cli.k8s)
+        .per_core(cli.per_core)
+        .cumulative(cli.cumulative || columns.contains(&Column::CpuTime))
+        .logins(cli.logins || columns.contains(&Column::Login))
+        .zombie_threshold(cli.zombie_threshold)
+        .max_runtime(cli.max_runtime.map(Duration::from_secs))
+        .batch_nice(cli.batch_nice, cli.batch_cores)
+        .new_heavy(cli.new_heavy)
+        .resolve_uids(cli.resolve_uids)
+        .merge_unknown(cli.merge_unknown)
+        .io(cli.io
+            || cli.sort_by == SortColumn::Io
+            || columns
+                .iter()
+                .any(|c| matches!(c, Column::Read | Column::Write)))
+        .swap(cli.swap || columns.contains(&Column::Swap))
+        .fds(cli.fds || columns.contains(&Column::Fds))
+        .nice(cli.nice || columns.contains(&Column::Nice))
+        .host(cli.header)
+        .forecast(cli.forecast)
+        .cgroup_limits(
+            cli.cgroup_limits
+                || columns
+                    .iter()
+                    .any(|c| matches!(c, Column::Limit | Column::OfLimit)),
+        )
+        .net(
+            cli.net
+                || cli.sort_by == SortColumn::Net
+                || columns
+                    .iter()
+                    .any(|c| matches!(c, Column::Sent | Column::Received)),
+        );
+    if let Some(scenario) = scenario {
+        sampler = sampler.simulate(scenario);
+    }
+    if !cli.uid_ranges.is_empty() {
+        sampler = sampler.uid_ranges(cli.uid_ranges.iter().cloned());
+    }
+    if let Some(command) = &cli.command {
+        sampler = sampler.command(command.clone());
+    }
+    if !cli.ignore_command.is_empty() {
+        sampler = sampler.ignore_commands(cli.ignore_command.iter().cloned());
+    }
+    if cli.k8s {
+        let token = match &cli.kubelet_token_file {
+            Some(path) => Some(fs::read_to_string(path).context("Could not read kubelet token")?),
+            None => fs::read_to_string(k8s::SERVICE_ACCOUNT_TOKEN).ok(),
+        };
+        sampler = sampler.kubelet(Kubelet {
+            url: cli.kubelet_url.clone(),
+            token: token.map(|token| token.trim().to_string()),
+        });
+    }
+    if cli.contacts.is_some() || cli.gecos {
+        let contacts = cli
+            .contacts
+            .as_ref()
+            .map(Contacts::load)
+            .transpose()
+            .context("Could not read contacts file")?
+            .unwrap_or_default();
+        sampler = sampler.contacts(Contacts {
+            gecos: cli.gecos,
+            ..contacts
+        });
+    }
+    if let Some(alpha) = cli.ewma {
+        sampler = sampler.ewma(alpha);
+    }
+    if let Some(warn_threshold) = cli.warn_threshold {
+        sampler = sampler.warn_threshold(warn_threshold.share(cores));
+    }
+    if cli.spikes {
+        sampler = sampler.spikes(cli.spike_sigmas, cli.spike_cores);
+    }
+    Ok(sampler)
+}
+
+/// The fair share policy selected by --policy and --fair-share, on a machine
+/// with `cores` cores.
+fn policy(cli: &Cli, cores: f64) -> Result<FairSharePolicy, String> {
+    match (cli.policy, cli.fair_share) {
+        (None, None) | (Some(Policy::Equal), None) => Ok(FairSharePolicy::Equal),
+        (None, Some(share)) | (Some(Policy::Fixed), Some(share)) => {
+            Ok(FairSharePolicy::Fixed(share.share(cores)))
+        }
+        (Some(Policy::Fixed), None) => Err("--policy fixed requires --fair-share".to_string()),
+        (Some(Policy::Weighted), None) => Ok(FairSharePolicy::Weighted(Weights {
+            users: cli.user_weights.iter().cloned().collect(),
+            groups: cli.group_weights.iter().cloned().collect(),
+            default: 1.0,
+        })),
+        (Some(policy), Some(_)) => Err(format!(
+            "--fair-share can't be used with --policy {}",
+            policy.to_possible_value().unwrap().get_name()
+        )),
+    }
+}
+
+/// The alert notifiers configured by the --alert-* options and --notify.
+fn notifiers(cli: &Cli) -> Result<Vec<Box<dyn Notifier>>, String> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+    if let Some(url) = &cli.alert_webhook {
+        notifiers.push(Box::new(alerts::Webhook::new(url)));
+    }
+    if !cli.alert_email.is_empty() || cli.alert_email_users.is_some() {
+        notifiers.push(Box::new(emailer(cli)?));
+    }
+    if let (Some(room), Some(homeserver), Some(token)) = (
+        &cli.alert_matrix_room,
+        &cli.alert_matrix_homeserver,
+        &cli.alert_matrix_token,
+    ) {
+        notifiers.push(Box::new(alerts::Matrix::new(homeserver, room, token)));
+    }
+    for command in &cli.alert_command {
+        notifiers.push(Box::new(alerts::Script::new(command)));
+    }
+    if cli.alert_tty {
+        notifiers.push(Box::new(alerts::Terminals));
+    }
+    if cli.notify {
+        notifiers.push(Box::new(alerts::Desktop::new(
+            loadrs::platform::current_user(),
+        )));
+    }
+    Ok(notifiers)
+}
 
-                let colored_row = Row::new(vec![
-                    Cell::new(&user)
-                        .with_style(Attr::ForegroundColor(color_from_string(&row_color))),
-                    Cell::new(&format!("{:.2}", sum))
-                        .with_style(Attr::ForegroundColor(color_from_string(&row_color))),
-                    Cell::new(&format!("{:.2}", sum / 100.0))
-                        .with_style(Attr::ForegroundColor(color_from_string(&row_color))),
-                    Cell::new(&format!("{:.2}", cpu_share))
-                        .with_style(Attr::ForegroundColor(color_from_string(&row_color))),
-                ]);
+/// The email alert sender configured by the --alert-* options.
+fn emailer(cli: &Cli) -> Result<Emailer, String> {
+    let smtp = SmtpConfig {
+        server: cli.alert_smtp_server.as_deref().unwrap_or_default(),
+        port: cli.alert_smtp_port,
+        security: cli.alert_smtp_security,
+        username: cli.alert_smtp_username.as_deref(),
+        password: cli.alert_smtp_password.as_deref(),
+    };
+    let from = cli.alert_from.clone().unwrap_or_else(|| {
+        let host = sysinfo::System::host_name().unwrap_or_else(|| "localhost".to_string());
+        format!("loadrs@{}", host)
+    });
+    Emailer::new(
+        &smtp,
+        &from,
+        &cli.alert_email,
+        cli.alert_email_users.as_deref(),
+    )
+}
+
+fn main() {
+    let cmd = config::apply(Cli::command()).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(2);
+    });
+    let cli = Cli::from_arg_matches(&cmd.get_matches()).unwrap_or_else(|e| e.exit());
+
+    match cli.color {
+        // Log lines are read by log collectors, not terminals.
+        _ if cli.log || cli.ascii => colored::control::set_override(false),
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+        // colored checks for a terminal and CLICOLOR, but not an empty NO_COLOR.
+        ColorChoice::Auto if env::var_os("NO_COLOR").is_some() => {
+            colored::control::set_override(false)
+        }
+        ColorChoice::Auto => {}
+    }
+
+    table::set_ascii(cli.ascii);
+
+    match run(cli) {
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+/// Run the subcommand, or sample and report, returning the code to exit
+/// with.
+fn run(cli: Cli) -> Result<i32, Error> {
+    if cli.explain && cli.format != Format::Table {
+        return Err(Error::usage("--explain only works with table output"));
+    }
+    // A plugin prints one status line and exits with its code. (Clap already
+    // refuses --format with --log, --tui, and --serve-metrics.)
+    let repeats = cli.live || matches!(cli.subcommand, Some(Command::Replay(_)));
+    if cli.format == Format::Nagios && repeats {
+        return Err(Error::usage(
+            "--format nagios reports once, so it doesn't work with --live or load replay",
+        ));
+    }
 
-                table.add_row(colored_row);
+    match &cli.subcommand {
+        Some(Command::History(args)) => {
+            commands::history::run(args).context("Could not read history")?;
+            return Ok(0);
+        }
+        Some(Command::Analyze(args)) => {
+            commands::analyze::run(args, &cli).context("Could not analyze usage")?;
+            return Ok(0);
+        }
+        Some(Command::Export(args)) => {
+            commands::export::run(args).context("Could not export usage")?;
+            return Ok(0);
+        }
+        #[cfg(unix)]
+        Some(Command::Status(args)) => {
+            let mut report = commands::status::run(args).context("Could not get status")?;
+            sort_users(&mut report, &cli);
+            return print_report(&report, &cli, !cli.no_header);
+        }
+        Some(Command::Cluster(args)) => {
+            commands::cluster::run(args, &cli).context("Could not read hosts file")?;
+            return Ok(0);
+        }
+        Some(Command::Remote(args)) => {
+            return commands::remote::run(args, &cli).context("Could not sample remote hosts");
+        }
+        Some(Command::Bench(args)) => {
+            commands::bench::run(args, &cli).context("Could not run benchmark")?;
+            return Ok(0);
+        }
+        Some(Command::Completions(args)) => {
+            commands::completions::run(args, &mut Cli::command().name("load"));
+            return Ok(0);
+        }
+        Some(Command::Manpage) => {
+            commands::manpage::run(Cli::command().name("load"))
+                .context("Could not print man page")?;
+            return Ok(0);
+        }
+        Some(Command::Fairshare(args)) => {
+            commands::fairshare::run(args, cli.half_life)
+                .context("Could not read fair-share file")?;
+            return Ok(0);
+        }
+        #[cfg(unix)]
+        Some(Command::InstallService(args)) => {
+            commands::service::run(args, cli.config.as_deref()).context("Could not write unit")?;
+            return Ok(0);
+        }
+        Some(Command::Replay(args)) => {
+            commands::replay::run(args, &cli).context("Could not replay session")?;
+            return Ok(0);
+        }
+        Some(Command::Diff(args)) => {
+            commands::diff::run(args).context("Could not compare snapshots")?;
+            return Ok(0);
+        }
+        Some(Command::Report(
+            args @ commands::report::ReportArgs {
+                session: Some(path),
+                ..
+            },
+        )) => {
+            let mut reports =
+                commands::replay::read_session(path).context("Could not read session")?;
+            for report in &mut reports {
+                sort_users(report, &cli);
             }
+            commands::report::run(args, &reports).context("Could not write report")?;
+            return Ok(0);
         }
+        #[cfg(unix)]
+        Some(Command::Daemon(_)) => {}
+        Some(Command::Agent(_))
+        | Some(Command::Serve(_))
+        | Some(Command::Report(_))
+        | Some(Command::Motd(_))
+        | Some(Command::Tree(_))
+        | None => {}
+    }
+
+    let scenario = cli
+        .simulate
+        .as_ref()
+        .map(Scenario::load)
+        .transpose()
+        .map_err(|e| Error::usage(e.to_string()))?;
+    let mut sampler = sampler(&cli, scenario)?;
+
+    match &cli.subcommand {
+        #[cfg(unix)]
+        Some(Command::Daemon(args)) => {
+            commands::daemon::run(args, sampler, Duration::from_secs(cli.interval), cli.log)
+                .context("Could not run daemon")?;
+            return Ok(0);
+        }
+        Some(Command::Agent(args)) => {
+            commands::agent::run(args, sampler, Duration::from_secs(cli.interval))
+                .context("Could not run agent")?;
+            return Ok(0);
+        }
+        Some(Command::Serve(args)) => {
+            commands::serve::run(args, sampler, Duration::from_secs(cli.interval))
+                .context("Could not serve the API")?;
+            return Ok(0);
+        }
+        Some(Command::Motd(args)) => {
+            let mut report = sampler.sample();
+            sort_users(&mut report, &cli);
+            commands::motd::run(args, &report).context("Could not write motd")?;
+            return Ok(0);
+        }
+        Some(Command::Tree(args)) => {
+            let report = sampler
+                .group_by(GroupBy::User)
+                .only_users([args.user.clone()])
+                .processes(usize::MAX)
+                .sample();
+            commands::tree::run(args, &report).context("Could not show process tree")?;
+            return Ok(0);
+        }
+        Some(Command::Report(args)) => {
+            let mut report = sampler.sample();
+            sort_users(&mut report, &cli);
+            commands::report::run(args, &[report]).context("Could not write report")?;
+            return Ok(0);
+        }
+        _ => {}
+    }
 
-        table.printstd();
+    if cli.tui {
+        tui::run(
+            sampler,
+            Duration::from_secs(cli.interval),
+            cli.sort_by.into(),
+            (cli.warn_ratio, cli.crit_ratio),
+        )
+        .context("Could not run interactive view")?;
+        return Ok(0);
+    }
 
-        println!("\nTotal cores: {}", cpus as u32);
-        let loadavg = System::load_average();
-        println!("1 minute load average: {:.2}", loadavg.one);
+    if let Some(addr) = &cli.serve_metrics {
+        metrics::serve(sampler, Duration::from_secs(cli.interval), addr)
+            .context("Could not serve metrics")?;
+        return Ok(0);
+    }
 
-        if loadavg.one > (cli.threshold / 100.) * cpus {
-            println!("\n{}", "Excessive load detected!".red().bold());
-            println!("Users exceeding fair share ({}%):", fair_share);
-            let mut table = Table::new();
-            table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-            table.set_titles(Row::new(vec![
-                Cell::new("Username"),
-                Cell::new("System CPU Share (%)"),
-                Cell::new("Excess Usage (%)"),
-            ]));
-            for (user, sum) in user_cpu_usage {
-                if sum / cpus > fair_share {
-                    table.add_row(Row::new(vec![
-                        Cell::new(&user),
-                        Cell::new(&format!("{:.2}%", sum / cpus)),
-                        Cell::new(&format!("{:.2}%", (sum / cpus) - fair_share)),
-                    ]));
+    if cli.check {
+        let report = sampler.sample();
+        return Ok(check(&report, &cli));
+    }
+
+    if cli.enforce && !enforce::is_root() {
+        return Err(Error::usage("--enforce requires root"));
+    }
+    if cli.enforce_cgroup && !enforce::is_root() {
+        return Err(Error::usage("--enforce-cgroup requires root"));
+    }
+    if cli.enforce_cgroup && cli.group_by != Grouping::User {
+        return Err(Error::usage(
+            "--enforce-cgroup only works with --group-by user",
+        ));
+    }
+
+    // Printing to a closed pipe (e.g. `load --live --log | head`) should end
+    // `load` quietly, as it does other command line tools, rather than
+    // panicking. This is only safe now that no servers are running, which
+    // must survive clients hanging up.
+    #[cfg(unix)]
+    // SAFETY: restoring a signal's default disposition has no preconditions.
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+
+    let (tx, rx) = mpsc::channel();
+
+    // If the loop has already ended, there's nothing left to interrupt.
+    if let Err(e) = ctrlc::set_handler(move || {
+        let _ = tx.send(());
+    }) {
+        eprintln!("Warning: Ctrl-C won't exit cleanly: {}", e);
+    }
+
+    let mut history = cli
+        .record
+        .as_ref()
+        .map(History::open)
+        .transpose()
+        .context("Could not open history database")?;
+
+    let mut ledger = cli
+        .fairshare_file
+        .as_ref()
+        .map(Ledger::load)
+        .transpose()
+        .context("Could not read fair-share file")?;
+
+    let mut session = cli
+        .record_session
+        .as_ref()
+        .map(|path| OpenOptions::new().create(true).append(true).open(path))
+        .transpose()
+        .context("Could not open session file")?;
+
+    let mut log_file = cli
+        .log_file
+        .as_ref()
+        .map(|path| {
+            logfile::RotatingLog::open(
+                path,
+                cli.log_rotate_size,
+                cli.log_rotate_every,
+                cli.log_keep,
+            )
+        })
+        .transpose()
+        .context("Could not open log file")?;
+
+    let statsd = cli
+        .statsd
+        .as_deref()
+        .map(|addr| statsd::Statsd::connect(addr, cli.statsd_flavor))
+        .transpose()
+        .context("Could not set up StatsD")?;
+
+    let mut graphite = cli
+        .graphite
+        .as_deref()
+        .map(|addr| graphite::Graphite::connect(addr, &cli.graphite_prefix))
+        .transpose()
+        .context("Could not connect to Graphite")?;
+
+    let otel = cli
+        .otel_endpoint
+        .as_deref()
+        .map(|endpoint| otel::Exporter::new(endpoint, cli.otel_header.clone()));
+
+    let notifiers = notifiers(&cli).map_err(Error::usage)?;
+    let mut alerter =
+        (!notifiers.is_empty()).then(|| Alerter::new(Duration::from_secs(cli.alert_cooldown)));
+
+    let mut logger = log::Logger::default();
+    let mut throttler = cli.enforce_cgroup.then(enforce::Throttler::default);
+    let mut header = !cli.no_header;
+    let mut status = 0;
+    let mut loads = VecDeque::with_capacity(LOAD_HISTORY);
+    let mut usual: Option<(Instant, Option<HashMap<String, f64>>)> = None;
+    let mut pacer = pace::Pacer::new(
+        Duration::from_secs(cli.interval),
+        Duration::from_secs(cli.min_interval),
+        Duration::from_secs(cli.max_interval),
+    );
+    loop {
+        if cli.live && cli.format == Format::Table && !cli.log && !cli.quiet {
+            print!("\x1B[2J\x1B[1;1H");
+        }
+
+        let start_time = Instant::now();
+
+        let mut report = sampler.sample();
+        sort_users(&mut report, &cli);
+        let interval = pacer.update(Duration::from_secs_f64(report.sampling_seconds));
+        if loads.len() == LOAD_HISTORY {
+            loads.pop_front();
+        }
+        loads.push_back(report.load_average.one);
+        if let (Some(ledger), Some(path)) = (&mut ledger, &cli.fairshare_file) {
+            ledger.record(&mut report, cli.half_life);
+            if let Err(e) = ledger.save(path) {
+                eprintln!("Error saving fair-share file: {}", e);
+            }
+        }
+        if let Some(history) = &history {
+            if usual
+                .as_ref()
+                .is_none_or(|(at, _)| at.elapsed() >= BASELINE_REFRESH)
+            {
+                let since = report.timestamp.saturating_sub(cli.baseline);
+                match history.usual_cores(since) {
+                    Ok(cores) => usual = Some((Instant::now(), cores)),
+                    Err(e) => eprintln!("Error reading usual usage: {}", e),
+                }
+            }
+            if let Some((_, Some(cores))) = &usual {
+                for user in &mut report.users {
+                    user.usual_cores = Some(cores.get(&user.username).copied().unwrap_or(0.0));
                 }
             }
-            table.printstd();
         }
+        if let Some(session) = &mut session {
+            let line = serde_json::to_string(&report).expect("Could not serialize report.");
+            if let Err(e) = writeln!(session, "{}", line) {
+                eprintln!("Error recording session: {}", e);
+            }
+        }
+        if let Some(path) = &cli.state_file {
+            if let Err(e) = write_state(path, &report) {
+                eprintln!("Error writing state file: {}", e);
+            }
+        }
+        if let Some(history) = &mut history {
+            if let Err(e) = history.record(&report) {
+                eprintln!("Error recording sample: {}", e);
+            }
+        }
+        if let Some(log_file) = &mut log_file {
+            if let Err(e) = log_file.append(&report) {
+                eprintln!("Error writing log file: {}", e);
+            }
+        }
+        if let Some(statsd) = &statsd {
+            if let Err(e) = statsd.send(&report) {
+                eprintln!("Error sending to StatsD: {}", e);
+            }
+        }
+        if let Some(graphite) = &mut graphite {
+            if let Err(e) = graphite.send(&report) {
+                eprintln!("Error sending to Graphite: {}", e);
+            }
+        }
+        if let Some(otel) = &otel {
+            if let Err(e) = otel.send(&report) {
+                eprintln!("Error sending to OpenTelemetry collector: {}", e);
+            }
+        }
+        if let Some(alerter) = &mut alerter {
+            for alert in alerter.check(&report) {
+                for notifier in &notifiers {
+                    if let Err(e) = notifier.send(&alert) {
+                        eprintln!("Error sending alert ({}): {}", notifier.name(), e);
+                    }
+                }
+            }
+        }
+        if cli.log {
+            logger.print(&report);
+        } else {
+            status = print_report(&report, &cli, header)?;
+        }
+        let targets = enforce::targets(&report);
+        let suggest = cli.suggest_renice && cli.format == Format::Table && !cli.log;
+        if suggest && !targets.is_empty() {
+            enforce::suggest(
+                "Suggested commands for users over their fair share:",
+                &targets,
+                cli.renice_to,
+            );
+        }
+        let unniced = enforce::unniced(&report);
+        if suggest && !unniced.is_empty() {
+            let nice = cli.batch_nice.unwrap_or_default();
+            enforce::suggest(
+                &format!("Suggested commands for batch work below nice {}:", nice),
+                &unniced,
+                nice,
+            );
+        }
+        if cli.enforce {
+            enforce::enforce(&targets, cli.renice_to);
+        }
+        if let Some(throttler) = &mut throttler {
+            throttler.update(&report);
+        }
+        if cli.live && cli.format == Format::Table && !cli.log && !cli.quiet {
+            print_load_history(&loads, report.total_cores);
+            if pacer.backed_off() {
+                println!(
+                    "{}",
+                    format!(
+                        "Sampling took {:.2}s, so updating every {}s instead of every {}s",
+                        report.sampling_seconds,
+                        interval.as_secs(),
+                        cli.interval
+                    )
+                    .yellow()
+                );
+            }
+        }
+        // Only print the CSV/TSV header once in live mode.
+        header = false;
 
         if !cli.live {
             break;
         }
 
         let elapsed = start_time.elapsed();
-        let sleep_duration = Duration::from_secs(cli.interval).saturating_sub(elapsed);
+        let sleep_duration = interval.saturating_sub(elapsed);
 
         if sleep_duration > Duration::from_millis(0) {
             match rx.recv_timeout(sleep_duration) {
                 Ok(_) => {
-                    println!("Received interrupt, exiting...");
+                    eprintln!("Received interrupt, exiting...");
                     break;
                 }
                 Err(mpsc::RecvTimeoutError::Timeout) => continue,
@@ -193,14 +1433,1307 @@ fn main() {
         }
     }
 
-    println!("Exiting...");
+    // Limits only make sense while we're watching.
+    if let Some(throttler) = &mut throttler {
+        throttler.release();
+    }
+
+    // Cron mails any output, including on stderr, and a plugin's output is
+    // only its status line.
+    if !cli.quiet && cli.format != Format::Nagios {
+        eprintln!("Exiting...");
+    }
+    Ok(status)
+}
+
+/// Print which machine a report is from, and when, with --header.
+fn print_host(timestamp: u64, host: &Host) {
+    println!(
+        "\n{} at {}, up {}",
+        host.hostname.bold(),
+        time::format_rfc3339(timestamp),
+        format_duration(host.uptime)
+    );
+    let kernel = host
+        .kernel
+        .as_ref()
+        .map(|kernel| format!("kernel {}", kernel));
+    let system: Vec<String> = host.os.iter().cloned().chain(kernel).collect();
+    if !system.is_empty() {
+        println!("{}", system.join(", "));
+    }
+}
+
+/// Replace the --state-file with `report`. Readers never see a half-written
+/// file, since it's written next to it and renamed into place.
+fn write_state(path: &Path, report: &LoadReport) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("loadrs-tmp");
+    let json = serde_json::to_string(report).expect("Could not serialize report.");
+    fs::write(&tmp, json + "\n")?;
+    fs::rename(&tmp, path)
+}
+
+/// Sort users as given by --sort-by and --reverse.
+fn sort_users(report: &mut LoadReport, cli: &Cli) {
+    // UID ranges stay in the order they're given.
+    if report.group_by == GroupBy::UidRange {
+        return;
+    }
+    report.sort_users(cli.sort_by.into());
+    if cli.reverse {
+        report.users.reverse();
+    }
+}
+
+/// Print the report in the selected format, with a header row for CSV and
+/// TSV if `header`, and return the code to exit with: the plugin's status
+/// for --format nagios, or else 0.
+fn print_report(report: &LoadReport, cli: &Cli, header: bool) -> Result<i32, Error> {
+    if cli.log {
+        log::Logger::default().print(report);
+        return Ok(0);
+    }
+    match cli.format {
+        Format::Table => print_tables(report, cli),
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(report).context("Could not serialize report")?
+        ),
+        Format::Ndjson => println!(
+            "{}",
+            serde_json::to_string(report).context("Could not serialize report")?
+        ),
+        Format::Csv => delimited::print(report, b',', header).context("Could not write CSV")?,
+        Format::Tsv => delimited::print(report, b'\t', header).context("Could not write TSV")?,
+        Format::Nagios => return Ok(nagios(report, cli)),
+    }
+    Ok(0)
+}
+
+/// Print a Nagios plugin status line with perfdata and return the plugin's
+/// exit code.
+fn nagios(report: &LoadReport, cli: &Cli) -> i32 {
+    let (status, code) = if report.excessive_load {
+        ("CRITICAL", 2)
+    } else if report.load_state == LoadState::Warn || !report.offenders.is_empty() {
+        ("WARNING", 1)
+    } else {
+        ("OK", 0)
+    };
+    let critical_load = report.threshold / 100.0 * report.total_cores as f64;
+    let warning_load = cli
+        .warn_threshold
+        .map_or(0.8 * report.threshold, |warn| {
+            warn.share(report.total_cores as f64)
+        })
+        .min(report.threshold)
+        / 100.0
+        * report.total_cores as f64;
+    // Warning and critical levels go on the load average that decides them.
+    let load_perfdata = |window: LoadWindow| {
+        let levels = if window == report.load_window {
+            format!("{:.2};{:.2}", warning_load, critical_load)
+        } else {
+            ";".to_string()
+        };
+        format!(
+            "load{}={:.2};{};0;",
+            window.minutes(),
+            report.load_average.get(window),
+            levels
+        )
+    };
+    let mut perfdata = vec![
+        load_perfdata(LoadWindow::One),
+        load_perfdata(LoadWindow::Five),
+        load_perfdata(LoadWindow::Fifteen),
+        format!("cores={}", report.total_cores),
+        format!("active_users={}", report.fair_share.active_users),
+        format!("users_over_fair_share={}", report.offenders.len()),
+    ];
+    if report.fair_share.value.is_finite() {
+        perfdata.push(format!(
+            "fair_share={:.2}%;;;0;100",
+            report.fair_share.value
+        ));
+    }
+    for user in &report.users {
+        let fair_share = if user.fair_share.is_finite() {
+            format!("{:.2}", user.fair_share)
+        } else {
+            String::new()
+        };
+        perfdata.push(format!(
+            "'{}'={:.2}%;{};;0;100",
+            user.username.replace('\'', "''"),
+            user.system_cpu_share,
+            fair_share
+        ));
+    }
+    println!(
+        "{}: load {:.2} on {} cores; {} users over fair share | {}",
+        status,
+        report.load(),
+        report.total_cores,
+        report.offenders.len(),
+        perfdata.join(" ")
+    );
+    code
+}
+
+/// Print a one-line summary of the report and return the exit code.
+fn check(report: &LoadReport, cli: &Cli) -> i32 {
+    let (status, code) = if report.excessive_load {
+        ("EXCESSIVE LOAD", cli.load_exit_code)
+    } else if !report.offenders.is_empty() {
+        ("FAIR SHARE EXCEEDED", cli.fair_share_exit_code)
+    } else {
+        ("OK", 0)
+    };
+    let offenders: Vec<String> = report
+        .offenders
+        .iter()
+        .map(|o| format!("{} ({:.2}%)", o.username, o.system_cpu_share))
+        .collect();
+    println!(
+        "{}: load {:.2} on {} cores (threshold {:.2}%, {:.2} cores); {} users over fair share ({:.2}%{}){}{}",
+        status,
+        report.load(),
+        report.total_cores,
+        report.threshold,
+        report.threshold / 100.0 * report.total_cores as f64,
+        offenders.len(),
+        report.fair_share.value,
+        if report.fair_share.policy == PolicyKind::Weighted {
+            " per unit of weight"
+        } else {
+            ""
+        },
+        if offenders.is_empty() { "" } else { ": " },
+        offenders.join(", ")
+    );
+    code
+}
+
+/// The capacity active users without quotas split, e.g. `100%`, or
+/// `(100% - 25.00% reserved)` when users with quotas are active.
+fn capacity(report: &LoadReport) -> String {
+    if report.fair_share.reserved > 0.0 {
+        format!("(100% - {:.2}% reserved)", report.fair_share.reserved)
+    } else {
+        "100%".to_string()
+    }
+}
+
+/// Print how the fair share was computed.
+fn print_fair_share(report: &LoadReport) {
+    let fair_share = report.fair_share.value;
+    let fair_cores = fair_share / 100.0 * report.total_cores as f64;
+    println!("\nFair Share Calculation:");
+    match report.fair_share.policy {
+        PolicyKind::Fixed => println!(
+            "Using user-specified fair share: {:.2}% ({:.2} cores)",
+            fair_share, fair_cores
+        ),
+        PolicyKind::Equal => {
+            println!("Using active users calculation:");
+            println!(
+                "  Active users (usage > {:.2}%): {}",
+                report.fair_share.active_threshold, report.fair_share.active_users
+            );
+            println!(
+                "  Fair share = {} / {} = {:.2}% ({:.2} cores)\n",
+                capacity(report),
+                report.fair_share.active_weight,
+                fair_share,
+                fair_cores
+            );
+        }
+        PolicyKind::Weighted => {
+            println!("Using weighted active users calculation:");
+            println!(
+                "  Active users (usage > {:.2}%): {}, total weight {}",
+                report.fair_share.active_threshold,
+                report.fair_share.active_users,
+                report.fair_share.active_weight
+            );
+            println!(
+                "{}",
+                table::plain(format!(
+                    "  Fair share = {} × weight / {} = {:.2}% ({:.2} cores) per unit of weight\n",
+                    capacity(report),
+                    report.fair_share.active_weight,
+                    fair_share,
+                    fair_cores
+                ))
+            );
+        }
+    }
+}
+
+/// Print the usage of each UID range, with every range given a row even if
+/// nothing runs in it.
+fn print_uid_ranges(report: &LoadReport) {
+    let mut table = table::new([
+        table::group_title(report.group_by),
+        "Processes",
+        "CPU Usage (%)",
+        "Equivalent Cores",
+        "System CPU Share (%)",
+        "Memory (RSS)",
+        "Memory Share (%)",
+    ]);
+    for range in &report.users {
+        table.add_row(Row::new(vec![
+            Cell::new(&range.username),
+            Cell::new(&range.process_count.to_string()),
+            Cell::new(&format!("{:.2}", range.total_cpu_usage)),
+            Cell::new(&format!("{:.2}", range.equivalent_cores)),
+            Cell::new(&format!("{:.2}", range.system_cpu_share)),
+            Cell::new(&format_bytes(range.memory)),
+            Cell::new(&format!("{:.2}", range.memory_share)),
+        ]));
+    }
+    table::print(&table);
+}
+
+/// Print the report as tables.
+fn print_tables(report: &LoadReport, cli: &Cli) {
+    if cli.quiet {
+        print_quiet(report, cli);
+        return;
+    }
+
+    if let Some(host) = &report.host {
+        print_host(report.timestamp, host);
+    }
+
+    if report.group_by == GroupBy::UidRange {
+        println!("\nUsage by UID range:");
+        print_uid_ranges(report);
+    } else {
+        print_fair_share(report);
+        table::print(&table::users(report, cli));
+    }
+
+    if report.users.iter().any(|u| !u.processes.is_empty()) {
+        println!("\nTop processes:");
+        print_processes(report);
+    }
+
+    if let Some(jobs) = &report.jobs {
+        println!("\nSlurm jobs:");
+        print_jobs(jobs, report.fair_share.active_threshold, &cli.exempt_users);
+    }
+
+    if let Some(pods) = &report.pods {
+        println!("\nKubernetes pods:");
+        print_pods(pods);
+    }
+
+    println!("\nTotal cores: {}", report.total_cores);
+    let load = &report.load_average;
+    let cores = report.total_cores.max(1) as f64;
+    println!(
+        "Load average (1, 5, 15 minutes): {:.2}, {:.2}, {:.2}",
+        load.one, load.five, load.fifteen
+    );
+    println!(
+        "Load per core: {:.2}, {:.2}, {:.2} (state from the {} minute average)",
+        load.one / cores,
+        load.five / cores,
+        load.fifteen / cores,
+        report.load_window.minutes()
+    );
+    let state = report.load_state.name();
+    let state = match report.load_state {
+        LoadState::Ok => state.green(),
+        LoadState::Warn => state.yellow(),
+        LoadState::Crit => state.red(),
+    };
+    match report.previous_load_state {
+        Some(previous) => println!("Load state: {} (was {})", state, previous.name()),
+        None => println!("Load state: {}", state),
+    }
+    if let Some(forecast) = &report.forecast {
+        println!(
+            "{}",
+            format!(
+                "At current growth (+{:.2} cores/minute), the load will exceed the threshold in {}.",
+                forecast.cores_per_minute,
+                format_minutes(forecast.seconds)
+            )
+            .yellow()
+        );
+    }
+    let memory = &report.memory;
+    println!(
+        "Memory: {} available of {}; swap: {} used of {}",
+        format_bytes(memory.available),
+        format_bytes(memory.total),
+        format_bytes(memory.swap_used),
+        format_bytes(memory.swap_total)
+    );
+    if memory.swapping {
+        println!(
+            "{}",
+            "Memory is nearly exhausted and the system is swapping; it may be thrashing or about to OOM kill processes."
+                .red()
+                .bold()
+        );
+    }
+    if let Some(hog) = &report.swap_hog {
+        println!(
+            "{}",
+            format!(
+                "{} holds {:.0}% of swapped-out memory ({}), slowing everyone down.",
+                hog.username,
+                hog.swap_share,
+                format_bytes(hog.swap)
+            )
+            .red()
+        );
+    }
+    if let Some(handles) = &report.file_handles {
+        print_file_handles(handles, report);
+    }
+
+    if let Some(wait) = &report.cpu_wait {
+        print_cpu_wait(wait);
+    }
+    if let Some(pressure) = &report.pressure {
+        print_pressure(pressure, cli.pressure_threshold);
+    }
+    if let Some(thermal) = &report.thermal {
+        print_thermal(thermal);
+    }
+
+    if let Some(cores) = &report.cores {
+        println!("\nPer-core usage:");
+        print_cores(cores);
+    }
+
+    for warning in &report.warnings {
+        println!("{}", format!("Warning: {}", warning).yellow());
+    }
+
+    for spike in &report.spikes {
+        println!(
+            "{}",
+            format!(
+                "Usage spike: {} jumped to {:.2} cores from a baseline of {:.2}",
+                spike.username, spike.equivalent_cores, spike.baseline_cores
+            )
+            .magenta()
+            .bold()
+        );
+    }
+
+    if !report.long_running.is_empty() {
+        println!(
+            "\n{}",
+            format!(
+                "Processes running longer than {}:",
+                format_duration(cli.max_runtime.unwrap_or_default())
+            )
+            .yellow()
+            .bold()
+        );
+        print_long_running(report);
+    }
+
+    if !report.new_heavy.is_empty() {
+        println!(
+            "\n{}",
+            format!(
+                "New heavy processes (started since the last sample, using at least {} cores):",
+                cli.new_heavy.unwrap_or_default()
+            )
+            .yellow()
+            .bold()
+        );
+        print_new_heavy(report);
+    }
+
+    if !report.unniced.is_empty() {
+        println!(
+            "\n{}",
+            format!(
+                "Batch work below nice {} (using at least {} cores per process):",
+                cli.batch_nice.unwrap_or_default(),
+                cli.batch_cores
+            )
+            .yellow()
+            .bold()
+        );
+        print_unniced(report);
+    }
+
+    if !report.limit_mismatches.is_empty() {
+        println!(
+            "\n{}",
+            "Users whose cgroup CPU limit doesn't match their fair share:"
+                .yellow()
+                .bold()
+        );
+        let mut table = table::new([
+            table::group_title(report.group_by),
+            "CPU Limit (cores)",
+            "Fair Share (cores)",
+        ]);
+        for mismatch in &report.limit_mismatches {
+            table.add_row(Row::new(vec![
+                Cell::new(&mismatch.username),
+                Cell::new(&format!("{:.2}", mismatch.cpu_limit)),
+                Cell::new(&format!("{:.2}", mismatch.fair_share_cores)),
+            ]));
+        }
+        table::print(&table);
+    }
+
+    if !report.zombies.is_empty() {
+        println!(
+            "\n{}",
+            "Users with many zombie processes (their parents need fixing or killing):"
+                .yellow()
+                .bold()
+        );
+        let mut table = table::new([
+            table::group_title(report.group_by),
+            "Zombies",
+            "Parent PIDs",
+        ]);
+        for zombies in &report.zombies {
+            let parents: Vec<String> = zombies.parent_pids.iter().map(u32::to_string).collect();
+            table.add_row(Row::new(vec![
+                Cell::new(&zombies.username),
+                Cell::new(&zombies.count.to_string()),
+                Cell::new(&parents.join(", ")),
+            ]));
+        }
+        table::print(&table);
+    }
+
+    if !report.memory_offenders.is_empty() {
+        println!(
+            "\nUsers exceeding their fair share of memory (100% / {} users active by CPU or memory = {:.2}%):",
+            report.fair_share.memory_active_users, report.fair_share.memory
+        );
+        let mut table = table::new([
+            table::group_title(report.group_by),
+            "Memory (RSS)",
+            "Memory Share (%)",
+            "Fair Share (%)",
+            "Excess Usage (%)",
+        ]);
+        for offender in &report.memory_offenders {
+            table.add_row(Row::new(vec![
+                Cell::new(&offender.username),
+                Cell::new(&format_bytes(offender.memory)),
+                Cell::new(&format!("{:.2}%", offender.memory_share)),
+                Cell::new(&format!("{:.2}%", offender.fair_share)),
+                Cell::new(&format!("{:.2}%", offender.excess_usage)),
+            ]));
+        }
+        table::print(&table);
+    }
+
+    if report.excessive_load {
+        println!("\n{}", "Excessive load detected!".red().bold());
+        if report.group_by != GroupBy::UidRange {
+            print_offenders(report, cli);
+        }
+    }
+    if cli.explain && report.group_by != GroupBy::UidRange {
+        print_explanation(report, cli);
+    }
+}
+
+/// Print only what's wrong, if anything: excessive load and users over their
+/// fair share.
+fn print_quiet(report: &LoadReport, cli: &Cli) {
+    if !report.excessive_load && report.offenders.is_empty() {
+        return;
+    }
+    if report.excessive_load {
+        println!(
+            "{}",
+            format!(
+                "Excessive load detected! {} minute load average {:.2} on {} cores",
+                report.load_window.minutes(),
+                report.load(),
+                report.total_cores
+            )
+            .red()
+            .bold()
+        );
+    }
+    if !report.offenders.is_empty() {
+        print_offenders(report, cli);
+        if cli.explain {
+            print_explanation(report, cli);
+        }
+    }
+}
+
+/// Print the users over their fair share.
+fn print_offenders(report: &LoadReport, cli: &Cli) {
+    let fair_share = report.fair_share.value;
+    // Weights and quotas give users different fair shares, and quotas take
+    // their share out of everyone else's.
+    let quota = |offender: &Offender| {
+        report
+            .users
+            .iter()
+            .any(|u| u.username == offender.username && u.quota.is_some())
+    };
+    let per_user = report.fair_share.policy == PolicyKind::Weighted
+        || report.fair_share.reserved > 0.0
+        || report.offenders.iter().any(quota);
+    // How long users have been over fair share is only tracked across
+    // samples.
+    let tracked = cli.live;
+    if per_user {
+        println!("Users exceeding their fair share:");
+    } else {
+        println!("Users exceeding fair share ({:.2}%):", fair_share);
+    }
+    let mut titles = vec![table::group_title(report.group_by), "System CPU Share (%)"];
+    if per_user {
+        titles.push("Fair Share (%)");
+    }
+    titles.push("Excess Usage (%)");
+    if tracked {
+        titles.push("Time Over Share");
+    }
+    // Whether someone is at the keyboard changes how to contact them.
+    let logins = report.users.iter().any(|u| u.logins.is_some());
+    if logins {
+        titles.push("Logged In");
+    }
+    let contacts = report.users.iter().any(|u| u.contact.is_some());
+    if contacts {
+        titles.push("Contact");
+    }
+    let mut table = table::new(titles);
+    for offender in &report.offenders {
+        let mut cells = vec![
+            Cell::new(&offender.username),
+            Cell::new(&format!("{:.2}%", offender.system_cpu_share)),
+        ];
+        if per_user {
+            cells.push(Cell::new(&format!("{:.2}%", offender.fair_share)));
+        }
+        cells.push(Cell::new(&format!("{:.2}%", offender.excess_usage)));
+        if tracked {
+            cells.push(Cell::new(&format_duration(offender.time_over_fair_share)));
+        }
+        let user = report
+            .users
+            .iter()
+            .find(|u| u.username == offender.username);
+        if logins {
+            cells.push(Cell::new(&user.map_or(String::new(), table::logins)));
+        }
+        if contacts {
+            let contact = user.and_then(|u| u.contact.as_ref());
+            cells.push(Cell::new(
+                &contact.map_or(String::new(), Contact::to_string),
+            ));
+        }
+        table.add_row(Row::new(cells));
+    }
+    table::print(&table);
+}
+
+/// Print how each offender's numbers were computed, for users who dispute
+/// them.
+fn print_explanation(report: &LoadReport, cli: &Cli) {
+    let fair_share = &report.fair_share;
+    let cores = report.total_cores.max(1) as f64;
+    let to_cores = |share: f64| share / 100.0 * cores;
+
+    println!("\nHow the fair share was computed:");
+    let active: Vec<String> = report
+        .users
+        .iter()
+        .filter(|u| u.system_cpu_share > fair_share.active_threshold)
+        .map(|u| format!("{} ({:.2}%)", u.username, u.system_cpu_share))
+        .collect();
+    let hidden = fair_share.active_users.saturating_sub(active.len());
+    println!(
+        "  Active users, with a system CPU share over {:.2}%: {}{} = {}",
+        fair_share.active_threshold,
+        active.join(", "),
+        if hidden > 0 {
+            format!(", and {} not shown", hidden)
+        } else {
+            String::new()
+        },
+        fair_share.active_users
+    );
+    if fair_share.reserved > 0.0 {
+        println!(
+            "  Reserved for active users with quotas: {:.2}% ({:.2} cores)",
+            fair_share.reserved,
+            to_cores(fair_share.reserved)
+        );
+    }
+    match fair_share.policy {
+        PolicyKind::Fixed => println!(
+            "  Fair share, set with --fair-share: {:.2}% ({:.2} cores)",
+            fair_share.value,
+            to_cores(fair_share.value)
+        ),
+        PolicyKind::Equal => println!(
+            "  Fair share = {} / {} active users without quotas = {:.2}% ({:.2} cores)",
+            capacity(report),
+            fair_share.active_weight,
+            fair_share.value,
+            to_cores(fair_share.value)
+        ),
+        PolicyKind::Weighted => println!(
+            "  Fair share per unit of weight = {} / {} total weight of active users without quotas = {:.2}% ({:.2} cores)",
+            capacity(report),
+            fair_share.active_weight,
+            fair_share.value,
+            to_cores(fair_share.value)
+        ),
+    }
+    if report.offenders.is_empty() {
+        println!("  No users are over their fair share.");
+    }
+
+    for offender in &report.offenders {
+        let Some(user) = report
+            .users
+            .iter()
+            .find(|u| u.username == offender.username)
+        else {
+            continue;
+        };
+        println!("\n{}:", user.username.bold());
+        println!(
+            "  CPU usage = sum over {} process{} = {:.2}%, where 100% is one core",
+            user.process_count,
+            if user.process_count == 1 { "" } else { "es" },
+            user.total_cpu_usage
+        );
+        if let (Some(raw), Some(alpha)) = (user.raw_cpu_usage, cli.ewma) {
+            println!(
+                "    smoothed from {:.2}% in this sample, with --ewma {}",
+                raw, alpha
+            );
+        }
+        println!(
+            "  Equivalent cores = {:.2}% / 100% = {:.2} cores",
+            user.total_cpu_usage, user.equivalent_cores
+        );
+        println!(
+            "  System CPU share = {:.2} cores / {} cores = {:.2}%",
+            user.equivalent_cores, report.total_cores, user.system_cpu_share
+        );
+        if let Some(quota) = user.quota {
+            println!(
+                "  Fair share = their quota from --quotas = {:.2}% ({:.2} cores)",
+                quota,
+                to_cores(quota)
+            );
+        } else if fair_share.policy == PolicyKind::Weighted {
+            println!(
+                "  Fair share = {:.2}% × weight {:.2} = {:.2}% ({:.2} cores)",
+                fair_share.value,
+                user.fair_share / fair_share.value,
+                user.fair_share,
+                to_cores(user.fair_share)
+            );
+        } else {
+            println!(
+                "  Fair share = {:.2}% ({:.2} cores), as above",
+                user.fair_share,
+                to_cores(user.fair_share)
+            );
+        }
+        println!(
+            "  Excess = {:.2}% - {:.2}% = {:.2}% ({:.2} cores over)",
+            offender.system_cpu_share,
+            offender.fair_share,
+            offender.excess_usage,
+            to_cores(offender.excess_usage)
+        );
+        if cli.live {
+            println!(
+                "  Over their fair share for {} (grace period {})",
+                format_duration(offender.time_over_fair_share),
+                format_duration(cli.grace_period)
+            );
+        }
+    }
+}
+
+/// Print the recent 1 minute load averages as a sparkline, where a full bar
+/// is every core busy (or the highest load, if higher).
+fn print_load_history(loads: &VecDeque<f64>, total_cores: usize) {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let peak = loads.iter().copied().fold(0.0, f64::max);
+    let scale = peak.max(total_cores as f64);
+    let sparkline: String = loads
+        .iter()
+        .map(|load| {
+            let level = (load / scale * BARS.len() as f64).ceil() as usize;
+            BARS[level.clamp(1, BARS.len()) - 1]
+        })
+        .collect();
+    println!(
+        "\nLoad history (last {} samples, peak {:.2}): {}",
+        loads.len(),
+        peak,
+        table::plain(sparkline)
+    );
+}
+
+/// Print usage per Slurm job, flagging users whose processes outside any
+/// job are active, i.e. who are bypassing the scheduler.
+fn print_jobs(jobs: &[JobUsage], active_threshold: f64, exempt_users: &[String]) {
+    let mut table = table::new([
+        "Job",
+        "Username",
+        "Total CPU Usage (%)",
+        "Equivalent Cores Used",
+        "Memory (RSS)",
+        "Processes",
+        "Top Command",
+    ]);
+    let mut outside = Vec::new();
+    for job in jobs {
+        let flagged = job.job_id.is_none()
+            && job.system_cpu_share > active_threshold
+            && !exempt_users.contains(&job.username);
+        if flagged {
+            outside.push(format!(
+                "{} ({:.2} cores)",
+                job.username, job.equivalent_cores
+            ));
+        }
+        let cells = [
+            job.job_id.map_or("none".to_string(), |id| id.to_string()),
+            job.username.clone(),
+            format!("{:.2}", job.total_cpu_usage),
+            format!("{:.2}", job.equivalent_cores),
+            format_bytes(job.memory),
+            job.processes.to_string(),
+            shorten(&job.top_command, 60),
+        ];
+        table.add_row(Row::new(
+            cells
+                .iter()
+                .map(|cell| match flagged {
+                    true => Cell::new(cell).with_style(Attr::ForegroundColor(color::RED)),
+                    false => Cell::new(cell),
+                })
+                .collect(),
+        ));
+    }
+    table::print(&table);
+    if !outside.is_empty() {
+        println!(
+            "{}",
+            format!("Running outside Slurm jobs: {}", outside.join(", ")).red()
+        );
+    }
+}
+
+/// Print usage per pod, then per namespace, so one tenant's many small pods
+/// add up.
+fn print_pods(pods: &[PodUsage]) {
+    let mut table = table::new([
+        "Namespace",
+        "Pod",
+        "Total CPU Usage (%)",
+        "Equivalent Cores Used",
+        "Memory (RSS)",
+        "Processes",
+        "Users",
+    ]);
+    for pod in pods {
+        table.add_row(Row::new(vec![
+            Cell::new(&pod.namespace),
+            Cell::new(&shorten(&pod.pod, 50)),
+            Cell::new(&format!("{:.2}", pod.total_cpu_usage)),
+            Cell::new(&format!("{:.2}", pod.equivalent_cores)),
+            Cell::new(&format_bytes(pod.memory)),
+            Cell::new(&pod.processes.to_string()),
+            Cell::new(&pod.users.join(", ")),
+        ]));
+    }
+    table::print(&table);
+
+    let mut namespaces: Vec<(&str, f64, u64, usize)> = Vec::new();
+    for pod in pods {
+        match namespaces.iter_mut().find(|(ns, ..)| *ns == pod.namespace) {
+            Some((_, cpu, memory, count)) => {
+                *cpu += pod.total_cpu_usage;
+                *memory += pod.memory;
+                *count += 1;
+            }
+            None => namespaces.push((&pod.namespace, pod.total_cpu_usage, pod.memory, 1)),
+        }
+    }
+    namespaces.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut table = table::new([
+        "Namespace",
+        "Total CPU Usage (%)",
+        "Equivalent Cores Used",
+        "Memory (RSS)",
+        "Pods",
+    ]);
+    for (namespace, cpu, memory, count) in namespaces {
+        table.add_row(Row::new(vec![
+            Cell::new(namespace),
+            Cell::new(&format!("{:.2}", cpu)),
+            Cell::new(&format!("{:.2}", cpu / 100.0)),
+            Cell::new(&format_bytes(memory)),
+            Cell::new(&count.to_string()),
+        ]));
+    }
+    println!();
+    table::print(&table);
+}
+
+/// Print how full the kernel's file table is, warning when it's nearly
+/// full and naming the user holding the most descriptors, since one user
+/// holding hundreds of thousands of them can stop everyone opening files.
+fn print_file_handles(handles: &FileHandles, report: &LoadReport) {
+    let percent = handles.used_percent();
+    println!(
+        "File handles: {} used of {} ({:.1}%)",
+        handles.allocated, handles.max, percent
+    );
+    if percent < FILE_HANDLE_WARNING {
+        return;
+    }
+    let top = report
+        .users
+        .iter()
+        .filter_map(|u| Some((u, u.fds?)))
+        .max_by_key(|(_, fds)| *fds);
+    let mut warning = "The system is running out of file handles".to_string();
+    if let Some((user, fds)) = top {
+        warning.push_str(&format!(
+            "; {} holds the most, with {} open",
+            user.username, fds
+        ));
+    }
+    println!("{}", format!("{}.", warning).red());
+}
+
+/// Print the share of CPU time spent in I/O wait and stolen by the
+/// hypervisor, colored and explained when elevated, since users get blamed for
+/// high load that's actually a dying disk or a busy host.
+fn print_cpu_wait(wait: &CpuWait) {
+    let color = |percent: f64| match percent {
+        p if p > 20.0 => "red",
+        p if p > 5.0 => "yellow",
+        _ => "green",
+    };
+    println!(
+        "CPU iowait: {}, steal: {}",
+        format!("{:.2}%", wait.iowait).color(color(wait.iowait)),
+        format!("{:.2}%", wait.steal).color(color(wait.steal))
+    );
+    if wait.iowait > 5.0 {
+        println!(
+            "{}",
+            "Warning: CPUs are idle waiting on I/O; high load may be a slow or failing disk rather than CPU use."
+                .yellow()
+        );
+    }
+    if wait.steal > 5.0 {
+        println!(
+            "{}",
+            "Warning: the hypervisor is taking CPU time for other virtual machines.".yellow()
+        );
+    }
+}
+
+/// Print the share of time tasks were stalled on each resource over the last
+/// 10 seconds, warning about resources stalled more than `threshold` percent
+/// of it.
+fn print_pressure(pressure: &Pressure, threshold: f64) {
+    let resources = [
+        ("CPU", pressure.cpu),
+        ("memory", pressure.memory),
+        ("I/O", pressure.io),
+    ];
+    let stalls: Vec<String> = resources
+        .iter()
+        .filter_map(|(name, stall)| {
+            let stall = stall.as_ref()?;
+            Some(match stall.full {
+                Some(full) => format!("{} {:.2}%/{:.2}%", name, stall.some.avg10, full.avg10),
+                None => format!("{} {:.2}%", name, stall.some.avg10),
+            })
+        })
+        .collect();
+    println!("Pressure (some/full, last 10s): {}", stalls.join(", "));
+    for (name, stall) in resources {
+        let Some(stall) = stall.filter(|s| s.some.avg10 > threshold) else {
+            continue;
+        };
+        let explanation = match name {
+            "CPU" => "runnable tasks are waiting for a core",
+            "memory" => "tasks are waiting on reclaim or swap",
+            _ => "load may be I/O wait rather than CPU use",
+        };
+        println!(
+            "{}",
+            format!(
+                "Warning: tasks were stalled on {} {:.2}% of the last 10 seconds; {}.",
+                name, stall.some.avg10, explanation
+            )
+            .yellow()
+        );
+    }
+}
+
+/// Print package temperatures and how fast busy cores are running, warning
+/// when the machine is thermally limited, since users report a slow machine at
+/// low load when it's throttling.
+fn print_thermal(thermal: &Thermal) {
+    let mut parts = Vec::new();
+    if !thermal.packages.is_empty() {
+        let temperatures: Vec<String> = thermal
+            .packages
+            .iter()
+            .map(|package| {
+                let degrees = if table::ascii() { "" } else { "°" };
+                let reading = table::plain(format!(
+                    "{} {:.0}{}C",
+                    package.label, package.celsius, degrees
+                ));
+                if package.hot() {
+                    reading.red().to_string()
+                } else {
+                    reading
+                }
+            })
+            .collect();
+        parts.push(format!("CPU temperature: {}", temperatures.join(", ")));
+    }
+    if let (Some(busy), Some(max)) = (thermal.busy_frequency_mhz, thermal.max_frequency_mhz) {
+        let frequency = format!("{:.1} of {:.1} GHz", busy / 1000.0, max / 1000.0);
+        let frequency = if thermal.slowed() {
+            frequency.red().to_string()
+        } else {
+            frequency
+        };
+        parts.push(format!("busy cores at {}", frequency));
+    }
+    if !parts.is_empty() {
+        println!("{}", parts.join("; "));
+    }
+    if thermal.throttled() {
+        let mut reasons = Vec::new();
+        if let Some(events) = thermal.throttle_events.filter(|events| *events > 0) {
+            reasons.push(format!("{} throttle events since the last sample", events));
+        }
+        if thermal.packages.iter().any(|p| p.hot()) {
+            reasons.push("packages near their critical temperature".to_string());
+        }
+        if thermal.slowed() {
+            reasons.push("busy cores running well below full speed".to_string());
+        }
+        println!(
+            "{}",
+            format!(
+                "Warning: the CPUs are thermally throttled ({}); the machine is slow because it's hot, not because of load.",
+                reasons.join(", ")
+            )
+            .red()
+        );
+    }
+}
+
+/// Print each core's utilization as a bar, several cores per line.
+fn print_cores(cores: &[f64]) {
+    const WIDTH: usize = 10;
+    const PER_LINE: usize = 4;
+    let label_width = (cores.len().max(1) - 1).to_string().len();
+    for (line, chunk) in cores.chunks(PER_LINE).enumerate() {
+        let cells: Vec<String> = chunk
+            .iter()
+            .enumerate()
+            .map(|(i, usage)| {
+                let filled = ((usage / 100.0 * WIDTH as f64).round() as usize).min(WIDTH);
+                let bar = table::plain(format!(
+                    "{}{}",
+                    "█".repeat(filled),
+                    "·".repeat(WIDTH - filled)
+                ));
+                // Saturated cores are red; fair share doesn't apply to a core.
+                let color = match usage {
+                    u if *u > 90.0 => "red",
+                    u if *u > 50.0 => "yellow",
+                    _ => "green",
+                };
+                format!(
+                    "{:>w$} [{}] {:>3.0}%",
+                    line * PER_LINE + i,
+                    bar.color(color),
+                    usage,
+                    w = label_width
+                )
+            })
+            .collect();
+        println!("  {}", cells.join("   "));
+    }
+}
+
+/// Print each user's processes over --max-runtime, with when they started.
+fn print_long_running(report: &LoadReport) {
+    let mut table = table::new([
+        table::group_title(report.group_by),
+        "PID",
+        "Started (UTC)",
+        "Runtime",
+        "CPU Usage (%)",
+        "Command",
+    ]);
+    for user in &report.long_running {
+        for (i, process) in user.processes.iter().enumerate() {
+            let username = if i == 0 { user.username.as_str() } else { "" };
+            table.add_row(Row::new(vec![
+                Cell::new(username),
+                Cell::new(&process.pid.to_string()),
+                Cell::new(&time::format_timestamp(
+                    report.timestamp.saturating_sub(process.run_time),
+                )),
+                Cell::new(&format_duration(process.run_time)),
+                Cell::new(&format!("{:.2}", process.cpu_usage)),
+                Cell::new(&shorten(&process.command, 60)),
+            ]));
+        }
+    }
+    table::print(&table);
+}
+
+/// Print each user's heavy processes that started since the last sample.
+fn print_new_heavy(report: &LoadReport) {
+    let mut table = table::new([
+        table::group_title(report.group_by),
+        "PID",
+        "CPU Usage (%)",
+        "Memory (RSS)",
+        "Runtime",
+        "Command",
+    ]);
+    for user in &report.new_heavy {
+        for (i, process) in user.processes.iter().enumerate() {
+            let username = if i == 0 { user.username.as_str() } else { "" };
+            table.add_row(Row::new(vec![
+                Cell::new(username),
+                Cell::new(&process.pid.to_string()),
+                Cell::new(&format!("{:.2}", process.cpu_usage)),
+                Cell::new(&format_bytes(process.memory)),
+                Cell::new(&format_duration(process.run_time)),
+                Cell::new(&shorten(&process.command, 60)),
+            ]));
+        }
+    }
+    table::print(&table);
+}
+
+/// Print each user's heavy processes below --batch-nice.
+fn print_unniced(report: &LoadReport) {
+    let mut table = table::new([
+        table::group_title(report.group_by),
+        "PID",
+        "Nice",
+        "CPU Usage (%)",
+        "Runtime",
+        "Command",
+    ]);
+    for user in &report.unniced {
+        for (i, process) in user.processes.iter().enumerate() {
+            let username = if i == 0 { user.username.as_str() } else { "" };
+            table.add_row(Row::new(vec![
+                Cell::new(username),
+                Cell::new(&process.pid.to_string()),
+                Cell::new(&process.nice.map_or(String::new(), |nice| nice.to_string())),
+                Cell::new(&format!("{:.2}", process.cpu_usage)),
+                Cell::new(&format_duration(process.run_time)),
+                Cell::new(&shorten(&process.command, 60)),
+            ]));
+        }
+    }
+    table::print(&table);
+}
+
+fn print_processes(report: &LoadReport) {
+    // Label containerized processes, unless they're already grouped by
+    // container.
+    let containers = report.group_by != GroupBy::Container
+        && report
+            .users
+            .iter()
+            .flat_map(|u| &u.processes)
+            .any(|p| p.container.is_some());
+    let mut titles = vec![
+        table::group_title(report.group_by),
+        "PID",
+        "CPU Usage (%)",
+        "Memory (RSS)",
+        "Runtime",
+    ];
+    if containers {
+        titles.push("Container");
+    }
+    titles.push("Command");
+    let mut table = table::new(titles);
+    for user in &report.users {
+        for (i, process) in user.processes.iter().enumerate() {
+            let username = if i == 0 { user.username.as_str() } else { "" };
+            let mut cells = vec![
+                Cell::new(username),
+                Cell::new(&process.pid.to_string()),
+                Cell::new(&format!("{:.2}", process.cpu_usage)),
+                Cell::new(&format_bytes(process.memory)),
+                Cell::new(&format_duration(process.run_time)),
+            ];
+            if containers {
+                cells.push(Cell::new(process.container.as_deref().unwrap_or("")));
+            }
+            cells.push(Cell::new(&shorten(&process.command, 60)));
+            table.add_row(Row::new(cells));
+        }
+    }
+    table::print(&table);
+}
+
+/// Format a byte count with binary units (e.g. `1.50 GiB`).
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.2} {}", value, UNITS[unit])
+}
+
+/// Format seconds as `[Nd ]HH:MM:SS`.
+fn format_duration(seconds: u64) -> String {
+    let (days, rest) = (seconds / 86400, seconds % 86400);
+    let hms = format!(
+        "{:02}:{:02}:{:02}",
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    );
+    if days > 0 {
+        format!("{}d {}", days, hms)
+    } else {
+        hms
+    }
+}
+
+/// Roughly how long `seconds` is, e.g. `~12 minutes`.
+fn format_minutes(seconds: u64) -> String {
+    match (seconds + 30) / 60 {
+        0 => "under a minute".to_string(),
+        1 => "~1 minute".to_string(),
+        minutes => format!("~{} minutes", minutes),
+    }
+}
+
+/// Collapse whitespace (command lines can contain newlines) and shorten `s`
+/// to at most `max` characters, marking truncation with `…` (or `...` with
+/// --ascii).
+fn shorten(s: &str, max: usize) -> String {
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    if s.chars().count() <= max {
+        return s;
+    }
+    let ellipsis = if table::ascii() { "..." } else { "…" };
+    let keep = max.saturating_sub(ellipsis.chars().count());
+    let mut truncated: String = s.chars().take(keep).collect();
+    truncated.push_str(ellipsis);
+    truncated
 }
 
-fn color_from_string(color: &str) -> color::Color {
-    match color {
-        "red" => color::RED,
-        "yellow" => color::YELLOW,
-        "green" => color::GREEN,
-        _ => color::WHITE,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A report on a small simulated machine at a fixed time, for golden
+    /// tests of the output formats. Its users' names need quoting or
+    /// escaping in most of them.
+    pub(crate) fn example_report() -> LoadReport {
+        let scenario = Scenario::parse(
+            r#"
+            hostname = "node1.example.com"
+            cores = 4
+            memory_gib = 16
+
+            [[users]]
+            name = "alice smith"
+            processes = [{ command = "python", cpu = 150, memory_mib = 1024 }]
+
+            [[users]]
+            name = 'o"brien'
+            processes = [{ command = "R", cpu = 50, memory_mib = 512 }]
+            "#,
+        )
+        .unwrap();
+        let mut report = Sampler::new().simulate(scenario).sample();
+        report.timestamp = 1_718_000_000;
+        report
+    }
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("load").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn scenarios_cannot_act_on_real_processes_or_users() {
+        for flags in [
+            &["--enforce"][..],
+            &["--enforce-cgroup"],
+            &["--suggest-renice"],
+            &["--alert-tty"],
+            &[
+                "--alert-email-users=example.edu",
+                "--alert-smtp-server=localhost",
+            ],
+        ] {
+            let args = [&["--simulate", "demo.toml"][..], flags].concat();
+            assert_eq!(
+                parse(&args).err().map(|e| e.kind()),
+                Some(clap::error::ErrorKind::ArgumentConflict),
+                "{:?}",
+                flags
+            );
+        }
+        assert!(parse(&["--simulate", "demo.toml", "--live"]).is_ok());
+    }
+
+    #[test]
+    fn weights_are_positive() {
+        assert_eq!(parse_weight("alice=2"), Ok(("alice".to_string(), 2.0)));
+        assert_eq!(parse_weight(" lab = 0.5 "), Ok(("lab".to_string(), 0.5)));
+        for weight in [
+            "alice=0",
+            "alice=-1",
+            "alice=inf",
+            "alice=NaN",
+            "alice=two",
+            "alice",
+        ] {
+            assert!(parse_weight(weight).is_err(), "{}", weight);
+        }
     }
 }
@@ -0,0 +1,337 @@
+//! Prometheus metrics endpoint, enabled with `--serve-metrics`.
+
+use std::fmt::Write as _;
+use std::io;
+use std::net::TcpListener;
+use std::time::Duration;
+
+use loadrs::{LoadReport, Sampler, UserUsage};
+
+use crate::http::{self, Latest, Response};
+
+/// Sample every `interval` in the background and serve the latest report at
+/// `GET /metrics` on `addr`.
+pub fn serve(sampler: Sampler, interval: Duration, addr: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let latest = Latest::sample(sampler, interval);
+    eprintln!(
+        "Serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+    http::serve(listener, move |request| {
+        if request.path() != "/metrics" {
+            return Response::not_found();
+        }
+        let body = render(&latest.get());
+        Response::new("200 OK", "text/plain; version=0.0.4", body)
+    })
+}
+
+/// A per-user gauge: name, help text, and its value for a user.
+type UserMetric = (&'static str, &'static str, fn(&UserUsage) -> f64);
+
+/// Render a report in the Prometheus text exposition format.
+fn render(report: &LoadReport) -> String {
+    let mut out = String::new();
+
+    scalar(
+        &mut out,
+        "loadrs_cores",
+        "Total number of CPU cores.",
+        report.total_cores as f64,
+    );
+
+    gauge(
+        &mut out,
+        "loadrs_load_average",
+        "System load average over the given window.",
+    );
+    for (window, value) in [
+        ("1m", report.load_average.one),
+        ("5m", report.load_average.five),
+        ("15m", report.load_average.fifteen),
+    ] {
+        sample(
+            &mut out,
+            "loadrs_load_average",
+            &[("window", window)],
+            value,
+        );
+    }
+
+    scalar(
+        &mut out,
+        "loadrs_excessive_load",
+        "Whether the load average (over --load-window) exceeds the threshold.",
+        report.excessive_load as u8 as f64,
+    );
+    scalar(
+        &mut out,
+        "loadrs_load_state",
+        "Load state with hysteresis: 0 (OK), 1 (WARN), or 2 (CRIT).",
+        report.load_state as u8 as f64,
+    );
+    scalar(
+        &mut out,
+        "loadrs_fair_share_percent",
+        "Fair share of system CPU per unit of weight, in percent.",
+        report.fair_share.value,
+    );
+    scalar(
+        &mut out,
+        "loadrs_active_users",
+        "Number of users above the active threshold.",
+        report.fair_share.active_users as f64,
+    );
+
+    for (name, help, value) in [
+        (
+            "loadrs_memory_total_bytes",
+            "Total system memory.",
+            report.memory.total,
+        ),
+        (
+            "loadrs_memory_available_bytes",
+            "Memory available for new allocations without swapping.",
+            report.memory.available,
+        ),
+        (
+            "loadrs_swap_used_bytes",
+            "Swap space in use.",
+            report.memory.swap_used,
+        ),
+    ] {
+        scalar(&mut out, name, help, value as f64);
+    }
+
+    if let Some(wait) = &report.cpu_wait {
+        scalar(
+            &mut out,
+            "loadrs_cpu_iowait_percent",
+            "Percent of CPU time idle waiting on I/O since the previous sample.",
+            wait.iowait,
+        );
+        scalar(
+            &mut out,
+            "loadrs_cpu_steal_percent",
+            "Percent of CPU time stolen by the hypervisor since the previous sample.",
+            wait.steal,
+        );
+    }
+
+    if let Some(pressure) = &report.pressure {
+        let name = "loadrs_pressure_percent";
+        gauge(
+            &mut out,
+            name,
+            "Percent of time tasks were stalled on a resource (Linux PSI).",
+        );
+        for (resource, stall) in [
+            ("cpu", pressure.cpu),
+            ("memory", pressure.memory),
+            ("io", pressure.io),
+        ] {
+            let Some(stall) = stall else { continue };
+            for (kind, averages) in [("some", Some(stall.some)), ("full", stall.full)] {
+                let Some(averages) = averages else { continue };
+                for (window, value) in [
+                    ("10s", averages.avg10),
+                    ("60s", averages.avg60),
+                    ("300s", averages.avg300),
+                ] {
+                    let labels = [("resource", resource), ("kind", kind), ("window", window)];
+                    sample(&mut out, name, &labels, value);
+                }
+            }
+        }
+    }
+
+    let user_metrics: [UserMetric; 8] = [
+        (
+            "loadrs_user_cpu_share_percent",
+            "User's share of total system CPU, in percent.",
+            |user| user.system_cpu_share,
+        ),
+        (
+            "loadrs_user_equivalent_cores",
+            "Number of cores the user's processes are using.",
+            |user| user.equivalent_cores,
+        ),
+        (
+            "loadrs_user_memory_bytes",
+            "Sum of the resident memory of the user's processes.",
+            |user| user.memory as f64,
+        ),
+        (
+            "loadrs_user_memory_share_percent",
+            "User's share of total system memory, in percent.",
+            |user| user.memory_share,
+        ),
+        (
+            "loadrs_user_fair_share_percent",
+            "User's fair share of system CPU, in percent.",
+            |user| user.fair_share,
+        ),
+        (
+            "loadrs_user_fair_share_excess_percent",
+            "How far the user's CPU share is above fair share (0 if below), in percent.",
+            |user| (user.system_cpu_share - user.fair_share).max(0.0),
+        ),
+        (
+            "loadrs_user_processes",
+            "Number of processes the user is running.",
+            |user| user.process_count as f64,
+        ),
+        (
+            "loadrs_user_threads",
+            "Number of threads across the user's processes.",
+            |user| user.thread_count as f64,
+        ),
+    ];
+    for (name, help, value) in user_metrics {
+        gauge(&mut out, name, help);
+        for user in &report.users {
+            let labels = [("user", user.username.as_str())];
+            sample(&mut out, name, &labels, value(user));
+        }
+    }
+
+    out
+}
+
+fn gauge(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+}
+
+fn scalar(out: &mut String, name: &str, help: &str, value: f64) {
+    gauge(out, name, help);
+    sample(out, name, &[], value);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+            .collect();
+        let _ = write!(out, "{{{}}}", labels.join(","));
+    }
+    let _ = writeln!(out, " {}", format_value(value));
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::example_report;
+
+    #[test]
+    fn reports_render_in_the_exposition_format() {
+        assert_eq!(
+            render(&example_report()),
+            r#"
+# HELP loadrs_cores Total number of CPU cores.
+# TYPE loadrs_cores gauge
+loadrs_cores 4
+# HELP loadrs_load_average System load average over the given window.
+# TYPE loadrs_load_average gauge
+loadrs_load_average{window="1m"} 2
+loadrs_load_average{window="5m"} 2
+loadrs_load_average{window="15m"} 2
+# HELP loadrs_excessive_load Whether the load average (over --load-window) exceeds the threshold.
+# TYPE loadrs_excessive_load gauge
+loadrs_excessive_load 0
+# HELP loadrs_load_state Load state with hysteresis: 0 (OK), 1 (WARN), or 2 (CRIT).
+# TYPE loadrs_load_state gauge
+loadrs_load_state 0
+# HELP loadrs_fair_share_percent Fair share of system CPU per unit of weight, in percent.
+# TYPE loadrs_fair_share_percent gauge
+loadrs_fair_share_percent 50
+# HELP loadrs_active_users Number of users above the active threshold.
+# TYPE loadrs_active_users gauge
+loadrs_active_users 2
+# HELP loadrs_memory_total_bytes Total system memory.
+# TYPE loadrs_memory_total_bytes gauge
+loadrs_memory_total_bytes 17179869184
+# HELP loadrs_memory_available_bytes Memory available for new allocations without swapping.
+# TYPE loadrs_memory_available_bytes gauge
+loadrs_memory_available_bytes 15569256448
+# HELP loadrs_swap_used_bytes Swap space in use.
+# TYPE loadrs_swap_used_bytes gauge
+loadrs_swap_used_bytes 0
+# HELP loadrs_user_cpu_share_percent User's share of total system CPU, in percent.
+# TYPE loadrs_user_cpu_share_percent gauge
+loadrs_user_cpu_share_percent{user="alice smith"} 37.5
+loadrs_user_cpu_share_percent{user="o\"brien"} 12.5
+# HELP loadrs_user_equivalent_cores Number of cores the user's processes are using.
+# TYPE loadrs_user_equivalent_cores gauge
+loadrs_user_equivalent_cores{user="alice smith"} 1.5
+loadrs_user_equivalent_cores{user="o\"brien"} 0.5
+# HELP loadrs_user_memory_bytes Sum of the resident memory of the user's processes.
+# TYPE loadrs_user_memory_bytes gauge
+loadrs_user_memory_bytes{user="alice smith"} 1073741824
+loadrs_user_memory_bytes{user="o\"brien"} 536870912
+# HELP loadrs_user_memory_share_percent User's share of total system memory, in percent.
+# TYPE loadrs_user_memory_share_percent gauge
+loadrs_user_memory_share_percent{user="alice smith"} 6.25
+loadrs_user_memory_share_percent{user="o\"brien"} 3.125
+# HELP loadrs_user_fair_share_percent User's fair share of system CPU, in percent.
+# TYPE loadrs_user_fair_share_percent gauge
+loadrs_user_fair_share_percent{user="alice smith"} 50
+loadrs_user_fair_share_percent{user="o\"brien"} 50
+# HELP loadrs_user_fair_share_excess_percent How far the user's CPU share is above fair share (0 if below), in percent.
+# TYPE loadrs_user_fair_share_excess_percent gauge
+loadrs_user_fair_share_excess_percent{user="alice smith"} 0
+loadrs_user_fair_share_excess_percent{user="o\"brien"} 0
+# HELP loadrs_user_processes Number of processes the user is running.
+# TYPE loadrs_user_processes gauge
+loadrs_user_processes{user="alice smith"} 1
+loadrs_user_processes{user="o\"brien"} 1
+# HELP loadrs_user_threads Number of threads across the user's processes.
+# TYPE loadrs_user_threads gauge
+loadrs_user_threads{user="alice smith"} 1
+loadrs_user_threads{user="o\"brien"} 1
+"#
+            .trim_start()
+        );
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        let mut out = String::new();
+        sample(
+            &mut out,
+            "m",
+            &[("user", "a \"b\" \\c\nd"), ("k", "v")],
+            1.0,
+        );
+        assert_eq!(out, "m{user=\"a \\\"b\\\" \\\\c\\nd\",k=\"v\"} 1\n");
+    }
+
+    #[test]
+    fn values_use_prometheus_spellings() {
+        assert_eq!(format_value(2.0), "2");
+        assert_eq!(format_value(0.125), "0.125");
+        assert_eq!(format_value(f64::INFINITY), "+Inf");
+        assert_eq!(format_value(f64::NEG_INFINITY), "-Inf");
+        assert_eq!(format_value(f64::NAN), "NaN");
+    }
+}
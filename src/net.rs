@@ -0,0 +1,111 @@
+//! Per-process TCP throughput, read from `ss` (iproute2, Linux only).
+//!
+//! The kernel keeps byte counters for each TCP socket, and `ss -p` lists the
+//! process owning it, so the bytes a process sent and received over an
+//! interval are the growth of its sockets' counters. Other users' sockets are
+//! only listed when run as root.
+
+use std::collections::HashMap;
+use std::io;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+/// A TCP socket's byte counters, which count up from when it was opened.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Socket {
+    pub pid: u32,
+    /// Bytes sent and acknowledged by the peer (so not counting
+    /// retransmissions).
+    pub sent: u64,
+    pub received: u64,
+}
+
+/// Bytes sent and received over TCP over the sampling interval.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct NetUsage {
+    pub sent_bytes: u64,
+    pub received_bytes: u64,
+    /// Bytes sent per second.
+    pub send_rate: f64,
+    /// Bytes received per second.
+    pub receive_rate: f64,
+}
+
+/// Every TCP socket owned by a visible process, keyed by its local and peer
+/// addresses.
+pub fn sockets() -> io::Result<HashMap<String, Socket>> {
+    let output = Command::new("ss")
+        .arg("-tinp")
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), "ss not found"),
+            _ => e,
+        })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "ss failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    // Each socket is a line "state recv-q send-q local peer users:((...))",
+    // followed by an indented line of "key:value" TCP info, e.g.
+    // "bytes_acked:3617253 bytes_received:26553631".
+    let mut sockets = HashMap::new();
+    let mut current: Option<(String, Socket)> = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if !line.starts_with(char::is_whitespace) {
+            sockets.extend(current.take());
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // Sockets without a process we can see can't be attributed.
+            let pid = line
+                .split_once("pid=")
+                .and_then(|(_, rest)| rest.split([',', ')']).next())
+                .and_then(|pid| pid.parse().ok());
+            if let (Some(pid), Some(local), Some(peer)) = (pid, fields.get(3), fields.get(4)) {
+                let socket = Socket {
+                    pid,
+                    ..Default::default()
+                };
+                current = Some((format!("{} {}", local, peer), socket));
+            }
+            continue;
+        }
+        let Some((_, socket)) = current.as_mut() else {
+            continue;
+        };
+        for field in line.split_whitespace() {
+            match field.split_once(':') {
+                Some(("bytes_acked", value)) => socket.sent = value.parse().unwrap_or(0),
+                Some(("bytes_received", value)) => socket.received = value.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    sockets.extend(current);
+    Ok(sockets)
+}
+
+/// Bytes each process sent and received between two readings of
+/// [`sockets`], by PID. Sockets opened in between count from zero, and the
+/// last bytes of sockets closed in between are missed.
+pub fn throughput(
+    before: &HashMap<String, Socket>,
+    after: &HashMap<String, Socket>,
+) -> HashMap<u32, (u64, u64)> {
+    let mut usage: HashMap<u32, (u64, u64)> = HashMap::new();
+    for (key, socket) in after {
+        // Counters that went down are a new connection between the same
+        // addresses.
+        let previous = before
+            .get(key)
+            .filter(|previous| previous.sent <= socket.sent && previous.received <= socket.received)
+            .copied()
+            .unwrap_or_default();
+        let totals = usage.entry(socket.pid).or_default();
+        totals.0 += socket.sent.saturating_sub(previous.sent);
+        totals.1 += socket.received.saturating_sub(previous.received);
+    }
+    usage
+}
@@ -0,0 +1,33 @@
+//! Process nice values (Unix only).
+//!
+//! Sites often require batch work to run niced so interactive sessions stay
+//! responsive, and a heavy process at nice 0 is the usual sign it wasn't.
+
+use std::io;
+
+/// A process's nice value, from -20 (highest priority) to 19 (lowest).
+#[cfg(unix)]
+pub fn priority(pid: u32) -> io::Result<i32> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    use libc::__errno_location as errno;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    use libc::__error as errno;
+
+    // getpriority can legitimately return -1, so errors are told apart by
+    // errno.
+    // SAFETY: errno is thread-local, and getpriority only reads its arguments.
+    unsafe {
+        *errno() = 0;
+        let nice = libc::getpriority(libc::PRIO_PROCESS, pid as libc::id_t);
+        match io::Error::last_os_error() {
+            e if e.raw_os_error() == Some(0) => Ok(nice),
+            e => Err(e),
+        }
+    }
+}
+
+/// Windows has no nice values.
+#[cfg(not(unix))]
+pub fn priority(_pid: u32) -> io::Result<i32> {
+    Err(io::ErrorKind::Unsupported.into())
+}
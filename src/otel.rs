@@ -0,0 +1,319 @@
+//! `--otel-endpoint`: push each sample's metrics to an OpenTelemetry
+//! collector over OTLP/HTTP, as JSON.
+
+use std::env;
+use std::time::Duration;
+
+use loadrs::LoadReport;
+use serde_json::{json, Value};
+
+/// Metric names start with this, as does the instrumentation scope.
+const PREFIX: &str = "loadrs";
+
+pub struct Exporter {
+    agent: ureq::Agent,
+    url: String,
+    headers: Vec<(String, String)>,
+    resource: Vec<Value>,
+}
+
+impl Exporter {
+    /// An exporter posting to the collector at `endpoint`, e.g.
+    /// `http://localhost:4318` (`/v1/metrics` is added unless the endpoint
+    /// already ends with it), with extra `headers`, e.g. for authentication.
+    ///
+    /// The resource has the service name (`OTEL_SERVICE_NAME`, or loadrs),
+    /// version, host name, and OS, plus any attributes in
+    /// `OTEL_RESOURCE_ATTRIBUTES`, as in the OpenTelemetry SDKs.
+    pub fn new(endpoint: &str, headers: Vec<(String, String)>) -> Self {
+        let endpoint = endpoint.trim_end_matches('/');
+        let url = if endpoint.ends_with("/v1/metrics") {
+            endpoint.to_string()
+        } else {
+            format!("{}/v1/metrics", endpoint)
+        };
+        let mut attributes = vec![
+            (
+                "service.name".to_string(),
+                env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| PREFIX.to_string()),
+            ),
+            (
+                "service.version".to_string(),
+                env!("CARGO_PKG_VERSION").to_string(),
+            ),
+            (
+                "host.name".to_string(),
+                sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string()),
+            ),
+            ("os.type".to_string(), env::consts::OS.to_string()),
+        ];
+        // e.g. "deployment.environment=prod,host.cluster=hpc1"
+        if let Ok(extra) = env::var("OTEL_RESOURCE_ATTRIBUTES") {
+            for (key, value) in extra.split(',').filter_map(|kv| kv.split_once('=')) {
+                let key = key.trim().to_string();
+                attributes.retain(|(k, _)| *k != key);
+                attributes.push((key, value.trim().to_string()));
+            }
+        }
+        Self {
+            agent: ureq::Agent::config_builder()
+                .timeout_global(Some(Duration::from_secs(10)))
+                .build()
+                .into(),
+            url,
+            headers,
+            resource: attributes
+                .iter()
+                .map(|(key, value)| attribute(key, value))
+                .collect(),
+        }
+    }
+
+    /// Send the report's gauges: the load averages, fair share, active users
+    /// and offender count, and each user's CPU share, cores, and memory,
+    /// with the user as the `user.name` attribute.
+    pub fn send(&self, report: &LoadReport) -> Result<(), ureq::Error> {
+        let mut request = self
+            .agent
+            .post(&self.url)
+            .header("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        request.send(self.body(report).to_string())?;
+        Ok(())
+    }
+
+    /// The report as an OTLP `ExportMetricsServiceRequest`.
+    fn body(&self, report: &LoadReport) -> Value {
+        let time = (report.timestamp as u128 * 1_000_000_000).to_string();
+        let point = |value: f64, user: Option<&str>| {
+            let mut point = json!({"timeUnixNano": time, "asDouble": value});
+            if let Some(user) = user {
+                point["attributes"] = json!([attribute("user.name", user)]);
+            }
+            point
+        };
+        let load = &report.load_average;
+        let system = [
+            ("load.1m", "{thread}", "1 minute load average", load.one),
+            ("load.5m", "{thread}", "5 minute load average", load.five),
+            (
+                "load.15m",
+                "{thread}",
+                "15 minute load average",
+                load.fifteen,
+            ),
+            ("cores", "{cpu}", "Logical cores", report.total_cores as f64),
+            (
+                "fair_share",
+                "%",
+                "Each active user's fair share of CPU",
+                report.fair_share.value,
+            ),
+            (
+                "active_users",
+                "{user}",
+                "Users counted towards the fair share",
+                report.fair_share.active_users as f64,
+            ),
+            (
+                "offenders",
+                "{user}",
+                "Users over their fair share",
+                report.offenders.len() as f64,
+            ),
+        ];
+        let mut metrics: Vec<Value> = system
+            .into_iter()
+            // An infinite fair share, with no active users, has no JSON value.
+            .filter(|(_, _, _, value)| value.is_finite())
+            .map(|(name, unit, description, value)| {
+                gauge(name, unit, description, vec![point(value, None)])
+            })
+            .collect();
+        if !report.users.is_empty() {
+            let mut points: [Vec<Value>; 4] = Default::default();
+            for user in &report.users {
+                let values = [
+                    user.system_cpu_share,
+                    user.equivalent_cores,
+                    user.memory as f64,
+                    user.memory_share,
+                ];
+                for (points, value) in points.iter_mut().zip(values) {
+                    points.push(point(value, Some(&user.username)));
+                }
+            }
+            let users = [
+                ("user.cpu_share", "%", "Percent of system CPU"),
+                ("user.cores", "{cpu}", "Cores used"),
+                ("user.memory", "By", "Resident memory"),
+                ("user.memory_share", "%", "Percent of system memory"),
+            ];
+            for ((name, unit, description), points) in users.into_iter().zip(points) {
+                metrics.push(gauge(name, unit, description, points));
+            }
+        }
+
+        json!({
+            "resourceMetrics": [{
+                "resource": {"attributes": self.resource},
+                "scopeMetrics": [{
+                    "scope": {"name": PREFIX, "version": env!("CARGO_PKG_VERSION")},
+                    "metrics": metrics,
+                }],
+            }],
+        })
+    }
+}
+
+fn gauge(name: &str, unit: &str, description: &str, points: Vec<Value>) -> Value {
+    json!({
+        "name": format!("{}.{}", PREFIX, name),
+        "unit": unit,
+        "description": description,
+        "gauge": {"dataPoints": points},
+    })
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({"key": key, "value": {"stringValue": value}})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::example_report;
+
+    #[test]
+    fn metrics_are_posted_to_the_v1_metrics_path() {
+        for endpoint in [
+            "http://localhost:4318",
+            "http://localhost:4318/",
+            "http://localhost:4318/v1/metrics",
+        ] {
+            assert_eq!(
+                Exporter::new(endpoint, Vec::new()).url,
+                "http://localhost:4318/v1/metrics"
+            );
+        }
+    }
+
+    #[test]
+    fn reports_are_otlp_gauges() {
+        let mut exporter = Exporter::new("http://localhost:4318", Vec::new());
+        exporter.resource = vec![attribute("host.name", "node1.example.com")];
+        let time = "1718000000000000000";
+        let point = |value: f64| json!({"timeUnixNano": time, "asDouble": value});
+        let user_point = |value: f64, user: &str| {
+            json!({
+                "timeUnixNano": time,
+                "asDouble": value,
+                "attributes": [{"key": "user.name", "value": {"stringValue": user}}],
+            })
+        };
+        let gauge = |name: &str, unit: &str, description: &str, points: Vec<Value>| {
+            json!({
+                "name": name,
+                "unit": unit,
+                "description": description,
+                "gauge": {"dataPoints": points},
+            })
+        };
+        let users = |alice: f64, obrien: f64| {
+            vec![
+                user_point(alice, "alice smith"),
+                user_point(obrien, "o\"brien"),
+            ]
+        };
+        let metrics = vec![
+            gauge(
+                "loadrs.load.1m",
+                "{thread}",
+                "1 minute load average",
+                vec![point(2.0)],
+            ),
+            gauge(
+                "loadrs.load.5m",
+                "{thread}",
+                "5 minute load average",
+                vec![point(2.0)],
+            ),
+            gauge(
+                "loadrs.load.15m",
+                "{thread}",
+                "15 minute load average",
+                vec![point(2.0)],
+            ),
+            gauge("loadrs.cores", "{cpu}", "Logical cores", vec![point(4.0)]),
+            gauge(
+                "loadrs.fair_share",
+                "%",
+                "Each active user's fair share of CPU",
+                vec![point(50.0)],
+            ),
+            gauge(
+                "loadrs.active_users",
+                "{user}",
+                "Users counted towards the fair share",
+                vec![point(2.0)],
+            ),
+            gauge(
+                "loadrs.offenders",
+                "{user}",
+                "Users over their fair share",
+                vec![point(0.0)],
+            ),
+            gauge(
+                "loadrs.user.cpu_share",
+                "%",
+                "Percent of system CPU",
+                users(37.5, 12.5),
+            ),
+            gauge("loadrs.user.cores", "{cpu}", "Cores used", users(1.5, 0.5)),
+            gauge(
+                "loadrs.user.memory",
+                "By",
+                "Resident memory",
+                users(1073741824.0, 536870912.0),
+            ),
+            gauge(
+                "loadrs.user.memory_share",
+                "%",
+                "Percent of system memory",
+                users(6.25, 3.125),
+            ),
+        ];
+        assert_eq!(
+            exporter.body(&example_report()),
+            json!({
+                "resourceMetrics": [{
+                    "resource": {"attributes": [
+                        {"key": "host.name", "value": {"stringValue": "node1.example.com"}},
+                    ]},
+                    "scopeMetrics": [{
+                        "scope": {"name": "loadrs", "version": env!("CARGO_PKG_VERSION")},
+                        "metrics": metrics,
+                    }],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn an_infinite_fair_share_is_left_out() {
+        let exporter = Exporter::new("http://localhost:4318", Vec::new());
+        let mut report = example_report();
+        report.fair_share.value = f64::INFINITY;
+        let body = exporter.body(&report);
+        let names: Vec<&str> = body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|metric| metric["name"].as_str().unwrap())
+            .collect();
+        assert!(!names.contains(&"loadrs.fair_share"));
+        assert!(names.contains(&"loadrs.cores"));
+    }
+}
@@ -0,0 +1,50 @@
+//! Backing off the `--live` interval when sampling is expensive, so loadrs
+//! doesn't become one of the top consumers on nodes with many processes.
+
+use std::time::Duration;
+
+/// Most of the interval sampling may take before the interval is backed off.
+const MAX_BUSY: f64 = 0.1;
+
+/// The interval between samples, adapted to how long sampling takes.
+pub struct Pacer {
+    /// The interval asked for with --interval, which the pacer returns to
+    /// once sampling is cheap again.
+    target: Duration,
+    min: Duration,
+    max: Duration,
+    interval: Duration,
+}
+
+impl Pacer {
+    pub fn new(target: Duration, min: Duration, max: Duration) -> Self {
+        let max = max.max(min);
+        let target = target.clamp(min, max);
+        Self {
+            target,
+            min,
+            max,
+            interval: target,
+        }
+    }
+
+    /// Update the interval after a sample that took `cost`, and return it.
+    /// Sampling taking more than a tenth of the interval doubles it (or more,
+    /// for very expensive samples), up to the maximum; sampling taking less
+    /// than a fortieth halves it, back down to the target.
+    pub fn update(&mut self, cost: Duration) -> Duration {
+        let busy = cost.as_secs_f64() / self.interval.as_secs_f64().max(f64::EPSILON);
+        if busy > MAX_BUSY {
+            let needed = cost.div_f64(MAX_BUSY);
+            self.interval = (self.interval * 2).max(needed).clamp(self.min, self.max);
+        } else if busy < MAX_BUSY / 4.0 && self.interval > self.target {
+            self.interval = (self.interval / 2).max(self.target);
+        }
+        self.interval
+    }
+
+    /// Whether the interval is longer than asked for.
+    pub fn backed_off(&self) -> bool {
+        self.interval > self.target
+    }
+}
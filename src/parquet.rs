@@ -0,0 +1,475 @@
+//! A minimal Parquet writer for `load export --parquet`: uncompressed, PLAIN
+//! encoded columns of recorded user samples, readable by polars, pandas,
+//! arrow, DuckDB, and Spark.
+//!
+//! The schema, one row per user per sample:
+//!
+//! | Column             | Type                   | Nullable                    |
+//! |--------------------|------------------------|-----------------------------|
+//! | `timestamp`        | timestamp (ms, UTC)    | no                          |
+//! | `username`         | string                 | no                          |
+//! | `equivalent_cores` | double                 | no                          |
+//! | `system_cpu_share` | double, percent        | no                          |
+//! | `memory_bytes`     | int64, resident memory | no                          |
+//! | `fair_share`       | double, percent        | yes, when no one was active |
+//!
+//! Append new columns at the end, like the CSV schema.
+
+use std::io::{self, Write};
+
+use loadrs::history::UserSample;
+
+/// Rows per row group, which readers can load and skip independently.
+const ROW_GROUP_ROWS: usize = 1 << 20;
+
+const MAGIC: &[u8] = b"PAR1";
+
+// Physical types.
+const INT64: i32 = 2;
+const DOUBLE: i32 = 5;
+const BYTE_ARRAY: i32 = 6;
+
+// Encodings.
+const PLAIN: i32 = 0;
+const RLE: i32 = 3;
+
+/// How a column's values are stored and annotated.
+#[derive(Clone, Copy)]
+enum Kind {
+    Timestamp,
+    String,
+    Double,
+    Int64,
+}
+
+impl Kind {
+    fn physical(self) -> i32 {
+        match self {
+            Kind::Timestamp | Kind::Int64 => INT64,
+            Kind::String => BYTE_ARRAY,
+            Kind::Double => DOUBLE,
+        }
+    }
+}
+
+/// Name, kind, and whether the column is nullable.
+const SCHEMA: [(&str, Kind, bool); 6] = [
+    ("timestamp", Kind::Timestamp, false),
+    ("username", Kind::String, false),
+    ("equivalent_cores", Kind::Double, false),
+    ("system_cpu_share", Kind::Double, false),
+    ("memory_bytes", Kind::Int64, false),
+    ("fair_share", Kind::Double, true),
+];
+
+/// One column's values in a row group.
+enum Values<'a> {
+    Int64(Vec<i64>),
+    Double(Vec<Option<f64>>),
+    String(Vec<&'a str>),
+}
+
+fn values(column: usize, samples: &[UserSample]) -> Values<'_> {
+    match column {
+        0 => Values::Int64(samples.iter().map(|s| s.timestamp as i64 * 1000).collect()),
+        1 => Values::String(samples.iter().map(|s| s.username.as_str()).collect()),
+        2 => Values::Double(samples.iter().map(|s| Some(s.equivalent_cores)).collect()),
+        3 => Values::Double(samples.iter().map(|s| Some(s.system_cpu_share)).collect()),
+        4 => Values::Int64(samples.iter().map(|s| s.memory as i64).collect()),
+        _ => Values::Double(samples.iter().map(|s| s.fair_share).collect()),
+    }
+}
+
+/// A written column chunk, for the footer.
+struct Chunk {
+    column: usize,
+    offset: u64,
+    size: u64,
+    rows: usize,
+    nulls: usize,
+    /// Minimum and maximum, for integer columns.
+    range: Option<(i64, i64)>,
+}
+
+/// Write `samples` as a Parquet file.
+pub fn write(out: &mut impl Write, samples: &[UserSample]) -> io::Result<()> {
+    out.write_all(MAGIC)?;
+    let mut offset = MAGIC.len() as u64;
+    let mut row_groups = Vec::new();
+    for rows in samples.chunks(ROW_GROUP_ROWS) {
+        let mut chunks = Vec::new();
+        for (column, (_, _, optional)) in SCHEMA.iter().enumerate() {
+            let (page, nulls, range) = page(values(column, rows), *optional);
+            let header = page_header(rows.len(), page.len());
+            out.write_all(&header)?;
+            out.write_all(&page)?;
+            let size = (header.len() + page.len()) as u64;
+            chunks.push(Chunk {
+                column,
+                offset,
+                size,
+                rows: rows.len(),
+                nulls,
+                range,
+            });
+            offset += size;
+        }
+        row_groups.push(chunks);
+    }
+    let footer = file_metadata(samples.len(), &row_groups);
+    out.write_all(&footer)?;
+    out.write_all(&(footer.len() as u32).to_le_bytes())?;
+    out.write_all(MAGIC)
+}
+
+/// A data page of PLAIN encoded values, preceded by definition levels if
+/// the column is optional, with its null count and range.
+fn page(values: Values, optional: bool) -> (Vec<u8>, usize, Option<(i64, i64)>) {
+    let mut page = Vec::new();
+    match values {
+        Values::Int64(values) => {
+            let range = values
+                .iter()
+                .copied()
+                .min()
+                .zip(values.iter().copied().max());
+            for value in values {
+                page.extend(value.to_le_bytes());
+            }
+            (page, 0, range)
+        }
+        Values::Double(values) => {
+            if optional {
+                let levels: Vec<bool> = values.iter().map(Option::is_some).collect();
+                definition_levels(&mut page, &levels);
+            }
+            for value in values.iter().flatten() {
+                page.extend(value.to_le_bytes());
+            }
+            let nulls = values.iter().filter(|v| v.is_none()).count();
+            (page, nulls, None)
+        }
+        Values::String(values) => {
+            for value in values {
+                page.extend((value.len() as u32).to_le_bytes());
+                page.extend(value.as_bytes());
+            }
+            (page, 0, None)
+        }
+    }
+}
+
+/// Definition levels (1 for a value, 0 for null) as runs of the RLE/bit
+/// packing hybrid encoding, with bit width 1, after their length.
+fn definition_levels(page: &mut Vec<u8>, levels: &[bool]) {
+    let mut runs = Vec::new();
+    let mut i = 0;
+    while i < levels.len() {
+        let run = levels[i..].iter().take_while(|l| **l == levels[i]).count();
+        // An RLE run's header is its length shifted left by one.
+        varint(&mut runs, (run as u64) << 1);
+        runs.push(levels[i] as u8);
+        i += run;
+    }
+    page.extend((runs.len() as u32).to_le_bytes());
+    page.extend(runs);
+}
+
+fn page_header(rows: usize, size: usize) -> Vec<u8> {
+    let mut t = Compact::default();
+    t.i32(1, 0); // DATA_PAGE
+    t.i32(2, size as i32);
+    t.i32(3, size as i32);
+    t.begin(5);
+    t.i32(1, rows as i32);
+    t.i32(2, PLAIN);
+    t.i32(3, RLE);
+    t.i32(4, RLE);
+    t.end();
+    t.finish()
+}
+
+fn file_metadata(rows: usize, row_groups: &[Vec<Chunk>]) -> Vec<u8> {
+    let mut t = Compact::default();
+    t.i32(1, 1);
+    t.list(2, STRUCT, SCHEMA.len() + 1);
+    t.element();
+    t.binary(4, b"schema");
+    t.i32(5, SCHEMA.len() as i32);
+    t.end();
+    for (name, kind, optional) in SCHEMA {
+        t.element();
+        t.i32(1, kind.physical());
+        t.i32(3, optional as i32);
+        t.binary(4, name.as_bytes());
+        match kind {
+            Kind::Timestamp => {
+                t.i32(6, 9); // TIMESTAMP_MILLIS
+                t.begin(10);
+                t.begin(8);
+                t.bool(1, true); // adjusted to UTC
+                t.begin(2);
+                t.begin(1); // milliseconds
+                t.end();
+                t.end();
+                t.end();
+                t.end();
+            }
+            Kind::String => {
+                t.i32(6, 0); // UTF8
+                t.begin(10);
+                t.begin(1);
+                t.end();
+                t.end();
+            }
+            Kind::Double | Kind::Int64 => {}
+        }
+        t.end();
+    }
+    t.i64(3, rows as i64);
+    t.list(4, STRUCT, row_groups.len());
+    for chunks in row_groups {
+        t.element();
+        t.list(1, STRUCT, chunks.len());
+        for chunk in chunks {
+            let (name, kind, _) = SCHEMA[chunk.column];
+            t.element();
+            t.i64(2, chunk.offset as i64);
+            t.begin(3);
+            t.i32(1, kind.physical());
+            t.list(2, I32, 2);
+            t.element_i32(PLAIN);
+            t.element_i32(RLE);
+            t.list(3, BINARY, 1);
+            t.element_binary(name.as_bytes());
+            t.i32(4, 0); // uncompressed
+            t.i64(5, chunk.rows as i64);
+            t.i64(6, chunk.size as i64);
+            t.i64(7, chunk.size as i64);
+            t.i64(9, chunk.offset as i64);
+            t.begin(12);
+            t.i64(3, chunk.nulls as i64);
+            if let Some((min, max)) = chunk.range {
+                t.binary(5, &max.to_le_bytes());
+                t.binary(6, &min.to_le_bytes());
+            }
+            t.end();
+            t.end();
+            t.end();
+        }
+        let size: u64 = chunks.iter().map(|c| c.size).sum();
+        t.i64(2, size as i64);
+        t.i64(3, chunks.first().map_or(0, |c| c.rows) as i64);
+        t.end();
+    }
+    t.binary(
+        6,
+        format!("loadrs version {}", env!("CARGO_PKG_VERSION")).as_bytes(),
+    );
+    t.finish()
+}
+
+// Thrift compact protocol types.
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+/// Writes structs in the Thrift compact protocol, which Parquet's metadata
+/// is serialized with.
+struct Compact {
+    buf: Vec<u8>,
+    /// The last field ID written in each struct being written, innermost
+    /// last, since field IDs are written as deltas.
+    fields: Vec<i16>,
+}
+
+impl Default for Compact {
+    fn default() -> Self {
+        Self {
+            buf: Vec::new(),
+            fields: vec![0],
+        }
+    }
+}
+
+impl Compact {
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.fields.last_mut().expect("Not in a struct.");
+        match id - *last {
+            delta @ 1..=15 => self.buf.push((delta as u8) << 4 | kind),
+            _ => {
+                self.buf.push(kind);
+                varint(&mut self.buf, zigzag(id as i64));
+            }
+        }
+        *last = id;
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        varint(&mut self.buf, zigzag(value as i64));
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        varint(&mut self.buf, zigzag(value));
+    }
+
+    fn bool(&mut self, id: i16, value: bool) {
+        // Booleans are stored in the field's type.
+        self.field(id, if value { 1 } else { 2 });
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, BINARY);
+        self.element_binary(value);
+    }
+
+    /// Start a struct field, ended with [`Compact::end`].
+    fn begin(&mut self, id: i16) {
+        self.field(id, STRUCT);
+        self.fields.push(0);
+    }
+
+    fn end(&mut self) {
+        self.buf.push(0);
+        self.fields.pop();
+    }
+
+    /// Start a list field of `len` elements of type `kind`.
+    fn list(&mut self, id: i16, kind: u8, len: usize) {
+        self.field(id, LIST);
+        if len < 15 {
+            self.buf.push((len as u8) << 4 | kind);
+        } else {
+            self.buf.push(0xf0 | kind);
+            varint(&mut self.buf, len as u64);
+        }
+    }
+
+    /// Start a struct in a list, ended with [`Compact::end`].
+    fn element(&mut self) {
+        self.fields.push(0);
+    }
+
+    fn element_i32(&mut self, value: i32) {
+        varint(&mut self.buf, zigzag(value as i64));
+    }
+
+    fn element_binary(&mut self, value: &[u8]) {
+        varint(&mut self.buf, value.len() as u64);
+        self.buf.extend(value);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.buf.push(0);
+        self.buf
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: u64, username: &str, fair_share: Option<f64>) -> UserSample {
+        UserSample {
+            timestamp,
+            username: username.to_string(),
+            equivalent_cores: 2.0,
+            system_cpu_share: 12.5,
+            memory: 1 << 30,
+            fair_share,
+        }
+    }
+
+    /// The file's footer, checking the magic at both ends and that the
+    /// footer's length fits between them.
+    fn footer(file: &[u8]) -> &[u8] {
+        assert_eq!(&file[..4], MAGIC);
+        assert_eq!(&file[file.len() - 4..], MAGIC);
+        let length = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap());
+        let start = file.len() - 8 - length as usize;
+        assert!(start >= MAGIC.len());
+        &file[start..file.len() - 8]
+    }
+
+    #[test]
+    fn files_are_framed_by_magic_and_footer() {
+        let samples = [
+            sample(1_700_000_000, "alice", Some(25.0)),
+            sample(1_700_000_000, "bob", None),
+            sample(1_700_000_060, "alice", Some(50.0)),
+        ];
+        let mut file = Vec::new();
+        write(&mut file, &samples).unwrap();
+        let footer = footer(&file);
+        // FileMetaData starts with field 1, the format version, 1.
+        assert_eq!(&footer[..2], [0x15, 0x02]);
+        // The last column chunk ends where the footer starts.
+        let (page, _, _) = page(values(SCHEMA.len() - 1, &samples), true);
+        let start = file.len() - 8 - footer.len();
+        assert_eq!(&file[start - page.len()..start], page);
+    }
+
+    #[test]
+    fn nulls_are_left_out_by_definition_levels() {
+        let samples = [
+            sample(1_700_000_000, "alice", Some(25.0)),
+            sample(1_700_000_000, "bob", None),
+            sample(1_700_000_000, "carol", None),
+            sample(1_700_000_060, "alice", Some(50.0)),
+        ];
+        let (page, nulls, range) = page(values(5, &samples), true);
+        assert_eq!(nulls, 2);
+        assert_eq!(range, None);
+        let mut expected = vec![6, 0, 0, 0];
+        // RLE runs of (length << 1, level): one value, two nulls, one value.
+        expected.extend([2, 1, 4, 0, 2, 1]);
+        expected.extend(25.0f64.to_le_bytes());
+        expected.extend(50.0f64.to_le_bytes());
+        assert_eq!(page, expected);
+    }
+
+    #[test]
+    fn required_columns_have_no_definition_levels() {
+        let samples = [sample(1_700_000_000, "alice", None)];
+        let (cores, nulls, _) = page(values(2, &samples), false);
+        assert_eq!(nulls, 0);
+        assert_eq!(cores, 2.0f64.to_le_bytes());
+        let (timestamps, _, range) = page(values(0, &samples), false);
+        assert_eq!(timestamps, 1_700_000_000_000i64.to_le_bytes());
+        assert_eq!(range, Some((1_700_000_000_000, 1_700_000_000_000)));
+    }
+
+    #[test]
+    fn long_runs_have_varint_lengths() {
+        let mut page = Vec::new();
+        definition_levels(&mut page, &[true; 100]);
+        // 100 << 1 = 200, as a two byte varint.
+        assert_eq!(page, [3, 0, 0, 0, 0xc8, 0x01, 1]);
+    }
+
+    #[test]
+    fn empty_exports_are_valid_files() {
+        let mut file = Vec::new();
+        write(&mut file, &[]).unwrap();
+        let footer = footer(&file);
+        // Nothing but the magic, footer, and footer length.
+        assert_eq!(footer.len(), file.len() - 12);
+        assert_eq!(&footer[..2], [0x15, 0x02]);
+    }
+}
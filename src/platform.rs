@@ -0,0 +1,334 @@
+//! Differences between platforms in who owns a process.
+//!
+//! On Linux and macOS, processes are owned by numeric UIDs, and sysinfo's
+//! user list comes from the password database (on macOS, leaving out the
+//! `_`-prefixed system accounts). On Windows, they are owned by SIDs, and the
+//! user list only has local accounts, so processes of services and domain
+//! users on RDS hosts may not resolve.
+
+use sysinfo::{Uid, Users};
+
+/// The lowest UID `useradd` gives people on Linux, where `/etc/login.defs`
+/// doesn't say.
+pub const LINUX_UID_MIN: u32 = 1000;
+
+/// The lowest UID people get on this platform.
+#[cfg(unix)]
+const DEFAULT_UID_MIN: u32 = if cfg!(target_os = "macos") {
+    500
+} else {
+    LINUX_UID_MIN
+};
+
+/// The `nobody` account, which owns unprivileged daemons.
+const NOBODY: u32 = 65534;
+
+/// The label for processes whose owner can't be resolved: `UID:1001`, or on
+/// Windows, `SID:S-1-5-21-…`.
+pub fn unresolved(uid: Option<&Uid>) -> String {
+    let prefix = if cfg!(windows) { "SID" } else { "UID" };
+    format!(
+        "{}:{}",
+        prefix,
+        uid.map_or("Unknown".to_string(), |uid| uid.to_string())
+    )
+}
+
+/// Whether `username` is a label from [`unresolved`] rather than a real
+/// name, which can't contain `:` on any platform.
+pub fn is_unresolved(username: &str) -> bool {
+    username.starts_with("UID:") || username.starts_with("SID:")
+}
+
+/// Whether `uid` belongs to a system account rather than a person: below
+/// `UID_MIN` from `/etc/login.defs`, or `nobody`.
+#[cfg(unix)]
+pub fn is_system(uid: &Uid) -> bool {
+    static UID_MIN: std::sync::OnceLock<u32> = std::sync::OnceLock::new();
+    let Ok(uid) = uid.to_string().parse::<u32>() else {
+        return false;
+    };
+    let min = *UID_MIN.get_or_init(|| {
+        std::fs::read_to_string("/etc/login.defs")
+            .ok()
+            .and_then(|defs| uid_min(&defs))
+            .unwrap_or(DEFAULT_UID_MIN)
+    });
+    is_system_uid(uid, min)
+}
+
+/// The `UID_MIN` setting in the contents of `/etc/login.defs`, if set.
+pub fn uid_min(login_defs: &str) -> Option<u32> {
+    login_defs.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("UID_MIN") => fields.next()?.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+/// Whether `uid` is a system account's, given the lowest UID people get.
+pub fn is_system_uid(uid: u32, uid_min: u32) -> bool {
+    uid < uid_min || uid == NOBODY
+}
+
+/// Whether `uid` belongs to a system account rather than a person: anything
+/// but a local or domain account, whose SIDs start with `S-1-5-21-`.
+#[cfg(not(unix))]
+pub fn is_system(uid: &Uid) -> bool {
+    is_system_sid(&uid.to_string())
+}
+
+#[cfg(any(not(unix), test))]
+fn is_system_sid(sid: &str) -> bool {
+    !sid.starts_with("S-1-5-21-")
+}
+
+/// The name of the user running `load`.
+pub fn current_user() -> Option<String> {
+    let uid = current_uid()?;
+    Users::new_with_refreshed_list()
+        .get_user_by_id(&uid)
+        .map(|user| user.name().to_string())
+        .or_else(|| system_account(&uid))
+}
+
+#[cfg(unix)]
+fn current_uid() -> Option<Uid> {
+    use std::str::FromStr;
+    // SAFETY: getuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    Uid::from_str(&uid.to_string()).ok()
+}
+
+/// Windows has no getuid, so look up the owner of our own process instead.
+#[cfg(not(unix))]
+fn current_uid() -> Option<Uid> {
+    use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+    let pid = sysinfo::get_current_pid().ok()?;
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        ProcessRefreshKind::new().with_user(UpdateKind::Always),
+    );
+    sys.process(pid)?.user_id().cloned()
+}
+
+/// Look up a UID in the password database through NSS, like `getent passwd`,
+/// which also finds LDAP and SSSD users missing from sysinfo's user list.
+#[cfg(unix)]
+pub fn lookup(uid: &Uid) -> Option<String> {
+    use std::ffi::CStr;
+    let uid: libc::uid_t = uid.to_string().parse().ok()?;
+    let mut buf = vec![0; 4096];
+    loop {
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        // SAFETY: getpwuid_r writes strings only into `buf`, which outlives
+        // the CStr read from `passwd.pw_name`.
+        unsafe {
+            let error =
+                libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result);
+            // Directory entries can have more group members or longer
+            // fields than fit in the buffer.
+            if error == libc::ERANGE && buf.len() < 1 << 20 {
+                buf.resize(buf.len() * 2, 0);
+                continue;
+            }
+            if result.is_null() {
+                return None;
+            }
+            return Some(
+                CStr::from_ptr(passwd.pw_name)
+                    .to_string_lossy()
+                    .into_owned(),
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn lookup(_uid: &Uid) -> Option<String> {
+    None
+}
+
+/// The UID of a user, from its name in the password database through NSS,
+/// or from an [`unresolved`] label like `UID:1001`.
+#[cfg(unix)]
+pub fn uid(username: &str) -> Option<u32> {
+    if let Some(uid) = username.strip_prefix("UID:") {
+        return uid.parse().ok();
+    }
+    passwd(username, |passwd| passwd.pw_uid)
+}
+
+#[cfg(not(unix))]
+pub fn uid(_username: &str) -> Option<u32> {
+    None
+}
+
+/// A user's GECOS field from the password database, e.g. `Jane Smith,Room
+/// 101,555-0100,,jsmith@example.edu`, if it isn't empty.
+#[cfg(unix)]
+pub fn gecos(username: &str) -> Option<String> {
+    use std::ffi::CStr;
+    passwd(username, |passwd| {
+        if passwd.pw_gecos.is_null() {
+            return String::new();
+        }
+        // SAFETY: `pw_gecos` points to a string in the lookup's buffer,
+        // which outlives this closure.
+        unsafe { CStr::from_ptr(passwd.pw_gecos) }
+            .to_string_lossy()
+            .into_owned()
+    })
+    .filter(|gecos| !gecos.trim().is_empty())
+}
+
+#[cfg(not(unix))]
+pub fn gecos(_username: &str) -> Option<String> {
+    None
+}
+
+/// Look up a user by name in the password database through NSS, like
+/// `getent passwd`, and read their entry with `f`.
+#[cfg(unix)]
+fn passwd<T>(username: &str, f: impl FnOnce(&libc::passwd) -> T) -> Option<T> {
+    use std::ffi::CString;
+    let name = CString::new(username).ok()?;
+    let mut buf = vec![0; 4096];
+    loop {
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        // SAFETY: getpwnam_r writes strings only into `buf`, which outlives
+        // the call to `f`.
+        unsafe {
+            let error = libc::getpwnam_r(
+                name.as_ptr(),
+                &mut passwd,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            );
+            if error == libc::ERANGE && buf.len() < 1 << 20 {
+                buf.resize(buf.len() * 2, 0);
+                continue;
+            }
+            if result.is_null() {
+                return None;
+            }
+        }
+        return Some(f(&passwd));
+    }
+}
+
+/// The name of an account that sysinfo's user list leaves out: on macOS, the
+/// hidden system accounts (e.g. `_windowserver`), from the password database.
+#[cfg(target_os = "macos")]
+pub fn system_account(uid: &Uid) -> Option<String> {
+    lookup(uid)
+}
+
+/// The name of an account that sysinfo's user list leaves out: on Windows,
+/// the service accounts, which own many processes, by their well-known SIDs.
+#[cfg(not(target_os = "macos"))]
+pub fn system_account(uid: &Uid) -> Option<String> {
+    well_known_sid(&uid.to_string()).map(str::to_string)
+}
+
+/// The name of a service account with a well-known SID.
+#[cfg(any(not(target_os = "macos"), test))]
+fn well_known_sid(sid: &str) -> Option<&'static str> {
+    const WELL_KNOWN_SIDS: [(&str, &str); 3] = [
+        ("S-1-5-18", "SYSTEM"),
+        ("S-1-5-19", "LOCAL SERVICE"),
+        ("S-1-5-20", "NETWORK SERVICE"),
+    ];
+    WELL_KNOWN_SIDS
+        .iter()
+        .find(|(known, _)| *known == sid)
+        .map(|(_, name)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn unresolved_labels_uids() {
+        use std::str::FromStr;
+        let uid = Uid::from_str("1001").unwrap();
+        assert_eq!(unresolved(Some(&uid)), "UID:1001");
+        assert_eq!(unresolved(None), "UID:Unknown");
+    }
+
+    #[test]
+    fn unresolved_labels_are_recognized() {
+        assert!(is_unresolved("UID:1001"));
+        assert!(is_unresolved("UID:Unknown"));
+        assert!(is_unresolved(
+            "SID:S-1-5-21-1004336348-1177238915-682003330-512"
+        ));
+        assert!(!is_unresolved("alice"));
+        assert!(!is_unresolved("uid"));
+        assert!(!is_unresolved("SYSTEM"));
+    }
+
+    #[test]
+    fn uid_min_is_read_from_login_defs() {
+        let defs = "\
+# Min/max values for automatic uid selection in useradd
+#UID_MIN 2000
+UID_MIN\t\t\t 1000
+UID_MAX\t\t\t60000
+";
+        assert_eq!(uid_min(defs), Some(1000));
+        assert_eq!(uid_min("UID_MIN 500\n"), Some(500));
+        assert_eq!(uid_min("UID_MAX 60000\n"), None);
+        assert_eq!(uid_min("UID_MIN\n"), None);
+        assert_eq!(uid_min("UID_MIN lots\n"), None);
+        assert_eq!(uid_min(""), None);
+    }
+
+    #[test]
+    fn system_uids_are_below_uid_min_or_nobody() {
+        assert!(is_system_uid(0, 1000));
+        assert!(is_system_uid(999, 1000));
+        assert!(!is_system_uid(1000, 1000));
+        assert!(!is_system_uid(60000, 1000));
+        assert!(is_system_uid(NOBODY, 1000));
+        assert!(!is_system_uid(501, 500));
+    }
+
+    #[test]
+    fn system_sids_are_not_local_or_domain_accounts() {
+        assert!(is_system_sid("S-1-5-18"));
+        assert!(is_system_sid(
+            "S-1-5-80-956008885-3418522649-1831038044-1853292631"
+        ));
+        assert!(!is_system_sid(
+            "S-1-5-21-1004336348-1177238915-682003330-1001"
+        ));
+    }
+
+    #[test]
+    fn well_known_sids_are_named() {
+        assert_eq!(well_known_sid("S-1-5-18"), Some("SYSTEM"));
+        assert_eq!(well_known_sid("S-1-5-19"), Some("LOCAL SERVICE"));
+        assert_eq!(well_known_sid("S-1-5-20"), Some("NETWORK SERVICE"));
+        assert_eq!(
+            well_known_sid("S-1-5-21-1004336348-1177238915-682003330-1001"),
+            None
+        );
+        assert_eq!(well_known_sid("1001"), None);
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn uids_are_not_well_known_system_accounts() {
+        use std::str::FromStr;
+        assert_eq!(system_account(&Uid::from_str("0").unwrap()), None);
+    }
+}
@@ -0,0 +1,241 @@
+//! How each user's fair share of the machine is computed.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// A fair share policy. Under every policy except [`FairSharePolicy::Fixed`],
+/// capacity is split among the *active* users, so idle users don't dilute
+/// everyone else's share.
+#[derive(Debug, Clone, Default)]
+pub enum FairSharePolicy {
+    /// Every active user gets an equal share: 100% / active users.
+    #[default]
+    Equal,
+    /// Every user gets this percent of total system CPU.
+    Fixed(f64),
+    /// Active users split capacity in proportion to their weights, e.g. a
+    /// lab that bought twice the share of the machine gets weight 2.
+    Weighted(Weights),
+}
+
+/// Per-user and per-group weights for [`FairSharePolicy::Weighted`].
+#[derive(Debug, Clone, Default)]
+pub struct Weights {
+    pub users: HashMap<String, f64>,
+    /// A user in several weighted groups gets the largest of their weights.
+    pub groups: HashMap<String, f64>,
+    /// Weight for users not otherwise weighted (usually 1).
+    pub default: f64,
+}
+
+/// Explicit entitlements, e.g. from contracts, that replace the computed
+/// fair share for some users. While they're active, their quotas are set
+/// aside and everyone else splits what's left under the policy.
+#[derive(Debug, Clone, Default)]
+pub struct Quotas {
+    pub users: HashMap<String, Quota>,
+}
+
+/// One user's entitlement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quota {
+    /// A number of cores, e.g. `16 cores`.
+    Cores(f64),
+    /// A percent of total system CPU, e.g. `25%`.
+    Percent(f64),
+}
+
+impl Quota {
+    /// The quota as a percent of total system CPU, given the number of cores.
+    pub fn share(&self, cores: f64) -> f64 {
+        match self {
+            Quota::Cores(quota) => 100.0 * quota / cores,
+            Quota::Percent(percent) => *percent,
+        }
+    }
+}
+
+impl FromStr for Quota {
+    type Err = String;
+
+    /// Parse a number of cores, e.g. `16 cores` or `16c`, or a percent, e.g.
+    /// `25%`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || format!("expected e.g. 16 cores, 16c, or 25%, got '{}'", s);
+        let amount = |s: &str| match s.trim().parse::<f64>() {
+            Ok(amount) if amount.is_finite() && amount >= 0.0 => Ok(amount),
+            _ => Err(invalid()),
+        };
+        let s = s.trim();
+        if let Some(percent) = s.strip_suffix('%') {
+            return Ok(Quota::Percent(amount(percent)?));
+        }
+        ["cores", "core", "c"]
+            .iter()
+            .find_map(|unit| s.strip_suffix(unit))
+            .ok_or_else(invalid)
+            .and_then(amount)
+            .map(Quota::Cores)
+    }
+}
+
+impl Quotas {
+    /// Read a quotas file, with one `user = 16 cores` (or `16c`) or `user = 25%` per
+    /// line. Blank lines and `#` comments are ignored.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut users = HashMap::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = || {
+                format!(
+                    "line {}: expected e.g. alice = 16 cores or alice = 25%, got '{}'",
+                    i + 1,
+                    line
+                )
+            };
+            let (user, quota) = line.split_once('=').ok_or_else(invalid)?;
+            let quota = quota.parse().map_err(|_| invalid())?;
+            users.insert(user.trim().to_string(), quota);
+        }
+        Ok(Self { users })
+    }
+
+    /// A user's quota, as a percent of total system CPU, if they have one.
+    pub fn share(&self, username: &str, cores: f64) -> Option<f64> {
+        self.users.get(username).map(|quota| quota.share(cores))
+    }
+
+    /// Check that the quotas fit on a machine with `cores` cores. If they
+    /// add up to over 100%, users with quotas can't all get theirs, and
+    /// everyone else's fair share would be nothing.
+    pub fn fit(&self, cores: f64) -> Result<(), String> {
+        let total: f64 = self.users.values().map(|quota| quota.share(cores)).sum();
+        if total > 100.0 {
+            return Err(format!(
+                "quotas add up to {:.2}% of {} cores, over 100%",
+                total, cores
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The name of a policy, as reported in [`FairShare`](crate::FairShare).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyKind {
+    Equal,
+    Fixed,
+    Weighted,
+}
+
+impl FairSharePolicy {
+    pub fn kind(&self) -> PolicyKind {
+        match self {
+            FairSharePolicy::Equal => PolicyKind::Equal,
+            FairSharePolicy::Fixed(_) => PolicyKind::Fixed,
+            FairSharePolicy::Weighted(_) => PolicyKind::Weighted,
+        }
+    }
+
+    /// Whether looking up a user's weight needs their groups.
+    pub(crate) fn needs_groups(&self) -> bool {
+        matches!(self, FairSharePolicy::Weighted(w) if !w.groups.is_empty())
+    }
+
+    /// A user's weight, given the names of their groups.
+    pub fn weight(&self, username: &str, groups: &[String]) -> f64 {
+        let FairSharePolicy::Weighted(weights) = self else {
+            return 1.0;
+        };
+        if let Some(weight) = weights.users.get(username) {
+            return *weight;
+        }
+        groups
+            .iter()
+            .filter_map(|group| weights.groups.get(group))
+            .copied()
+            .reduce(f64::max)
+            .unwrap_or(weights.default)
+    }
+
+    /// The fair share for one unit of weight, given the percent of capacity
+    /// to split and the total weight of the active users splitting it.
+    pub fn base_share(&self, capacity: f64, active_weight: f64) -> f64 {
+        match self {
+            FairSharePolicy::Fixed(share) => *share,
+            _ => capacity / active_weight,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotas_are_cores_or_percents() {
+        assert_eq!("16 cores".parse(), Ok(Quota::Cores(16.0)));
+        assert_eq!("1 core".parse(), Ok(Quota::Cores(1.0)));
+        assert_eq!("16c".parse(), Ok(Quota::Cores(16.0)));
+        assert_eq!(" 2.5 c ".parse(), Ok(Quota::Cores(2.5)));
+        assert_eq!("25%".parse(), Ok(Quota::Percent(25.0)));
+        assert_eq!("0%".parse(), Ok(Quota::Percent(0.0)));
+        for quota in ["16", "cores", "-1c", "inf%", "NaN%", "16 cpus", "25 %%", ""] {
+            assert!(quota.parse::<Quota>().is_err(), "{}", quota);
+        }
+    }
+
+    #[test]
+    fn quota_shares_are_percents_of_the_machine() {
+        assert_eq!(Quota::Cores(4.0).share(16.0), 25.0);
+        assert_eq!(Quota::Percent(25.0).share(16.0), 25.0);
+    }
+
+    #[test]
+    fn quotas_files_are_parsed() {
+        let quotas = Quotas::parse(
+            "# Contracts for 2024\n\
+             alice = 16 cores\n\
+             \n\
+             bob=25%  # through June\n",
+        )
+        .unwrap();
+        assert_eq!(quotas.users.len(), 2);
+        assert_eq!(quotas.users["alice"], Quota::Cores(16.0));
+        assert_eq!(quotas.share("bob", 64.0), Some(25.0));
+        assert_eq!(quotas.share("carol", 64.0), None);
+    }
+
+    #[test]
+    fn bad_quota_lines_are_errors_with_line_numbers() {
+        let error = Quotas::parse("alice = 16 cores\nbob 25%\n").unwrap_err();
+        assert!(error.starts_with("line 2:"), "{}", error);
+        let error = Quotas::parse("alice = lots\n").unwrap_err();
+        assert!(error.starts_with("line 1:"), "{}", error);
+    }
+
+    #[test]
+    fn quotas_over_the_whole_machine_are_errors() {
+        let quotas = Quotas::parse("alice = 8 cores\nbob = 50%\n").unwrap();
+        assert!(quotas.fit(16.0).is_ok());
+        assert_eq!(
+            quotas.fit(8.0),
+            Err("quotas add up to 150.00% of 8 cores, over 100%".to_string())
+        );
+        assert!(Quotas::default().fit(1.0).is_ok());
+    }
+}
@@ -0,0 +1,77 @@
+//! Pressure Stall Information, read from `/proc/pressure` (Linux 4.20+).
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+/// Share of time tasks were stalled waiting for each resource.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Pressure {
+    pub cpu: Option<Stall>,
+    pub memory: Option<Stall>,
+    pub io: Option<Stall>,
+}
+
+/// Stall averages for one resource.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Stall {
+    /// Time at least one task was stalled.
+    pub some: Averages,
+    /// Time every non-idle task was stalled at once, i.e. the machine did
+    /// no work at all (not reported for CPU on older kernels).
+    pub full: Option<Averages>,
+}
+
+/// Percent of time stalled, averaged over the last 10, 60, and 300 seconds.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Averages {
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+}
+
+/// The system's pressure, or `None` if the kernel doesn't report it.
+pub fn pressure() -> Option<Pressure> {
+    let pressure = Pressure {
+        cpu: stall("cpu"),
+        memory: stall("memory"),
+        io: stall("io"),
+    };
+    (pressure.cpu.is_some() || pressure.memory.is_some() || pressure.io.is_some())
+        .then_some(pressure)
+}
+
+/// Parse `/proc/pressure/<resource>`, with lines like
+/// `some avg10=4.89 avg60=4.50 avg300=3.43 total=24789067`.
+fn stall(resource: &str) -> Option<Stall> {
+    let contents = fs::read_to_string(format!("/proc/pressure/{}", resource)).ok()?;
+    let mut some = None;
+    let mut full = None;
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next()?;
+        let mut averages = Averages {
+            avg10: 0.0,
+            avg60: 0.0,
+            avg300: 0.0,
+        };
+        for field in fields {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+            let value = value.parse().unwrap_or(0.0);
+            match key {
+                "avg10" => averages.avg10 = value,
+                "avg60" => averages.avg60 = value,
+                "avg300" => averages.avg300 = value,
+                _ => {}
+            }
+        }
+        match kind {
+            "some" => some = Some(averages),
+            "full" => full = Some(averages),
+            _ => {}
+        }
+    }
+    Some(Stall { some: some?, full })
+}
@@ -0,0 +1,69 @@
+//! `--profile`: presets of thresholds, grace periods, runtime limits, and
+//! checks for common classes of machine, so a fleet can share one config
+//! file per class rather than hand-tuned flags per machine.
+//!
+//! A profile sets defaults just like the config file does, and the config
+//! file and command line override it.
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Profile {
+    /// Shared interactive login nodes: a low threshold, short grace period,
+    /// a 2 hour runtime limit, spike detection, and notices on offending
+    /// users' terminals
+    LoginNode,
+    /// Batch compute nodes: only sustained overload counts, a long grace
+    /// period, and no runtime limit
+    ComputeNode,
+    /// Single-user or small group workstations: desktop notifications about
+    /// excessive load or your own usage
+    Workstation,
+}
+
+impl Profile {
+    /// The profile's defaults, in the config file's format.
+    pub fn defaults(self) -> &'static str {
+        match self {
+            Profile::LoginNode => {
+                r#"
+                threshold = 50
+                load_window = "5"
+                min_dwell = "1m"
+                grace_period = "2m"
+                max_runtime = "2h"
+                zombie_threshold = 10
+                spikes = true
+                suggest_renice = true
+                alert_tty = true
+                alert_cooldown = "30m"
+                exempt_users = ["root"]
+                "#
+            }
+            Profile::ComputeNode => {
+                r#"
+                threshold = 100
+                hysteresis = 10
+                load_window = "15"
+                min_dwell = "5m"
+                grace_period = "15m"
+                zombie_threshold = 50
+                alert_cooldown = "1h"
+                exempt_users = ["root"]
+                "#
+            }
+            Profile::Workstation => {
+                r#"
+                threshold = 100
+                load_window = "5"
+                grace_period = "5m"
+                notify = true
+                "#
+            }
+        }
+    }
+
+    pub fn name(self) -> String {
+        self.to_possible_value().unwrap().get_name().to_string()
+    }
+}
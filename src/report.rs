@@ -0,0 +1,595 @@
+use serde::{Deserialize, Serialize};
+
+use crate::contacts::Contact;
+use crate::cpustat::CpuWait;
+use crate::fds::FileHandles;
+use crate::gpu::GpuUsage;
+use crate::k8s::PodUsage;
+use crate::login::Login;
+use crate::net::NetUsage;
+use crate::policy::PolicyKind;
+use crate::pressure::Pressure;
+use crate::slurm::JobUsage;
+use crate::thermal::Thermal;
+use sysinfo::Process;
+
+/// A single user's aggregated CPU and memory usage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserUsage {
+    pub username: String,
+    /// How many UIDs that couldn't be resolved are combined into this row,
+    /// with `merge_unknown`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unresolved_uids: Option<usize>,
+    /// Sum of process CPU usage, where 100% is one fully used core; smoothed
+    /// if requested with [`Sampler::ewma`](crate::Sampler::ewma).
+    pub total_cpu_usage: f64,
+    /// Unsmoothed CPU usage in this sample, if smoothing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_cpu_usage: Option<f64>,
+    pub equivalent_cores: f64,
+    /// CPU time used since the previous sample (unsmoothed), in seconds.
+    #[serde(default)]
+    pub cpu_seconds: f64,
+    /// CPU time used over every sample so far, in seconds, if requested with
+    /// [`Sampler::cumulative`](crate::Sampler::cumulative).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time: Option<f64>,
+    /// Percent of total system CPU capacity.
+    pub system_cpu_share: f64,
+    /// This user's fair share of system CPU, in percent.
+    #[serde(deserialize_with = "infinite_if_null")]
+    pub fair_share: f64,
+    /// The user's quota from [`Sampler::quotas`](crate::Sampler::quotas),
+    /// in percent, if they have one, in which case it's their fair share.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<f64>,
+    /// Long-running fair-share score from the user's decayed usage, if kept
+    /// in a [`Ledger`](crate::fairshare::Ledger).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fairshare_score: Option<f64>,
+    /// Mean cores the user used over the recent past, from the samples in a
+    /// [`History`](crate::history::History), to compare current usage with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usual_cores: Option<f64>,
+    /// Change in equivalent cores since the previous sample, counting users
+    /// missing from it as idle, from the second sample on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cores_change: Option<f64>,
+    /// Seconds the user has continuously been above their fair share, as
+    /// of this sample (0 on the first sample over it), if they are above it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_over_fair_share: Option<u64>,
+    /// Sum of process resident set sizes, in bytes.
+    pub memory: u64,
+    /// Percent of total system memory, compared against the
+    /// [memory fair share](FairShare::memory).
+    pub memory_share: f64,
+    /// Number of processes, not counting their threads.
+    #[serde(default)]
+    pub process_count: usize,
+    /// Number of threads across all of the user's processes.
+    #[serde(default)]
+    pub thread_count: usize,
+    /// Number of zombie processes, which have exited but not been reaped.
+    #[serde(default)]
+    pub zombies: usize,
+    /// The user's top processes by CPU usage, if requested with
+    /// [`Sampler::processes`](crate::Sampler::processes).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub processes: Vec<ProcessUsage>,
+    /// Summed GPU usage, if requested with [`Sampler::gpu`](crate::Sampler::gpu).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gpu: Option<GpuUsage>,
+    /// The user's login sessions, if requested with
+    /// [`Sampler::logins`](crate::Sampler::logins). Empty if the user isn't
+    /// logged in, e.g. only running detached jobs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logins: Option<Vec<Login>>,
+    /// Summed disk I/O since the previous sample, if requested with
+    /// [`Sampler::io`](crate::Sampler::io).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk: Option<DiskUsage>,
+    /// Summed TCP throughput since the previous sample, if requested with
+    /// [`Sampler::net`](crate::Sampler::net).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub net: Option<NetUsage>,
+    /// Memory of the user's processes swapped out, in bytes, if requested
+    /// with [`Sampler::swap`](crate::Sampler::swap).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swap: Option<u64>,
+    /// File descriptors the user's processes have open, if requested with
+    /// [`Sampler::fds`](crate::Sampler::fds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fds: Option<u64>,
+    /// Mean nice value of the user's processes, weighted by their CPU usage
+    /// (or unweighted if they're idle), if requested with
+    /// [`Sampler::nice`](crate::Sampler::nice).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nice: Option<f64>,
+    /// CPU limit the kernel enforces on the user through their systemd user
+    /// slice's cgroup, in cores, if requested with
+    /// [`Sampler::cgroup_limits`](crate::Sampler::cgroup_limits) and they
+    /// have one (Linux only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_limit: Option<f64>,
+    /// Who the user is and how to reach them, if requested with
+    /// [`Sampler::contacts`](crate::Sampler::contacts).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<Contact>,
+    /// Whether the user is a system account rather than a person, e.g. a
+    /// daemon's.
+    #[serde(default)]
+    pub system_account: bool,
+}
+
+/// Bytes read from and written to disk over the sampling interval.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DiskUsage {
+    pub read_bytes: u64,
+    pub written_bytes: u64,
+    /// Bytes read per second.
+    pub read_rate: f64,
+    /// Bytes written per second.
+    pub write_rate: f64,
+}
+
+/// A single process's CPU and memory usage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessUsage {
+    pub pid: u32,
+    /// The parent process's PID, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u32>,
+    pub command: String,
+    /// Process CPU usage, where 100% is one fully used core.
+    pub cpu_usage: f64,
+    /// Resident set size, in bytes.
+    pub memory: u64,
+    /// Seconds since the process started.
+    pub run_time: u64,
+    /// The container the process runs in, if any (Linux only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// The process's nice value, if requested with
+    /// [`Sampler::nice`](crate::Sampler::nice) (Unix only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nice: Option<i32>,
+}
+
+impl From<&Process> for ProcessUsage {
+    fn from(p: &Process) -> Self {
+        Self {
+            pid: p.pid().as_u32(),
+            parent: p.parent().map(|pid| pid.as_u32()),
+            command: command_line(p),
+            cpu_usage: cpu_usage(p.cpu_usage()),
+            memory: p.memory(),
+            run_time: p.run_time(),
+            container: None,
+            nice: None,
+        }
+    }
+}
+
+/// A CPU usage reading, or 0 if it isn't a number, which sysinfo can report
+/// for processes that exit between refreshes.
+pub(crate) fn cpu_usage(reading: f32) -> f64 {
+    if reading.is_finite() && reading > 0.0 {
+        reading as f64
+    } else {
+        0.0
+    }
+}
+
+/// A process's command line, or its name if the command line is unavailable
+/// (e.g. for kernel threads).
+pub(crate) fn command_line(p: &Process) -> String {
+    let cmd = p.cmd();
+    if cmd.is_empty() {
+        p.name().to_string_lossy().into_owned()
+    } else {
+        cmd.iter()
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// The column to sort users by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Cpu,
+    Mem,
+    /// Alphabetically by username (ascending).
+    User,
+    /// Share of system CPU.
+    Share,
+    /// How far the user's CPU share is above their fair share.
+    Excess,
+    /// Disk bytes read and written.
+    Io,
+    /// Network bytes sent and received.
+    Net,
+}
+
+/// What usage is grouped by. Groups take the place of users throughout a
+/// report: each gets a fair share, and can be an offender.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    #[default]
+    User,
+    /// The container a process runs in, or its user for processes outside
+    /// any container.
+    Container,
+    /// The user's primary Unix group, unless mapped to another group with
+    /// [`Sampler::group_map`](crate::Sampler::group_map).
+    Group,
+    /// Ranges of the owners' UIDs, e.g. system accounts, people, and
+    /// service accounts, set with
+    /// [`Sampler::uid_ranges`](crate::Sampler::uid_ranges).
+    UidRange,
+}
+
+/// How loaded the system is, with hysteresis: the load has to drop a margin
+/// below a threshold to leave the state it entered by crossing it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadState {
+    #[default]
+    Ok,
+    /// Above the [warning threshold](crate::Sampler::warn_threshold).
+    Warn,
+    /// Above the excessive load [threshold](crate::Sampler::threshold).
+    Crit,
+}
+
+impl LoadState {
+    pub fn name(self) -> &'static str {
+        match self {
+            LoadState::Ok => "OK",
+            LoadState::Warn => "WARN",
+            LoadState::Crit => "CRIT",
+        }
+    }
+}
+
+/// How the fair share was arrived at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FairShare {
+    /// Fair share as a percent of total system CPU capacity, for a user
+    /// with weight 1. See [`UserUsage::fair_share`] for each user's share.
+    #[serde(deserialize_with = "infinite_if_null")]
+    pub value: f64,
+    pub policy: PolicyKind,
+    pub user_specified: bool,
+    pub active_threshold: f64,
+    pub active_users: usize,
+    /// Sum of the active users' weights (the number of active users,
+    /// unless the policy is weighted), not counting users with quotas.
+    pub active_weight: f64,
+    /// Percent of system CPU set aside for active users with quotas, which
+    /// the other active users don't split.
+    #[serde(default)]
+    pub reserved: f64,
+    /// Fair share of system memory, in percent: all of it split equally
+    /// between the users active by CPU or memory.
+    #[serde(default = "infinite", deserialize_with = "infinite_if_null")]
+    pub memory: f64,
+    /// Users using more than the active threshold of CPU or memory, who
+    /// split memory.
+    #[serde(default)]
+    pub memory_active_users: usize,
+}
+
+/// The machine a report is from, so shared reports describe themselves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Host {
+    pub hostname: String,
+    /// Seconds since boot.
+    pub uptime: u64,
+    /// The OS name and version, e.g. `Linux (Ubuntu 24.04)`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+    /// The kernel version, e.g. `6.8.0-45-generic`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<String>,
+}
+
+/// System-wide memory and swap, in bytes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SystemMemory {
+    pub total: u64,
+    pub available: u64,
+    pub swap_total: u64,
+    pub swap_used: u64,
+    /// Whether memory is nearly exhausted while swap is in use, so the
+    /// system is likely thrashing.
+    pub swapping: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LoadAverages {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+
+impl LoadAverages {
+    pub fn get(&self, window: LoadWindow) -> f64 {
+        match window {
+            LoadWindow::One => self.one,
+            LoadWindow::Five => self.five,
+            LoadWindow::Fifteen => self.fifteen,
+        }
+    }
+}
+
+/// Which load average the load state is based on. Longer windows ignore
+/// brief spikes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadWindow {
+    #[default]
+    One,
+    Five,
+    Fifteen,
+}
+
+impl LoadWindow {
+    pub fn minutes(self) -> u32 {
+        match self {
+            LoadWindow::One => 1,
+            LoadWindow::Five => 5,
+            LoadWindow::Fifteen => 15,
+        }
+    }
+}
+
+/// A user whose system CPU share has been above the fair share for at
+/// least the [grace period](crate::Sampler::grace_period).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Offender {
+    pub username: String,
+    pub system_cpu_share: f64,
+    pub fair_share: f64,
+    pub excess_usage: f64,
+    /// Seconds the user has continuously been above their fair share.
+    pub time_over_fair_share: u64,
+}
+
+/// A user whose share of system memory is above the fair share.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryOffender {
+    pub username: String,
+    /// Resident memory, in bytes.
+    pub memory: u64,
+    pub memory_share: f64,
+    pub fair_share: f64,
+    pub excess_usage: f64,
+}
+
+/// A user with more zombie (defunct) processes than the threshold, e.g. from
+/// a pipeline whose parent process never reaps its children.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Zombies {
+    pub username: String,
+    pub count: usize,
+    /// Processes that haven't reaped their zombies, which need fixing or
+    /// killing to clear them.
+    pub parent_pids: Vec<u32>,
+}
+
+/// A user holding most of the memory swapped out by processes, who slows
+/// everyone else down waiting on disk far more than a CPU hog would.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapHog {
+    pub username: String,
+    /// The user's swapped-out memory, in bytes.
+    pub swap: u64,
+    /// The user's percent of all processes' swapped-out memory.
+    pub swap_share: f64,
+}
+
+/// A user's processes that have run longer than the maximum runtime, e.g.
+/// on a login node where long jobs belong on the scheduler.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LongRunning {
+    pub username: String,
+    /// Longest running first.
+    pub processes: Vec<ProcessUsage>,
+}
+
+/// A user's heavy processes running at less than the nice value batch work
+/// must run at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Unniced {
+    pub username: String,
+    /// Busiest first.
+    pub processes: Vec<ProcessUsage>,
+}
+
+/// A user's processes that started since the previous sample and already use
+/// many cores.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewHeavy {
+    pub username: String,
+    /// Busiest first.
+    pub processes: Vec<ProcessUsage>,
+}
+
+/// A user whose cgroup CPU limit doesn't match their fair share, so either
+/// the policy or the limit is out of date.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitMismatch {
+    pub username: String,
+    /// The enforced limit, in cores.
+    pub cpu_limit: f64,
+    /// The user's fair share, in cores.
+    pub fair_share_cores: f64,
+}
+
+/// A user whose usage jumped well above their recent baseline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spike {
+    pub username: String,
+    pub equivalent_cores: f64,
+    /// Mean cores used over the recent samples.
+    pub baseline_cores: f64,
+    /// Standard deviations above the baseline (infinite if usage was
+    /// constant).
+    #[serde(deserialize_with = "infinite_if_null")]
+    pub sigmas: f64,
+}
+
+/// When the load will cross the excessive load threshold if it keeps growing
+/// at its recent rate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Forecast {
+    /// How fast the load is growing, in cores per minute.
+    pub cores_per_minute: f64,
+    /// Seconds until the load crosses the threshold.
+    pub seconds: u64,
+}
+
+/// Everything loadrs knows about the system after one sample.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadReport {
+    /// When the sample was taken, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The machine's hostname, uptime, OS, and kernel, if requested with
+    /// [`Sampler::host`](crate::Sampler::host).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<Host>,
+    pub total_cores: usize,
+    /// What [`LoadReport::users`] are grouped by.
+    #[serde(default)]
+    pub group_by: GroupBy,
+    pub fair_share: FairShare,
+    /// Users with non-zero CPU usage, sorted by descending CPU usage
+    /// unless re-sorted with [`LoadReport::sort_users`].
+    pub users: Vec<UserUsage>,
+    pub load_average: LoadAverages,
+    /// The load average the load state is based on.
+    #[serde(default)]
+    pub load_window: LoadWindow,
+    /// Excessive load threshold, as a percent of total cores.
+    pub threshold: f64,
+    /// Whether the load is in [`LoadState::Crit`].
+    pub excessive_load: bool,
+    #[serde(default)]
+    pub load_state: LoadState,
+    /// The state before this sample, if the state changed in this sample.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_load_state: Option<LoadState>,
+    /// When the load will become excessive, if it's growing and
+    /// forecast with [`Sampler::forecast`](crate::Sampler::forecast).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<Forecast>,
+    pub offenders: Vec<Offender>,
+    pub memory: SystemMemory,
+    /// Share of CPU time since the previous sample spent in I/O wait or
+    /// stolen by the hypervisor (Linux only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_wait: Option<CpuWait>,
+    /// Pressure Stall Information, where the kernel reports it (Linux only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure: Option<Pressure>,
+    /// CPU temperatures and frequencies, where the system reports them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thermal: Option<Thermal>,
+    /// The kernel's file table usage and limit, if requested with
+    /// [`Sampler::fds`](crate::Sampler::fds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_handles: Option<FileHandles>,
+    /// Users over their fair share of memory (never exempt users).
+    pub memory_offenders: Vec<MemoryOffender>,
+    /// Users whose usage suddenly jumped, if detected with
+    /// [`Sampler::spikes`](crate::Sampler::spikes).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spikes: Vec<Spike>,
+    /// Processes running longer than the maximum runtime, by user, if one
+    /// was set with [`Sampler::max_runtime`](crate::Sampler::max_runtime).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub long_running: Vec<LongRunning>,
+    /// Heavy processes at less than the batch nice value, by user, if one
+    /// was set with [`Sampler::batch_nice`](crate::Sampler::batch_nice).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unniced: Vec<Unniced>,
+    /// Heavy processes that started since the previous sample, by user, if
+    /// requested with [`Sampler::new_heavy`](crate::Sampler::new_heavy).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub new_heavy: Vec<NewHeavy>,
+    /// Users whose cgroup CPU limit is well above or below their fair share,
+    /// with [`Sampler::cgroup_limits`](crate::Sampler::cgroup_limits).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub limit_mismatches: Vec<LimitMismatch>,
+    /// Users with more zombie processes than the threshold.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub zombies: Vec<Zombies>,
+    /// The user holding most swapped-out memory, if any, with
+    /// [`Sampler::swap`](crate::Sampler::swap).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swap_hog: Option<SwapHog>,
+    /// Usage per Slurm job, if requested with
+    /// [`Sampler::slurm`](crate::Sampler::slurm).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<Vec<JobUsage>>,
+    /// Usage per Kubernetes pod, if requested with
+    /// [`Sampler::k8s`](crate::Sampler::k8s).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pods: Option<Vec<PodUsage>>,
+    /// Utilization of each core, in percent, if requested with
+    /// [`Sampler::per_core`](crate::Sampler::per_core).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cores: Option<Vec<f64>>,
+    /// Problems that made parts of the report unavailable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Seconds spent taking the sample (not counting the first sample's
+    /// sampling window), i.e. loadrs's own cost.
+    #[serde(default)]
+    pub sampling_seconds: f64,
+}
+
+impl LoadReport {
+    /// The load average the load state is based on.
+    pub fn load(&self) -> f64 {
+        self.load_average.get(self.load_window)
+    }
+
+    /// Sort users by the given column, largest first for numeric columns.
+    pub fn sort_users(&mut self, sort_by: SortBy) {
+        match sort_by {
+            SortBy::Cpu => self
+                .users
+                .sort_by(|a, b| b.total_cpu_usage.total_cmp(&a.total_cpu_usage)),
+            SortBy::Mem => self.users.sort_by_key(|u| std::cmp::Reverse(u.memory)),
+            SortBy::User => self.users.sort_by(|a, b| a.username.cmp(&b.username)),
+            SortBy::Share => self
+                .users
+                .sort_by(|a, b| b.system_cpu_share.total_cmp(&a.system_cpu_share)),
+            SortBy::Excess => self.users.sort_by(|a, b| {
+                let excess = |u: &UserUsage| u.system_cpu_share - u.fair_share;
+                excess(b).total_cmp(&excess(a))
+            }),
+            SortBy::Io => self.users.sort_by_key(|u| {
+                std::cmp::Reverse(u.disk.map_or(0, |d| d.read_bytes + d.written_bytes))
+            }),
+            SortBy::Net => self.users.sort_by_key(|u| {
+                std::cmp::Reverse(u.net.map_or(0, |n| n.sent_bytes + n.received_bytes))
+            }),
+        }
+    }
+}
+
+/// With no active users the fair share is infinite, which JSON can only
+/// represent as `null`.
+fn infinite() -> f64 {
+    f64::INFINITY
+}
+
+fn infinite_if_null<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::INFINITY))
+}
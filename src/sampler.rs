@@ -0,0 +1,2097 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+use sysinfo::{
+    Components, CpuRefreshKind, Groups, MemoryRefreshKind, ProcessRefreshKind, ProcessStatus,
+    ProcessesToUpdate, RefreshKind, System, ThreadKind, UpdateKind, Users,
+    MINIMUM_CPU_UPDATE_INTERVAL,
+};
+
+use crate::alloc::Allocations;
+use crate::cgroup;
+use crate::contacts::{Contact, Contacts};
+use crate::container;
+use crate::cpustat::{self, Counters, CpuWait};
+use crate::fds::{self, FileHandles};
+use crate::gpu::{self, GpuUsage};
+use crate::k8s::{self, Kubelet, Pod, PodUsage};
+use crate::login::{self, Login};
+use crate::net::{self, NetUsage, Socket};
+use crate::nice;
+use crate::platform;
+use crate::policy::{FairSharePolicy, Quotas};
+use crate::pressure::{self, Pressure};
+use crate::report::{
+    cpu_usage, DiskUsage, FairShare, Forecast, GroupBy, Host, LimitMismatch, LoadAverages,
+    LoadReport, LoadState, LoadWindow, LongRunning, MemoryOffender, NewHeavy, Offender,
+    ProcessUsage, Spike, SwapHog, SystemMemory, Unniced, UserUsage, Zombies,
+};
+use crate::simulate::Scenario;
+use crate::slurm::{self, JobUsage};
+use crate::swap;
+use crate::thermal::{self, Thermal};
+
+/// The row processes of unresolvable UIDs are combined into, with
+/// [`Sampler::merge_unknown`].
+pub const UNKNOWN_USER: &str = "unknown";
+
+/// The group of processes whose UIDs aren't in any range, or are unknown,
+/// with [`GroupBy::UidRange`].
+pub const OTHER_UIDS: &str = "other";
+
+/// Number of recent samples a user's usage is compared against to detect
+/// spikes.
+const SPIKE_BASELINE: usize = 30;
+
+/// Samples needed before a baseline is trusted.
+const SPIKE_MIN_SAMPLES: usize = 5;
+
+/// Number of recent samples the load's trend is fit to.
+const FORECAST_SAMPLES: usize = 30;
+
+/// Samples needed before the load's trend is trusted.
+const FORECAST_MIN_SAMPLES: usize = 5;
+
+/// How far ahead the load is forecast. Past this, a straight line says
+/// little about what the load will do.
+const FORECAST_HORIZON: Duration = Duration::from_secs(3600);
+
+/// How far, as a fraction of the fair share, a cgroup CPU limit can be from
+/// it before it's reported as a mismatch.
+const LIMIT_TOLERANCE: f64 = 0.1;
+
+/// How long one stage of taking a sample took, and what it allocated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stage {
+    pub time: Duration,
+    /// Only counted if the program uses [`Counting`](crate::alloc::Counting)
+    /// as its global allocator.
+    pub allocations: Allocations,
+}
+
+/// The stages of taking a sample, as timed by [`Sampler::stages`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stages {
+    /// Reading processes and everything else about the system (or the
+    /// simulated scenario), not counting the first sample's sampling window.
+    pub sampling: Stage,
+    /// Summing processes by user and computing fair shares and offenders
+    /// into the report.
+    pub aggregation: Stage,
+}
+
+/// A named range of UIDs, for [`GroupBy::UidRange`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UidRange {
+    pub name: String,
+    pub first: u32,
+    /// The last UID in the range, or `None` for every UID from `first` up.
+    pub last: Option<u32>,
+}
+
+impl UidRange {
+    /// System accounts (below 1000), people (1000 to 59999), and service
+    /// accounts (60000 and up, e.g. `nobody` and container users).
+    pub fn defaults() -> Vec<Self> {
+        let range = |name: &str, first, last| UidRange {
+            name: name.to_string(),
+            first,
+            last,
+        };
+        vec![
+            range("system", 0, Some(999)),
+            range("humans", 1000, Some(59999)),
+            range("service", 60000, None),
+        ]
+    }
+
+    pub fn contains(&self, uid: u32) -> bool {
+        uid >= self.first && self.last.is_none_or(|last| uid <= last)
+    }
+}
+
+impl FromStr for UidRange {
+    type Err = String;
+
+    /// Parse a name and range, e.g. `humans=1000-59999`, `service=60000-`
+    /// for every UID from 60000 up, or `root=0` for one UID.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "expected NAME=FIRST-LAST, e.g. humans=1000-59999 or service=60000-, got '{}'",
+                s
+            )
+        };
+        let (name, range) = s.split_once('=').ok_or_else(invalid)?;
+        let uid = |s: &str| s.trim().parse::<u32>().map_err(|_| invalid());
+        let (first, last) = match range.split_once('-') {
+            Some((first, "")) => (uid(first)?, None),
+            Some((first, last)) => (uid(first)?, Some(uid(last)?)),
+            None => (uid(range)?, Some(uid(range)?)),
+        };
+        if name.trim().is_empty() || last.is_some_and(|last| last < first) {
+            return Err(invalid());
+        }
+        Ok(UidRange {
+            name: name.trim().to_string(),
+            first,
+            last,
+        })
+    }
+}
+
+/// Running per-user sums over their processes.
+#[derive(Debug, Default)]
+struct Totals {
+    cpu: f64,
+    /// CPU usage before smoothing.
+    raw_cpu: f64,
+    memory: u64,
+    gpu: GpuUsage,
+    read_bytes: u64,
+    written_bytes: u64,
+    sent_bytes: u64,
+    received_bytes: u64,
+    swap: u64,
+    fds: u64,
+    /// Sums of nice values weighted by CPU usage, of the CPU usage of the
+    /// processes whose nice value is known, and of their nice values.
+    weighted_nice: f64,
+    niced_cpu: f64,
+    nice: f64,
+    /// Processes whose nice value is known.
+    niced: usize,
+    process_count: usize,
+    /// Processes owned by system accounts.
+    system_processes: usize,
+    thread_count: usize,
+    zombies: usize,
+    /// Parents of the zombies, which haven't reaped them.
+    zombie_parents: HashSet<u32>,
+    processes: Vec<ProcessUsage>,
+}
+
+/// What a report needs from a process.
+#[derive(Debug, Clone)]
+pub(crate) struct ProcessInfo {
+    pub(crate) usage: ProcessUsage,
+    pub(crate) threads: usize,
+    pub(crate) zombie: bool,
+    pub(crate) read_bytes: u64,
+    pub(crate) written_bytes: u64,
+    pub(crate) gpu: GpuUsage,
+    pub(crate) sent_bytes: u64,
+    pub(crate) received_bytes: u64,
+    pub(crate) swap: u64,
+    /// Open file descriptors.
+    pub(crate) fds: u64,
+    pub(crate) job_id: Option<u64>,
+    pub(crate) pod: Option<Pod>,
+    /// Whether the owner is a system account rather than a person.
+    pub(crate) system_account: bool,
+    /// The owner's UID, if numeric (not a Windows SID).
+    pub(crate) uid: Option<u32>,
+}
+
+/// Each process's and core's CPU usage summed over several measurements, to
+/// average them.
+#[derive(Default)]
+struct CpuTotals {
+    processes: HashMap<u32, f64>,
+    cores: Vec<f64>,
+    measurements: usize,
+}
+
+impl CpuTotals {
+    fn add(&mut self, sys: &System) {
+        for p in sys.processes().values() {
+            *self.processes.entry(p.pid().as_u32()).or_default() += cpu_usage(p.cpu_usage());
+        }
+        self.cores.resize(sys.cpus().len(), 0.0);
+        for (total, cpu) in self.cores.iter_mut().zip(sys.cpus()) {
+            *total += cpu_usage(cpu.cpu_usage());
+        }
+        self.measurements += 1;
+    }
+
+    /// A process's mean CPU usage, counting measurements from before it
+    /// started as idle.
+    fn process(&self, pid: u32) -> f64 {
+        self.processes.get(&pid).copied().unwrap_or(0.0) / self.measurements as f64
+    }
+
+    fn cores(&self) -> Vec<f64> {
+        self.cores
+            .iter()
+            .map(|total| total / self.measurements as f64)
+            .collect()
+    }
+}
+
+/// Everything a report is built from, read from the system or taken from a
+/// simulated scenario.
+pub(crate) struct Snapshot {
+    /// Each process not excluded, by owner.
+    pub(crate) processes: Vec<(String, ProcessInfo)>,
+    /// Each user's groups, when needed for exclusion or weights.
+    pub(crate) user_groups: HashMap<String, Vec<String>>,
+    /// Each user's primary group, when grouping by group.
+    pub(crate) primary_groups: HashMap<String, String>,
+    pub(crate) sessions: Option<HashMap<String, Vec<Login>>>,
+    /// The machine, with `host`.
+    pub(crate) host: Option<Host>,
+    /// Each limited user's cgroup CPU limit, in cores.
+    pub(crate) cpu_limits: HashMap<String, f64>,
+    /// Each core's utilization.
+    pub(crate) cores: Vec<f64>,
+    pub(crate) memory: SystemMemory,
+    pub(crate) load_average: LoadAverages,
+    pub(crate) cpu_wait: Option<CpuWait>,
+    pub(crate) pressure: Option<Pressure>,
+    pub(crate) thermal: Option<Thermal>,
+    /// The kernel's file table, with `fds`.
+    pub(crate) file_handles: Option<FileHandles>,
+    pub(crate) warnings: Vec<String>,
+}
+
+/// Samples per-user CPU and memory usage and computes each user's fair share.
+///
+/// A `Sampler` keeps its view of the system between calls to
+/// [`Sampler::sample`], so sampling repeatedly (e.g. in a monitoring loop)
+/// only refreshes what changed, and CPU usage is measured over the time since
+/// the previous sample.
+///
+/// ```no_run
+/// let mut sampler = loadrs::Sampler::new().threshold(80.0);
+/// let report = sampler.sample();
+/// for user in &report.users {
+///     println!("{}: {:.2}%", user.username, user.system_cpu_share);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Sampler {
+    threshold: f64,
+    warn_threshold: Option<f64>,
+    hysteresis: f64,
+    load_window: LoadWindow,
+    min_dwell: Duration,
+    active_threshold: f64,
+    policy: FairSharePolicy,
+    quotas: Quotas,
+    contacts: Option<Contacts>,
+    exempt_users: HashSet<String>,
+    exclude_users: HashSet<String>,
+    exclude_groups: HashSet<String>,
+    only_users: HashSet<String>,
+    command: Option<Regex>,
+    ignore_commands: Vec<Regex>,
+    group_by: GroupBy,
+    group_map: HashMap<String, String>,
+    uid_ranges: Vec<UidRange>,
+    processes: usize,
+    gpu: bool,
+    io: bool,
+    net: bool,
+    swap: bool,
+    fds: bool,
+    nice: bool,
+    host: bool,
+    cgroup_limits: bool,
+    slurm: bool,
+    k8s: bool,
+    kubelet: Option<Kubelet>,
+    per_core: bool,
+    logins: bool,
+    cumulative: bool,
+    zombie_threshold: usize,
+    max_runtime: Option<Duration>,
+    /// Nice value heavy processes must run at, and the cores that make a
+    /// process heavy.
+    batch_nice: Option<(i32, f64)>,
+    /// Cores a newly started process has to use to be reported.
+    new_heavy: Option<f64>,
+    resolve_uids: bool,
+    merge_unknown: bool,
+    window: Duration,
+    /// Measurements to average on the first sample, and time between them.
+    samples: usize,
+    sample_interval: Duration,
+    grace_period: Duration,
+    ewma: Option<f64>,
+    /// Standard deviations and cores above baseline a jump in usage needs
+    /// to count as a spike.
+    spikes: Option<(f64, f64)>,
+    forecast: bool,
+    /// The load over the last [`FORECAST_SAMPLES`] samples, as a percent of
+    /// total cores, and when it was sampled.
+    loads: VecDeque<(Instant, f64)>,
+    /// Each user's cores used over the last [`SPIKE_BASELINE`] samples.
+    baselines: HashMap<String, VecDeque<f64>>,
+    /// Samples taken so far, up to [`SPIKE_BASELINE`].
+    baseline_samples: usize,
+    /// Each user's CPU time since the first sample, in seconds.
+    cpu_time: HashMap<String, f64>,
+    /// Names looked up through NSS with `resolve_uids`, by UID, including
+    /// UIDs with no name, since lookups can go over the network.
+    resolved: HashMap<String, Option<String>>,
+    /// Contacts looked up with `contacts`, by username, including users with
+    /// none, since GECOS lookups can go over the network.
+    contacted: HashMap<String, Option<Contact>>,
+    /// Each user's smoothed CPU usage as of the previous sample.
+    smoothed: HashMap<String, f64>,
+    /// When each user above their fair share first went over it.
+    over_since: HashMap<String, Instant>,
+    /// Each user's cores as of the previous sample, once sampled.
+    previous_cores: Option<HashMap<String, f64>>,
+    /// PIDs in the previous sample, with `new_heavy`.
+    previous_pids: Option<HashSet<u32>>,
+    load_state: LoadState,
+    /// When the load entered its current state, once it has been sampled.
+    load_state_since: Option<Instant>,
+    /// `/proc/stat` counters as of the previous refresh.
+    counters: Option<Counters>,
+    /// Temperature sensors, listed on the first sample.
+    components: Option<Components>,
+    /// Thermal throttle count as of the previous refresh.
+    throttles: Option<u64>,
+    /// Pods seen on the node by UID, with `k8s`.
+    pods: HashMap<String, Pod>,
+    /// TCP sockets' counters as of the previous sample, with `net`.
+    sockets: HashMap<String, Socket>,
+    /// Created on the first sample.
+    sys: Option<System>,
+    /// Reported on instead of the system, if set.
+    scenario: Option<Scenario>,
+    refreshed_at: Option<Instant>,
+    users: Users,
+    stages: Stages,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self {
+            threshold: 100.0,
+            warn_threshold: None,
+            hysteresis: 5.0,
+            load_window: LoadWindow::One,
+            min_dwell: Duration::ZERO,
+            active_threshold: 1.0,
+            policy: FairSharePolicy::Equal,
+            quotas: Quotas::default(),
+            contacts: None,
+            exempt_users: HashSet::new(),
+            exclude_users: HashSet::new(),
+            exclude_groups: HashSet::new(),
+            only_users: HashSet::new(),
+            command: None,
+            ignore_commands: Vec::new(),
+            group_by: GroupBy::User,
+            group_map: HashMap::new(),
+            uid_ranges: UidRange::defaults(),
+            processes: 0,
+            gpu: false,
+            io: false,
+            net: false,
+            swap: false,
+            fds: false,
+            nice: false,
+            host: false,
+            cgroup_limits: false,
+            slurm: false,
+            k8s: false,
+            kubelet: None,
+            per_core: false,
+            logins: false,
+            cumulative: false,
+            zombie_threshold: 20,
+            max_runtime: None,
+            batch_nice: None,
+            new_heavy: None,
+            resolve_uids: false,
+            merge_unknown: false,
+            window: MINIMUM_CPU_UPDATE_INTERVAL,
+            samples: 1,
+            sample_interval: Duration::from_secs(1),
+            grace_period: Duration::ZERO,
+            ewma: None,
+            spikes: None,
+            forecast: false,
+            loads: VecDeque::new(),
+            baselines: HashMap::new(),
+            baseline_samples: 0,
+            cpu_time: HashMap::new(),
+            resolved: HashMap::new(),
+            contacted: HashMap::new(),
+            smoothed: HashMap::new(),
+            over_since: HashMap::new(),
+            previous_cores: None,
+            previous_pids: None,
+            load_state: LoadState::Ok,
+            load_state_since: None,
+            counters: None,
+            components: None,
+            throttles: None,
+            pods: HashMap::new(),
+            sockets: HashMap::new(),
+            sys: None,
+            scenario: None,
+            refreshed_at: None,
+            users: Users::new(),
+            stages: Stages::default(),
+        }
+    }
+}
+
+impl Sampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Excessive load threshold, as a percent of total cores (default 100%).
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Load warning threshold, as a percent of total cores (default 80% of
+    /// the [threshold](Sampler::threshold)).
+    pub fn warn_threshold(mut self, warn_threshold: f64) -> Self {
+        self.warn_threshold = Some(warn_threshold);
+        self
+    }
+
+    /// How many percent of total cores the load has to drop below a
+    /// threshold to leave the state it entered by crossing it (default 5),
+    /// so a load hovering around a threshold doesn't flap between states.
+    pub fn hysteresis(mut self, hysteresis: f64) -> Self {
+        self.hysteresis = hysteresis.max(0.0);
+        self
+    }
+
+    /// Which load average the load state is based on (default the 1 minute
+    /// one). The 15 minute average keeps brief spikes from counting as
+    /// excessive load.
+    pub fn load_window(mut self, load_window: LoadWindow) -> Self {
+        self.load_window = load_window;
+        self
+    }
+
+    /// Stay in a load state for at least this long before changing to
+    /// another (default 0). Like [`Sampler::grace_period`], this only has an
+    /// effect when sampling repeatedly.
+    pub fn min_dwell(mut self, min_dwell: Duration) -> Self {
+        self.min_dwell = min_dwell;
+        self
+    }
+
+    /// Percent of system CPU a user needs to count as active (default 1%).
+    pub fn active_threshold(mut self, active_threshold: f64) -> Self {
+        self.active_threshold = active_threshold;
+        self
+    }
+
+    /// How to compute each user's fair share (default
+    /// [`FairSharePolicy::Equal`]).
+    pub fn policy(mut self, policy: FairSharePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Use a fixed fair share percent instead of 100% / active users.
+    pub fn fair_share(self, fair_share: Option<f64>) -> Self {
+        self.policy(fair_share.map_or(FairSharePolicy::Equal, FairSharePolicy::Fixed))
+    }
+
+    /// Give these users their quota instead of a fair share. While they're
+    /// active, their quotas are set aside and everyone else splits the rest.
+    pub fn quotas(mut self, quotas: Quotas) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// Report each user's contact (name, email, and Slack handle), from a
+    /// contacts file or their GECOS field.
+    pub fn contacts(mut self, contacts: Contacts) -> Self {
+        self.contacts = Some(contacts);
+        self
+    }
+
+    /// Users who are never listed as offenders, even above their fair share.
+    pub fn exempt_users(mut self, users: impl IntoIterator<Item = String>) -> Self {
+        self.exempt_users = users.into_iter().collect();
+        self
+    }
+
+    /// Users whose processes are ignored entirely: they are neither reported
+    /// nor counted as active users in the fair share calculation.
+    pub fn exclude_users(mut self, users: impl IntoIterator<Item = String>) -> Self {
+        self.exclude_users = users.into_iter().collect();
+        self
+    }
+
+    /// Like [`Sampler::exclude_users`], for all members of these groups.
+    pub fn exclude_groups(mut self, groups: impl IntoIterator<Item = String>) -> Self {
+        self.exclude_groups = groups.into_iter().collect();
+        self
+    }
+
+    /// Only report these users (default all). Everyone else still counts
+    /// towards the fair share calculation.
+    pub fn only_users(mut self, users: impl IntoIterator<Item = String>) -> Self {
+        self.only_users = users.into_iter().collect();
+        self
+    }
+
+    /// Only account for processes whose command line matches `command`, so
+    /// usage, active users, and fair shares are all computed from just those
+    /// processes.
+    pub fn command(mut self, command: Regex) -> Self {
+        self.command = Some(command);
+        self
+    }
+
+    /// Leave out processes whose command line matches any of `patterns`,
+    /// e.g. backup agents and monitoring daemons, so their owners' usage and
+    /// the fair shares are computed without them.
+    pub fn ignore_commands(mut self, patterns: impl IntoIterator<Item = Regex>) -> Self {
+        self.ignore_commands = patterns.into_iter().collect();
+        self
+    }
+
+    /// Group usage by something other than users (default
+    /// [`GroupBy::User`]). Fair shares are computed per group, and
+    /// exclusions still apply to the processes' users.
+    pub fn group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    /// With [`GroupBy::Group`], put these users (username, group) in the
+    /// given group instead of their primary group, e.g. to group by lab.
+    pub fn group_map(mut self, map: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.group_map = map.into_iter().collect();
+        self
+    }
+
+    /// With [`GroupBy::UidRange`], the UID ranges to group processes into
+    /// (default [`UidRange::defaults`]), checked in order. Processes in no
+    /// range are grouped as [`OTHER_UIDS`].
+    pub fn uid_ranges(mut self, ranges: impl IntoIterator<Item = UidRange>) -> Self {
+        self.uid_ranges = ranges.into_iter().collect();
+        self
+    }
+
+    /// Keep each user's top `n` processes by CPU usage (default 0).
+    pub fn processes(mut self, n: usize) -> Self {
+        self.processes = n;
+        self
+    }
+
+    /// Also report each user's NVIDIA GPU usage (requires `nvidia-smi`).
+    pub fn gpu(mut self, gpu: bool) -> Self {
+        self.gpu = gpu;
+        self
+    }
+
+    /// Also report each user's disk I/O since the previous sample.
+    pub fn io(mut self, io: bool) -> Self {
+        self.io = io;
+        self
+    }
+
+    /// Also report each user's TCP throughput since the previous sample
+    /// (Linux only, requires `ss`). Other users' sockets are only visible
+    /// to root.
+    pub fn net(mut self, net: bool) -> Self {
+        self.net = net;
+        self
+    }
+
+    /// Also report how much of each user's memory is swapped out (Linux
+    /// only), and the user holding most of it, if any.
+    pub fn swap(mut self, swap: bool) -> Self {
+        self.swap = swap;
+        self
+    }
+
+    /// Also report how many file descriptors each user's processes have
+    /// open, and the system's file handle usage and limit (Linux only;
+    /// other users' descriptors are only visible to root).
+    pub fn fds(mut self, fds: bool) -> Self {
+        self.fds = fds;
+        self
+    }
+
+    /// Also report the mean nice value of each user's processes, weighted
+    /// by their CPU usage (Unix only).
+    pub fn nice(mut self, nice: bool) -> Self {
+        self.nice = nice;
+        self
+    }
+
+    /// Also report the machine's hostname, uptime, OS, and kernel.
+    pub fn host(mut self, host: bool) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// Also report each user's CPU limit, from the cgroup of their systemd
+    /// user slice (Linux only), and users whose limit is more than 10% above
+    /// or below their fair share.
+    pub fn cgroup_limits(mut self, cgroup_limits: bool) -> Self {
+        self.cgroup_limits = cgroup_limits;
+        self
+    }
+
+    /// Also report usage per Slurm job, and per user outside any job
+    /// (Linux only).
+    pub fn slurm(mut self, slurm: bool) -> Self {
+        self.slurm = slurm;
+        self
+    }
+
+    /// Also report usage per Kubernetes pod, on a Kubernetes node (Linux
+    /// only). Pods are named from the kubelet's pod log directories, or
+    /// from the [kubelet API](Sampler::kubelet) if those can't be read.
+    pub fn k8s(mut self, k8s: bool) -> Self {
+        self.k8s = k8s;
+        self
+    }
+
+    /// The kubelet to ask for pod names with [`Sampler::k8s`].
+    pub fn kubelet(mut self, kubelet: Kubelet) -> Self {
+        self.kubelet = Some(kubelet);
+        self
+    }
+
+    /// Also report the utilization of each CPU core.
+    pub fn per_core(mut self, per_core: bool) -> Self {
+        self.per_core = per_core;
+        self
+    }
+
+    /// Also report each user's login sessions (requires `who`), to tell
+    /// users at the keyboard apart from those running detached jobs. Only
+    /// applies when grouping by user.
+    pub fn logins(mut self, logins: bool) -> Self {
+        self.logins = logins;
+        self
+    }
+
+    /// Also report each user's CPU time summed over every sample so far,
+    /// since fairness over hours matters more than a momentary snapshot.
+    pub fn cumulative(mut self, cumulative: bool) -> Self {
+        self.cumulative = cumulative;
+        self
+    }
+
+    /// Report users with more than this many zombie processes (default
+    /// 20), since zombie storms from broken pipelines can exhaust PIDs.
+    pub fn zombie_threshold(mut self, threshold: usize) -> Self {
+        self.zombie_threshold = threshold;
+        self
+    }
+
+    /// Report processes that have been running longer than this, by user,
+    /// for login nodes where long jobs are forbidden. Exempt users'
+    /// processes are never reported.
+    pub fn max_runtime(mut self, max_runtime: Option<Duration>) -> Self {
+        self.max_runtime = max_runtime;
+        self
+    }
+
+    /// Report processes using at least `cores` cores at less than nice value
+    /// `nice`, by user, where site policy says batch work must be niced.
+    /// Exempt users' processes are never reported.
+    pub fn batch_nice(mut self, nice: Option<i32>, cores: f64) -> Self {
+        self.batch_nice = nice.map(|nice| (nice, cores));
+        self
+    }
+
+    /// Report processes that started since the previous sample and already
+    /// use at least this many cores, by user, to catch jobs at launch.
+    /// Exempt users' processes are never reported.
+    pub fn new_heavy(mut self, cores: Option<f64>) -> Self {
+        self.new_heavy = cores;
+        self
+    }
+
+    /// Look up UIDs missing from the local user list through NSS, like
+    /// `getent passwd`, so users from LDAP or SSSD are named rather than
+    /// shown as `UID:1001`. Each UID is only looked up once.
+    pub fn resolve_uids(mut self, resolve_uids: bool) -> Self {
+        self.resolve_uids = resolve_uids;
+        self
+    }
+
+    /// Combine the processes of every UID that can't be resolved into a
+    /// single [`UNKNOWN_USER`] row, which counts how many UIDs it covers.
+    pub fn merge_unknown(mut self, merge_unknown: bool) -> Self {
+        self.merge_unknown = merge_unknown;
+        self
+    }
+
+    /// On the first sample, time to wait between the two process refreshes
+    /// that CPU usage is computed from (default [`MINIMUM_CPU_UPDATE_INTERVAL`]).
+    /// Longer windows smooth out bursty processes but make sampling slower.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window.max(MINIMUM_CPU_UPDATE_INTERVAL);
+        self
+    }
+
+    /// On the first sample, average CPU usage over `samples` measurements
+    /// (each over the sampling window) started `interval` apart, rather than
+    /// one, since a single instantaneous reading often misrepresents bursty
+    /// workloads. Later samples already measure usage since the previous one.
+    pub fn samples(mut self, samples: usize, interval: Duration) -> Self {
+        self.samples = samples.max(1);
+        self.sample_interval = interval;
+        self
+    }
+
+    /// Only list users as offenders once they have been continuously above
+    /// their fair share for this long (default 0), so short spikes like a
+    /// compile don't count. Time over the fair share is tracked across
+    /// samples, so this only has an effect when sampling repeatedly.
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Smooth each user's CPU usage across samples with an exponentially
+    /// weighted moving average, where `alpha` (between 0 and 1) is the weight
+    /// of the newest sample. Fair shares and offenders are computed from the
+    /// smoothed usage, so users don't flap in and out of them.
+    pub fn ewma(mut self, alpha: f64) -> Self {
+        self.ewma = Some(alpha.clamp(f64::MIN_POSITIVE, 1.0));
+        self
+    }
+
+    /// Flag users whose usage jumps both `sigmas` standard deviations and
+    /// `cores` cores above their mean over the last 30 samples, so runaway
+    /// jobs are caught as they start rather than once the load average
+    /// catches up. Set either to 0 to only use the other. Baselines are
+    /// tracked across samples, so this only has an effect when sampling
+    /// repeatedly.
+    pub fn spikes(mut self, sigmas: f64, cores: f64) -> Self {
+        self.spikes = Some((sigmas, cores));
+        self
+    }
+
+    /// Forecast when the load will cross the excessive load
+    /// [threshold](Sampler::threshold) if it keeps growing at its rate over
+    /// the last 30 samples, so admins can act before the machine stops
+    /// responding. The load is tracked across samples, so this only has an
+    /// effect when sampling repeatedly.
+    pub fn forecast(mut self, forecast: bool) -> Self {
+        self.forecast = forecast;
+        self
+    }
+
+    /// Build reports from a simulated scenario instead of this machine's
+    /// processes, e.g. for demos or to try out fair-share settings.
+    pub fn simulate(mut self, scenario: Scenario) -> Self {
+        self.scenario = Some(scenario);
+        self
+    }
+
+    /// Take a snapshot of the system. Per-process CPU usage is only
+    /// meaningful as the difference between two refreshes, so the first
+    /// sample blocks for the sampling window; later samples measure usage
+    /// since the previous one.
+    pub fn sample(&mut self) -> LoadReport {
+        let start = Instant::now();
+        let allocations = Allocations::now();
+        if let Some(scenario) = &self.scenario {
+            let now = Instant::now();
+            let elapsed = self
+                .refreshed_at
+                .map_or(self.window, |previous| now.duration_since(previous));
+            let user_groups = scenario.user_groups();
+            let excluded = self.excluded(&user_groups);
+            let snapshot = scenario.snapshot(user_groups, &excluded);
+            self.refreshed_at = Some(now);
+            let report = self.build(snapshot, now, elapsed);
+            self.record_sampling(start, allocations, Duration::ZERO);
+            return report;
+        }
+        let mut waited = Duration::ZERO;
+        let mut averages = None;
+        let (sys, previous) = match (self.sys.take(), self.refreshed_at) {
+            (Some(mut sys), Some(previous)) => {
+                sys.refresh_cpu_usage();
+                sys.refresh_memory();
+                sys.refresh_processes_specifics(ProcessesToUpdate::All, self.refresh_kind());
+                (sys, previous)
+            }
+            _ => {
+                // Unlike System::new_all, this skips everything about
+                // processes that isn't reported, like their environment.
+                let mut sys = System::new_with_specifics(
+                    RefreshKind::new()
+                        .with_cpu(CpuRefreshKind::new().with_cpu_usage())
+                        .with_memory(MemoryRefreshKind::everything())
+                        .with_processes(self.refresh_kind()),
+                );
+                let previous = Instant::now();
+                self.counters = cpustat::counters();
+                self.throttles = thermal::throttle_count();
+                if self.net {
+                    self.sockets = net::sockets().unwrap_or_default();
+                }
+                thread::sleep(self.window);
+                waited = self.window;
+                sys.refresh_cpu_usage();
+                sys.refresh_processes_specifics(ProcessesToUpdate::All, self.refresh_kind());
+                if self.samples > 1 {
+                    let mut totals = CpuTotals::default();
+                    totals.add(&sys);
+                    let pause = self.sample_interval.saturating_sub(self.window);
+                    for _ in 1..self.samples {
+                        thread::sleep(pause);
+                        sys.refresh_cpu_usage();
+                        sys.refresh_processes_specifics(
+                            ProcessesToUpdate::All,
+                            self.refresh_kind(),
+                        );
+                        thread::sleep(self.window);
+                        waited += pause + self.window;
+                        sys.refresh_cpu_usage();
+                        sys.refresh_processes_specifics(
+                            ProcessesToUpdate::All,
+                            self.refresh_kind(),
+                        );
+                        totals.add(&sys);
+                    }
+                    averages = Some(totals);
+                }
+                (sys, previous)
+            }
+        };
+        let now = Instant::now();
+        self.refreshed_at = Some(now);
+        self.users.refresh_list();
+        let mut report = self.report(&sys, averages.as_ref(), now, now.duration_since(previous));
+        self.sys = Some(sys);
+        report.sampling_seconds = start.elapsed().saturating_sub(waited).as_secs_f64();
+        self.record_sampling(start, allocations, waited);
+        report
+    }
+
+    /// How long each stage of the last sample took and what it allocated,
+    /// e.g. to find what's slow on nodes with huge process tables.
+    pub fn stages(&self) -> Stages {
+        self.stages
+    }
+
+    /// Record the sampling stage of a sample started at `start`: everything
+    /// but building the report and waiting out the sampling window.
+    fn record_sampling(&mut self, start: Instant, allocations: Allocations, waited: Duration) {
+        let aggregation = self.stages.aggregation;
+        self.stages.sampling = Stage {
+            time: start
+                .elapsed()
+                .saturating_sub(waited)
+                .saturating_sub(aggregation.time),
+            allocations: Allocations::now() - allocations - aggregation.allocations,
+        };
+    }
+
+    /// What to refresh about each process: CPU, memory, and the user of
+    /// processes started since the previous refresh, plus disk usage and
+    /// command lines only when they are reported. Refreshing less matters on
+    /// large nodes with tens of thousands of processes.
+    fn refresh_kind(&self) -> ProcessRefreshKind {
+        let mut kind = ProcessRefreshKind::new()
+            .with_memory()
+            .with_cpu()
+            .with_user(UpdateKind::OnlyIfNotSet);
+        if self.io {
+            kind = kind.with_disk_usage();
+        }
+        if self.command.is_some()
+            || !self.ignore_commands.is_empty()
+            || self.processes > 0
+            || self.slurm
+            || self.max_runtime.is_some()
+            || self.new_heavy.is_some()
+            || self.batch_nice.is_some()
+        {
+            kind = kind.with_cmd(UpdateKind::OnlyIfNotSet);
+        }
+        kind
+    }
+
+    /// Build a report from a system refreshed at `now`, `elapsed` after the
+    /// previous refresh, with CPU usage from `averages` if several
+    /// measurements were taken.
+    fn report(
+        &mut self,
+        sys: &System,
+        averages: Option<&CpuTotals>,
+        now: Instant,
+        elapsed: Duration,
+    ) -> LoadReport {
+        // Create a mapping of user IDs to usernames
+        let mut uid_to_name: HashMap<_, _> = self
+            .users
+            .iter()
+            .map(|user| (user.id().to_string(), user.name().to_string()))
+            .collect();
+        if self.resolve_uids {
+            for uid in sys.processes().values().filter_map(|p| p.user_id()) {
+                let id = uid.to_string();
+                if uid_to_name.contains_key(&id) {
+                    continue;
+                }
+                let name = self
+                    .resolved
+                    .entry(id.clone())
+                    .or_insert_with(|| platform::lookup(uid));
+                if let Some(name) = name {
+                    uid_to_name.insert(id, name.clone());
+                }
+            }
+        }
+
+        // Group lookups are slow, so only do them when needed.
+        let user_groups: HashMap<String, Vec<String>> =
+            if !self.exclude_groups.is_empty() || self.policy.needs_groups() {
+                self.users
+                    .iter()
+                    .map(|user| {
+                        let groups = user.groups().iter().map(|g| g.name().to_string()).collect();
+                        (user.name().to_string(), groups)
+                    })
+                    .collect()
+            } else {
+                HashMap::new()
+            };
+        let excluded = self.excluded(&user_groups);
+
+        let mut warnings = Vec::new();
+        let gpu_usage = if self.gpu {
+            gpu::processes().unwrap_or_else(|e| {
+                warnings.push(format!("Could not read GPU usage: {}", e));
+                HashMap::new()
+            })
+        } else {
+            HashMap::new()
+        };
+
+        let net_usage = if self.net {
+            match net::sockets() {
+                Ok(sockets) => {
+                    let usage = net::throughput(&self.sockets, &sockets);
+                    self.sockets = sockets;
+                    usage
+                }
+                Err(e) => {
+                    warnings.push(format!("Could not read network usage: {}", e));
+                    HashMap::new()
+                }
+            }
+        } else {
+            HashMap::new()
+        };
+
+        let sessions = if self.logins && self.group_by == GroupBy::User {
+            Some(login::sessions().unwrap_or_else(|e| {
+                warnings.push(format!("Could not read login sessions: {}", e));
+                HashMap::new()
+            }))
+        } else {
+            None
+        };
+
+        let pod_uids: HashMap<u32, String> = if self.k8s {
+            sys.processes()
+                .keys()
+                .filter_map(|pid| Some((pid.as_u32(), k8s::pod_uid(pid.as_u32())?)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        self.resolve_pods(pod_uids.values(), &mut warnings);
+
+        let processes = sys
+            .processes()
+            .values()
+            .map(|p| {
+                let username = p
+                    .user_id()
+                    .and_then(|uid| {
+                        uid_to_name
+                            .get(&uid.to_string())
+                            .cloned()
+                            .or_else(|| platform::system_account(uid))
+                    })
+                    .unwrap_or_else(|| platform::unresolved(p.user_id()));
+                (username, p)
+            })
+            .filter(|(username, _)| !excluded.contains(username))
+            // Threads are listed alongside processes, but their usage is
+            // already included in their process's.
+            .filter(|(_, p)| p.thread_kind() != Some(ThreadKind::Userland))
+            .map(|(username, p)| {
+                let pid = p.pid().as_u32();
+                let disk = p.disk_usage();
+                let (sent, received) = net_usage.get(&pid).copied().unwrap_or_default();
+                let mut usage = ProcessUsage::from(p);
+                if let Some(averages) = averages {
+                    usage.cpu_usage = averages.process(pid);
+                }
+                if self.group_by == GroupBy::Container {
+                    usage.container = container::container(pid);
+                }
+                if self.nice || self.batch_nice.is_some() {
+                    usage.nice = nice::priority(pid).ok();
+                }
+                let info = ProcessInfo {
+                    usage,
+                    // The main thread isn't listed among a process's tasks.
+                    threads: 1 + p.tasks().map_or(0, |tasks| tasks.len()),
+                    zombie: p.status() == ProcessStatus::Zombie,
+                    read_bytes: disk.read_bytes,
+                    written_bytes: disk.written_bytes,
+                    gpu: gpu_usage.get(&pid).copied().unwrap_or_default(),
+                    sent_bytes: sent,
+                    received_bytes: received,
+                    swap: if self.swap {
+                        swap::swapped(pid).unwrap_or(0)
+                    } else {
+                        0
+                    },
+                    fds: if self.fds {
+                        fds::open(pid).unwrap_or(0)
+                    } else {
+                        0
+                    },
+                    job_id: if self.slurm { slurm::job_id(pid) } else { None },
+                    pod: pod_uids
+                        .get(&pid)
+                        .and_then(|uid| self.pods.get(uid))
+                        .cloned(),
+                    system_account: p.user_id().is_some_and(platform::is_system),
+                    uid: p.user_id().and_then(|uid| uid.to_string().parse().ok()),
+                };
+                (username, info)
+            })
+            .collect();
+
+        let counters = cpustat::counters();
+        let cpu_wait = self
+            .counters
+            .zip(counters)
+            .and_then(|(previous, current)| cpustat::wait(previous, current));
+        self.counters = counters;
+
+        let cores: Vec<f64> = match averages {
+            Some(averages) => averages.cores(),
+            None => sys
+                .cpus()
+                .iter()
+                .map(|cpu| cpu_usage(cpu.cpu_usage()))
+                .collect(),
+        };
+        match &mut self.components {
+            Some(components) => components.refresh(),
+            None => self.components = Some(Components::new_with_refreshed_list()),
+        }
+        let throttles = thermal::throttle_count();
+        let thermal = self.components.as_ref().and_then(|components| {
+            thermal::thermal(components, &cores, (self.throttles, throttles))
+        });
+        self.throttles = throttles;
+
+        let loadavg = System::load_average();
+        let snapshot = Snapshot {
+            processes,
+            primary_groups: if self.group_by == GroupBy::Group {
+                self.primary_groups()
+            } else {
+                HashMap::new()
+            },
+            user_groups,
+            sessions,
+            host: self.host.then(|| Host {
+                hostname: System::host_name().unwrap_or_else(|| "unknown".to_string()),
+                uptime: System::uptime(),
+                os: System::long_os_version(),
+                kernel: System::kernel_version(),
+            }),
+            cpu_limits: if self.cgroup_limits && self.group_by == GroupBy::User {
+                cgroup::user_cpu_limits()
+                    .into_iter()
+                    .filter_map(|(uid, limit)| {
+                        Some((uid_to_name.get(&uid.to_string())?.clone(), limit))
+                    })
+                    .collect()
+            } else {
+                HashMap::new()
+            },
+            cores,
+            memory: SystemMemory {
+                total: sys.total_memory(),
+                available: sys.available_memory(),
+                swap_total: sys.total_swap(),
+                swap_used: sys.used_swap(),
+                swapping: false,
+            },
+            load_average: LoadAverages {
+                one: loadavg.one,
+                five: loadavg.five,
+                fifteen: loadavg.fifteen,
+            },
+            cpu_wait,
+            pressure: pressure::pressure(),
+            thermal,
+            file_handles: self.fds.then(fds::file_handles).flatten(),
+            warnings,
+        };
+        let mut report = self.build(snapshot, now, elapsed);
+        for user in &mut report.users {
+            for process in &mut user.processes {
+                if process.container.is_none() {
+                    process.container = container::container(process.pid);
+                }
+            }
+        }
+        report
+    }
+
+    /// Build a report from a snapshot taken at `now`, `elapsed` after the
+    /// previous one.
+    fn build(&mut self, snapshot: Snapshot, now: Instant, elapsed: Duration) -> LoadReport {
+        let start = Instant::now();
+        let allocations = Allocations::now();
+        let Snapshot {
+            processes,
+            user_groups,
+            primary_groups,
+            mut sessions,
+            host,
+            cpu_limits,
+            cores,
+            memory,
+            load_average,
+            cpu_wait,
+            pressure,
+            thermal,
+            file_handles,
+            mut warnings,
+        } = snapshot;
+        let total_cores = cores.len();
+        let cpus = total_cores as f64;
+        let total_memory = memory.total.max(1) as f64;
+        let seconds = elapsed.as_secs_f64();
+
+        let processes: Vec<(String, ProcessInfo)> = processes
+            .into_iter()
+            .filter(|(_, p)| {
+                self.command
+                    .as_ref()
+                    .is_none_or(|command| command.is_match(&p.usage.command))
+                    && !self
+                        .ignore_commands
+                        .iter()
+                        .any(|ignore| ignore.is_match(&p.usage.command))
+            })
+            .collect();
+
+        let jobs = self.slurm.then(|| self.jobs(&processes, cpus));
+        let pods = self.k8s.then(|| self.pod_usage(&processes, cpus));
+        let long_running = match self.max_runtime {
+            Some(max_runtime) => self.long_running(&processes, max_runtime),
+            None => Vec::new(),
+        };
+        let unniced = match self.batch_nice {
+            Some((nice, cores)) => self.unniced(&processes, nice, cores),
+            None => Vec::new(),
+        };
+        let new_heavy = match self.new_heavy {
+            Some(cores) => {
+                let pids = processes.iter().map(|(_, p)| p.usage.pid).collect();
+                match self.previous_pids.replace(pids) {
+                    Some(previous) => self.new_heavy_processes(&processes, &previous, cores),
+                    None => Vec::new(),
+                }
+            }
+            None => Vec::new(),
+        };
+
+        let mut unknown_uids = HashSet::new();
+        let processes = processes.into_iter().map(|(username, p)| {
+            if self.merge_unknown && platform::is_unresolved(&username) {
+                unknown_uids.insert(username);
+                (UNKNOWN_USER.to_string(), p)
+            } else {
+                (username, p)
+            }
+        });
+
+        let processes = processes.map(|(username, p)| match self.group_by {
+            GroupBy::User => (username, p),
+            GroupBy::Container => (p.usage.container.clone().unwrap_or(username), p),
+            GroupBy::Group => {
+                let group = self
+                    .group_map
+                    .get(&username)
+                    .or_else(|| primary_groups.get(&username))
+                    .cloned();
+                (group.unwrap_or(username), p)
+            }
+            GroupBy::UidRange => {
+                let range = p
+                    .uid
+                    .and_then(|uid| self.uid_ranges.iter().find(|range| range.contains(uid)));
+                let name = range.map_or(OTHER_UIDS, |range| range.name.as_str());
+                (name.to_string(), p)
+            }
+        });
+
+        // UID ranges are a fixed summary rather than parties to the fair
+        // share: every range gets a row, in order, and none is active, has a
+        // fair share, or can be an offender.
+        let summary = self.group_by == GroupBy::UidRange;
+        let ranges = if summary { &self.uid_ranges[..] } else { &[] };
+        let empty: HashMap<String, Totals> = ranges
+            .iter()
+            .map(|range| (range.name.clone(), Totals::default()))
+            .collect();
+
+        let mut user_usage: Vec<(String, Totals)> = processes
+            .fold(empty, |mut acc, (username, p)| {
+                let totals: &mut Totals = acc.entry(username).or_default();
+                totals.cpu += p.usage.cpu_usage;
+                totals.memory += p.usage.memory;
+                totals.process_count += 1;
+                totals.system_processes += p.system_account as usize;
+                totals.thread_count += p.threads;
+                if p.zombie {
+                    totals.zombies += 1;
+                    totals.zombie_parents.extend(p.usage.parent);
+                }
+                totals.read_bytes += p.read_bytes;
+                totals.written_bytes += p.written_bytes;
+                totals.gpu += p.gpu;
+                totals.sent_bytes += p.sent_bytes;
+                totals.received_bytes += p.received_bytes;
+                totals.swap += p.swap;
+                totals.fds += p.fds;
+                if let Some(nice) = p.usage.nice {
+                    totals.weighted_nice += nice as f64 * p.usage.cpu_usage;
+                    totals.niced_cpu += p.usage.cpu_usage;
+                    totals.nice += nice as f64;
+                    totals.niced += 1;
+                }
+                if self.processes > 0 {
+                    totals.processes.push(p.usage);
+                }
+                acc
+            })
+            .into_iter()
+            .collect();
+
+        let mut zombies: Vec<Zombies> = user_usage
+            .iter()
+            .filter(|(_, totals)| totals.zombies > self.zombie_threshold)
+            .map(|(username, totals)| {
+                let mut parent_pids: Vec<u32> = totals.zombie_parents.iter().copied().collect();
+                parent_pids.sort_unstable();
+                Zombies {
+                    username: username.clone(),
+                    count: totals.zombies,
+                    parent_pids,
+                }
+            })
+            .collect();
+        zombies.sort_by_key(|z| std::cmp::Reverse(z.count));
+
+        for (username, totals) in &mut user_usage {
+            totals.raw_cpu = totals.cpu;
+            if self.cumulative {
+                *self.cpu_time.entry(username.clone()).or_default() +=
+                    totals.raw_cpu / 100.0 * seconds;
+            }
+            if let Some(alpha) = self.ewma {
+                let previous = self.smoothed.get(username).copied().unwrap_or(totals.cpu);
+                totals.cpu = alpha * totals.cpu + (1.0 - alpha) * previous;
+            }
+        }
+        if self.ewma.is_some() {
+            self.smoothed = user_usage
+                .iter()
+                .map(|(username, totals)| (username.clone(), totals.cpu))
+                .collect();
+        }
+
+        user_usage.sort_by(|a, b| b.1.cpu.total_cmp(&a.1.cpu));
+        if summary {
+            let position = |name: &str| {
+                ranges
+                    .iter()
+                    .position(|range| range.name == name)
+                    .unwrap_or(ranges.len())
+            };
+            user_usage.sort_by_key(|(name, _)| position(name));
+        }
+
+        let spikes = match self.spikes {
+            Some(thresholds) => self.detect_spikes(&user_usage, thresholds),
+            None => Vec::new(),
+        };
+
+        let contacts: HashMap<String, Contact> = match &self.contacts {
+            Some(contacts) if self.group_by == GroupBy::User => user_usage
+                .iter()
+                .filter_map(|(username, _)| {
+                    let contact = self
+                        .contacted
+                        .entry(username.clone())
+                        .or_insert_with(|| contacts.lookup(username));
+                    Some((username.clone(), contact.clone()?))
+                })
+                .collect(),
+            _ => HashMap::new(),
+        };
+
+        let weight = |username: &str| match self.group_by {
+            // Groups are weighted by --group-weights.
+            GroupBy::Group => self.policy.weight(username, &[username.to_string()]),
+            _ => {
+                let groups = user_groups.get(username).map_or(&[][..], |g| g.as_slice());
+                self.policy.weight(username, groups)
+            }
+        };
+        let active: Vec<&str> = user_usage
+            .iter()
+            .filter(|(_, totals)| !summary && totals.cpu / cpus > self.active_threshold)
+            .map(|(username, _)| username.as_str())
+            .collect();
+        let active_users = active.len();
+        let quota = |username: &str| self.quotas.share(username, cpus);
+        let reserved = active
+            .iter()
+            .filter_map(|username| quota(username))
+            .fold(0.0, |a, b| a + b);
+        // Fold from 0 rather than sum(), whose empty sum is -0, so that no
+        // active users gives an infinite fair share rather than -inf.
+        let active_weight = active
+            .iter()
+            .filter(|username| quota(username).is_none())
+            .map(|username| weight(username))
+            .fold(0.0, |a, b| a + b);
+        // Memory is split equally whatever the CPU policy, and users sitting
+        // on memory while idle count toward splitting it.
+        let memory_active_users = user_usage
+            .iter()
+            .filter(|(_, totals)| {
+                !summary
+                    && (totals.cpu / cpus > self.active_threshold
+                        || 100.0 * totals.memory as f64 / total_memory > self.active_threshold)
+            })
+            .count();
+        let memory_fair_share = 100.0 / memory_active_users as f64;
+        let fair_share = if summary {
+            f64::INFINITY
+        } else {
+            self.policy
+                .base_share((100.0 - reserved).max(0.0), active_weight)
+        };
+
+        let mut users: Vec<UserUsage> = user_usage
+            .into_iter()
+            .filter(|(_, totals)| {
+                summary || totals.cpu > 0.0 || totals.memory > 0 || totals.swap > 0
+            })
+            .filter(|(username, _)| {
+                self.only_users.is_empty() || self.only_users.contains(username)
+            })
+            .map(|(username, mut totals)| {
+                totals
+                    .processes
+                    .sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage));
+                totals.processes.truncate(self.processes);
+                let user_quota = if summary { None } else { quota(&username) };
+                let user_fair_share = match summary {
+                    true => f64::INFINITY,
+                    false => user_quota.unwrap_or_else(|| fair_share * weight(&username)),
+                };
+                let cpu_time = self
+                    .cumulative
+                    .then(|| self.cpu_time.get(&username).copied().unwrap_or(0.0));
+                let logins = sessions
+                    .as_mut()
+                    .map(|sessions| sessions.remove(&username).unwrap_or_default());
+                let cpu_limit = cpu_limits.get(&username).copied();
+                let contact = contacts.get(&username).cloned();
+                let unresolved_uids =
+                    (self.merge_unknown && username == UNKNOWN_USER).then_some(unknown_uids.len());
+                UserUsage {
+                    username,
+                    unresolved_uids,
+                    total_cpu_usage: totals.cpu,
+                    raw_cpu_usage: self.ewma.map(|_| totals.raw_cpu),
+                    cpu_seconds: totals.raw_cpu / 100.0 * seconds,
+                    cpu_time,
+                    equivalent_cores: totals.cpu / 100.0,
+                    system_cpu_share: totals.cpu / cpus,
+                    fair_share: user_fair_share,
+                    quota: user_quota,
+                    fairshare_score: None,
+                    usual_cores: None,
+                    cores_change: None,
+                    time_over_fair_share: None,
+                    memory: totals.memory,
+                    memory_share: 100.0 * totals.memory as f64 / total_memory,
+                    process_count: totals.process_count,
+                    thread_count: totals.thread_count,
+                    zombies: totals.zombies,
+                    processes: totals.processes,
+                    gpu: self.gpu.then_some(totals.gpu),
+                    logins,
+                    disk: self.io.then(|| DiskUsage {
+                        read_bytes: totals.read_bytes,
+                        written_bytes: totals.written_bytes,
+                        read_rate: totals.read_bytes as f64 / seconds,
+                        write_rate: totals.written_bytes as f64 / seconds,
+                    }),
+                    net: self.net.then(|| NetUsage {
+                        sent_bytes: totals.sent_bytes,
+                        received_bytes: totals.received_bytes,
+                        send_rate: totals.sent_bytes as f64 / seconds,
+                        receive_rate: totals.received_bytes as f64 / seconds,
+                    }),
+                    swap: self.swap.then_some(totals.swap),
+                    fds: self.fds.then_some(totals.fds),
+                    cpu_limit,
+                    contact,
+                    nice: (self.nice && totals.niced > 0).then(|| {
+                        if totals.niced_cpu > 0.0 {
+                            totals.weighted_nice / totals.niced_cpu
+                        } else {
+                            totals.nice / totals.niced as f64
+                        }
+                    }),
+                    system_account: self.group_by == GroupBy::User
+                        && totals.system_processes == totals.process_count,
+                }
+            })
+            .collect();
+
+        let user_cores = users
+            .iter()
+            .map(|u| (u.username.clone(), u.equivalent_cores))
+            .collect();
+        if let Some(previous) = self.previous_cores.replace(user_cores) {
+            for user in &mut users {
+                let before = previous.get(&user.username).copied().unwrap_or(0.0);
+                user.cores_change = Some(user.equivalent_cores - before);
+            }
+        }
+
+        self.over_since.retain(|username, _| {
+            users
+                .iter()
+                .any(|u| &u.username == username && u.system_cpu_share > u.fair_share)
+        });
+        for user in users
+            .iter_mut()
+            .filter(|u| u.system_cpu_share > u.fair_share)
+        {
+            let since = *self.over_since.entry(user.username.clone()).or_insert(now);
+            user.time_over_fair_share = Some(now.duration_since(since).as_secs());
+        }
+
+        let grace_period = self.grace_period.as_secs();
+        let offenders = users
+            .iter()
+            .filter(|u| u.time_over_fair_share.is_some_and(|t| t >= grace_period))
+            .filter(|u| !self.exempt_users.contains(&u.username))
+            .map(|u| Offender {
+                username: u.username.clone(),
+                system_cpu_share: u.system_cpu_share,
+                fair_share: u.fair_share,
+                excess_usage: u.system_cpu_share - u.fair_share,
+                time_over_fair_share: u.time_over_fair_share.unwrap_or(0),
+            })
+            .collect();
+
+        let limit_mismatches = users
+            .iter()
+            .filter(|u| u.fair_share.is_finite())
+            .filter_map(|u| {
+                let fair_share_cores = u.fair_share / 100.0 * cpus;
+                let cpu_limit = u.cpu_limit?;
+                ((cpu_limit - fair_share_cores).abs() > LIMIT_TOLERANCE * fair_share_cores).then(
+                    || LimitMismatch {
+                        username: u.username.clone(),
+                        cpu_limit,
+                        fair_share_cores,
+                    },
+                )
+            })
+            .collect();
+
+        let memory_offenders = users
+            .iter()
+            .filter(|u| u.memory_share > memory_fair_share)
+            .filter(|u| !self.exempt_users.contains(&u.username))
+            .map(|u| MemoryOffender {
+                username: u.username.clone(),
+                memory: u.memory,
+                memory_share: u.memory_share,
+                fair_share: memory_fair_share,
+                excess_usage: u.memory_share - memory_fair_share,
+            })
+            .collect();
+
+        let swap_hog = self.swap.then(|| swap_hog(&users, memory.total)).flatten();
+        let swap_hog = swap_hog.filter(|hog| !self.exempt_users.contains(&hog.username));
+
+        // Thrashing can't be measured directly, but swapping with under 10%
+        // of memory available is a good sign of it.
+        let swapping = memory.swap_used > 0 && memory.available < memory.total / 10;
+        let memory = SystemMemory { swapping, ..memory };
+
+        let mut missing: Vec<&String> = self
+            .only_users
+            .iter()
+            .filter(|username| !users.iter().any(|u| &u.username == *username))
+            .collect();
+        missing.sort();
+        for username in missing {
+            warnings.push(format!("No processes for user {}", username));
+        }
+
+        let load = 100.0 * load_average.get(self.load_window) / cpus;
+        let previous_load_state = self.update_load_state(load, now);
+        let forecast = if self.forecast {
+            self.forecast_load(load, now, cpus)
+        } else {
+            None
+        };
+
+        let report = LoadReport {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            host: host.filter(|_| self.host),
+            total_cores,
+            group_by: self.group_by,
+            fair_share: FairShare {
+                value: fair_share,
+                policy: self.policy.kind(),
+                user_specified: matches!(self.policy, FairSharePolicy::Fixed(_)),
+                active_threshold: self.active_threshold,
+                active_users,
+                active_weight,
+                reserved,
+                memory: memory_fair_share,
+                memory_active_users,
+            },
+            users,
+            load_average,
+            load_window: self.load_window,
+            threshold: self.threshold,
+            excessive_load: self.load_state == LoadState::Crit,
+            load_state: self.load_state,
+            previous_load_state,
+            forecast,
+            offenders,
+            memory,
+            cpu_wait,
+            pressure,
+            thermal,
+            file_handles,
+            memory_offenders,
+            spikes,
+            long_running,
+            unniced,
+            new_heavy,
+            limit_mismatches,
+            zombies,
+            swap_hog,
+            jobs,
+            pods,
+            cores: self.per_core.then_some(cores),
+            warnings,
+            sampling_seconds: 0.0,
+        };
+        self.stages.aggregation = Stage {
+            time: start.elapsed(),
+            allocations: Allocations::now() - allocations,
+        };
+        report
+    }
+
+    /// Move the load state machine along given the load as a percent of
+    /// total cores, returning the previous state if it changed.
+    fn update_load_state(&mut self, load: f64, now: Instant) -> Option<LoadState> {
+        let crit = self.threshold;
+        let warn = self.warn_threshold.unwrap_or(0.8 * crit).min(crit);
+        let state = self.load_state;
+        let target = if load > crit || (state == LoadState::Crit && load > crit - self.hysteresis) {
+            LoadState::Crit
+        } else if load > warn || (state >= LoadState::Warn && load > warn - self.hysteresis) {
+            LoadState::Warn
+        } else {
+            LoadState::Ok
+        };
+        let dwelled = self
+            .load_state_since
+            .is_none_or(|since| now.duration_since(since) >= self.min_dwell);
+        if self.load_state_since.is_none() {
+            self.load_state_since = Some(now);
+        }
+        if target == state || !dwelled {
+            return None;
+        }
+        self.load_state = target;
+        self.load_state_since = Some(now);
+        Some(state)
+    }
+
+    /// When the load, as a percent of total cores, will cross the threshold
+    /// going by a least squares line through the recent loads, updating them
+    /// with this sample.
+    fn forecast_load(&mut self, load: f64, now: Instant, cpus: f64) -> Option<Forecast> {
+        if self.loads.len() == FORECAST_SAMPLES {
+            self.loads.pop_front();
+        }
+        self.loads.push_back((now, load));
+        if self.loads.len() < FORECAST_MIN_SAMPLES || load >= self.threshold {
+            return None;
+        }
+        let start = self.loads[0].0;
+        let points: Vec<(f64, f64)> = self
+            .loads
+            .iter()
+            .map(|(at, load)| (at.duration_since(start).as_secs_f64(), *load))
+            .collect();
+        let n = points.len() as f64;
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let mean_load = points.iter().map(|(_, load)| load).sum::<f64>() / n;
+        let variance: f64 = points.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+        if variance == 0.0 {
+            return None;
+        }
+        // Percent of total cores per second.
+        let slope = points
+            .iter()
+            .map(|(t, load)| (t - mean_t) * (load - mean_load))
+            .sum::<f64>()
+            / variance;
+        if slope <= 0.0 {
+            return None;
+        }
+        let seconds = (self.threshold - load) / slope;
+        (seconds <= FORECAST_HORIZON.as_secs_f64()).then(|| Forecast {
+            cores_per_minute: slope * 60.0 * cpus / 100.0,
+            seconds: seconds.round() as u64,
+        })
+    }
+
+    /// Users whose raw usage jumped above their baseline, updating the
+    /// baselines with this sample.
+    fn detect_spikes(
+        &mut self,
+        user_usage: &[(String, Totals)],
+        (sigmas, cores): (f64, f64),
+    ) -> Vec<Spike> {
+        let mut spikes = Vec::new();
+        // Users with no history were idle, as far as the baseline goes.
+        for (username, _) in user_usage {
+            self.baselines
+                .entry(username.clone())
+                .or_insert_with(|| VecDeque::from(vec![0.0; self.baseline_samples]));
+        }
+        for (username, baseline) in &mut self.baselines {
+            let current = user_usage
+                .iter()
+                .find(|(u, _)| u == username)
+                .map_or(0.0, |(_, totals)| totals.raw_cpu / 100.0);
+            if baseline.len() >= SPIKE_MIN_SAMPLES
+                && (self.only_users.is_empty() || self.only_users.contains(username))
+            {
+                let n = baseline.len() as f64;
+                let mean = baseline.iter().sum::<f64>() / n;
+                let sd = (baseline.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / n).sqrt();
+                let increase = current - mean;
+                let z = if sd > 0.0 {
+                    increase / sd
+                } else {
+                    f64::INFINITY
+                };
+                if increase > 0.0 && increase >= cores && z >= sigmas {
+                    spikes.push(Spike {
+                        username: username.clone(),
+                        equivalent_cores: current,
+                        baseline_cores: mean,
+                        sigmas: z,
+                    });
+                }
+            }
+            if baseline.len() == SPIKE_BASELINE {
+                baseline.pop_front();
+            }
+            baseline.push_back(current);
+        }
+        self.baselines
+            .retain(|_, baseline| baseline.iter().any(|cores| *cores > 0.0));
+        self.baseline_samples = (self.baseline_samples + 1).min(SPIKE_BASELINE);
+        spikes.sort_by(|a, b| b.equivalent_cores.total_cmp(&a.equivalent_cores));
+        spikes
+    }
+
+    /// Users left out of the report, by name or by group.
+    fn excluded(&self, user_groups: &HashMap<String, Vec<String>>) -> HashSet<String> {
+        let mut excluded = self.exclude_users.clone();
+        excluded.extend(
+            user_groups
+                .iter()
+                .filter(|(_, groups)| groups.iter().any(|g| self.exclude_groups.contains(g)))
+                .map(|(username, _)| username.clone()),
+        );
+        excluded
+    }
+
+    /// The name of each user's primary group.
+    fn primary_groups(&self) -> HashMap<String, String> {
+        let groups: HashMap<_, _> = Groups::new_with_refreshed_list()
+            .iter()
+            .map(|group| (*group.id(), group.name().to_string()))
+            .collect();
+        self.users
+            .iter()
+            .filter_map(|user| {
+                let group = groups.get(&user.group_id())?;
+                Some((user.name().to_string(), group.clone()))
+            })
+            .collect()
+    }
+
+    /// Usage per Slurm job, and per user outside any job, by descending CPU
+    /// usage.
+    fn jobs(&self, processes: &[(String, ProcessInfo)], cpus: f64) -> Vec<JobUsage> {
+        let mut jobs: HashMap<(Option<u64>, &str), Vec<&ProcessUsage>> = HashMap::new();
+        for (username, p) in processes {
+            jobs.entry((p.job_id, username)).or_default().push(&p.usage);
+        }
+        let mut jobs: Vec<JobUsage> = jobs
+            .into_iter()
+            .map(|((job_id, username), processes)| {
+                let cpu: f64 = processes.iter().map(|p| p.cpu_usage).sum();
+                let top = processes
+                    .iter()
+                    .max_by(|a, b| a.cpu_usage.total_cmp(&b.cpu_usage))
+                    .map(|p| p.command.clone())
+                    .unwrap_or_default();
+                JobUsage {
+                    job_id,
+                    username: username.to_string(),
+                    total_cpu_usage: cpu,
+                    equivalent_cores: cpu / 100.0,
+                    system_cpu_share: cpu / cpus,
+                    memory: processes.iter().map(|p| p.memory).sum(),
+                    processes: processes.len(),
+                    top_command: top,
+                }
+            })
+            .filter(|job| self.only_users.is_empty() || self.only_users.contains(&job.username))
+            .collect();
+        jobs.sort_by(|a, b| b.total_cpu_usage.total_cmp(&a.total_cpu_usage));
+        jobs
+    }
+
+    /// Name the pods with the given UIDs, looking up any not seen before,
+    /// and forget pods that are gone. Pods that can't be named are called
+    /// after their UID, in the "unknown" namespace.
+    fn resolve_pods<'a>(
+        &mut self,
+        uids: impl Iterator<Item = &'a String>,
+        warnings: &mut Vec<String>,
+    ) {
+        let seen: HashSet<&String> = uids.collect();
+        if seen.iter().all(|uid| self.pods.contains_key(*uid)) {
+            return;
+        }
+        self.pods.extend(k8s::logged_pods());
+        if !seen.iter().all(|uid| self.pods.contains_key(*uid)) {
+            if let Some(kubelet) = &self.kubelet {
+                match k8s::kubelet_pods(kubelet) {
+                    Ok(pods) => self.pods.extend(pods),
+                    Err(e) => warnings.push(format!("Could not list pods from the kubelet: {}", e)),
+                }
+            }
+        }
+        for &uid in &seen {
+            // Not looked up again, so an unreachable kubelet isn't asked on
+            // every sample.
+            self.pods.entry(uid.clone()).or_insert_with(|| Pod {
+                namespace: UNKNOWN_USER.to_string(),
+                name: uid.clone(),
+            });
+        }
+        self.pods.retain(|uid, _| seen.contains(uid));
+    }
+
+    /// Usage per Kubernetes pod, by descending CPU usage.
+    fn pod_usage(&self, processes: &[(String, ProcessInfo)], cpus: f64) -> Vec<PodUsage> {
+        let mut pods: HashMap<&Pod, Vec<(&str, &ProcessUsage)>> = HashMap::new();
+        for (username, p) in processes {
+            if let Some(pod) = &p.pod {
+                pods.entry(pod).or_default().push((username, &p.usage));
+            }
+        }
+        let mut pods: Vec<PodUsage> = pods
+            .into_iter()
+            .map(|(pod, processes)| {
+                let cpu: f64 = processes.iter().map(|(_, p)| p.cpu_usage).sum();
+                let mut users: Vec<String> = processes.iter().map(|(u, _)| u.to_string()).collect();
+                users.sort();
+                users.dedup();
+                PodUsage {
+                    namespace: pod.namespace.clone(),
+                    pod: pod.name.clone(),
+                    total_cpu_usage: cpu,
+                    equivalent_cores: cpu / 100.0,
+                    system_cpu_share: cpu / cpus,
+                    memory: processes.iter().map(|(_, p)| p.memory).sum(),
+                    processes: processes.len(),
+                    users,
+                }
+            })
+            .filter(|pod| {
+                self.only_users.is_empty() || pod.users.iter().any(|u| self.only_users.contains(u))
+            })
+            .collect();
+        pods.sort_by(|a, b| b.total_cpu_usage.total_cmp(&a.total_cpu_usage));
+        pods
+    }
+
+    /// Each non-exempt user's processes that have run longer than
+    /// `max_runtime`, longest first, for users with any.
+    fn long_running(
+        &self,
+        processes: &[(String, ProcessInfo)],
+        max_runtime: Duration,
+    ) -> Vec<LongRunning> {
+        let mut by_user: HashMap<&str, Vec<ProcessUsage>> = HashMap::new();
+        for (username, p) in processes {
+            if p.usage.run_time > max_runtime.as_secs() && !self.exempt_users.contains(username) {
+                by_user.entry(username).or_default().push(p.usage.clone());
+            }
+        }
+        let mut long_running: Vec<LongRunning> = by_user
+            .into_iter()
+            .filter(|(username, _)| {
+                self.only_users.is_empty() || self.only_users.contains(*username)
+            })
+            .map(|(username, mut processes)| {
+                processes.sort_by_key(|p| (std::cmp::Reverse(p.run_time), p.pid));
+                LongRunning {
+                    username: username.to_string(),
+                    processes,
+                }
+            })
+            .collect();
+        long_running.sort_by_key(|l| std::cmp::Reverse(l.processes[0].run_time));
+        long_running
+    }
+
+    /// Each non-exempt user's processes using at least `cores` cores at less
+    /// than nice value `nice`, busiest first, for users with any.
+    fn unniced(&self, processes: &[(String, ProcessInfo)], nice: i32, cores: f64) -> Vec<Unniced> {
+        let mut by_user: HashMap<&str, Vec<ProcessUsage>> = HashMap::new();
+        for (username, p) in processes {
+            let heavy = p.usage.cpu_usage >= 100.0 * cores;
+            if heavy
+                && p.usage.nice.is_some_and(|n| n < nice)
+                && !self.exempt_users.contains(username)
+            {
+                by_user.entry(username).or_default().push(p.usage.clone());
+            }
+        }
+        let mut unniced: Vec<Unniced> = by_user
+            .into_iter()
+            .filter(|(username, _)| {
+                self.only_users.is_empty() || self.only_users.contains(*username)
+            })
+            .map(|(username, mut processes)| {
+                processes.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage));
+                Unniced {
+                    username: username.to_string(),
+                    processes,
+                }
+            })
+            .collect();
+        unniced.sort_by(|a, b| {
+            b.processes[0]
+                .cpu_usage
+                .total_cmp(&a.processes[0].cpu_usage)
+        });
+        unniced
+    }
+
+    /// Each non-exempt user's processes missing from the `previous` sample's
+    /// PIDs that use at least `cores` cores, busiest first, for users with
+    /// any.
+    fn new_heavy_processes(
+        &self,
+        processes: &[(String, ProcessInfo)],
+        previous: &HashSet<u32>,
+        cores: f64,
+    ) -> Vec<NewHeavy> {
+        let mut by_user: HashMap<&str, Vec<ProcessUsage>> = HashMap::new();
+        for (username, p) in processes {
+            if !previous.contains(&p.usage.pid)
+                && p.usage.cpu_usage >= 100.0 * cores
+                && !self.exempt_users.contains(username)
+            {
+                by_user.entry(username).or_default().push(p.usage.clone());
+            }
+        }
+        let mut new_heavy: Vec<NewHeavy> = by_user
+            .into_iter()
+            .filter(|(username, _)| {
+                self.only_users.is_empty() || self.only_users.contains(*username)
+            })
+            .map(|(username, mut processes)| {
+                processes.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage));
+                NewHeavy {
+                    username: username.to_string(),
+                    processes,
+                }
+            })
+            .collect();
+        new_heavy.sort_by(|a, b| {
+            b.processes[0]
+                .cpu_usage
+                .total_cmp(&a.processes[0].cpu_usage)
+        });
+        new_heavy
+    }
+}
+
+/// The user holding over half of all processes' swapped-out memory, as long
+/// as it's at least 1% of total memory, so a few swapped pages don't count.
+fn swap_hog(users: &[UserUsage], total_memory: u64) -> Option<SwapHog> {
+    let total: u64 = users.iter().filter_map(|u| u.swap).sum();
+    let user = users.iter().max_by_key(|u| u.swap.unwrap_or(0))?;
+    let swap = user.swap.unwrap_or(0);
+    let swap_share = 100.0 * swap as f64 / total as f64;
+    (swap_share > 50.0 && swap >= total_memory / 100).then(|| SwapHog {
+        username: user.username.clone(),
+        swap,
+        swap_share,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `loads` one second apart, returning the state after each.
+    fn states(sampler: &mut Sampler, loads: &[f64]) -> Vec<LoadState> {
+        let start = Instant::now();
+        loads
+            .iter()
+            .enumerate()
+            .map(|(i, &load)| {
+                sampler.update_load_state(load, start + Duration::from_secs(i as u64));
+                sampler.load_state
+            })
+            .collect()
+    }
+
+    #[test]
+    fn uid_ranges_are_parsed() {
+        let range = |name: &str, first, last| UidRange {
+            name: name.to_string(),
+            first,
+            last,
+        };
+        for (s, expected) in [
+            ("humans=1000-59999", range("humans", 1000, Some(59999))),
+            ("service=60000-", range("service", 60000, None)),
+            ("root=0", range("root", 0, Some(0))),
+            (" lab = 2000 - 2999 ", range("lab", 2000, Some(2999))),
+            ("one=5-5", range("one", 5, Some(5))),
+        ] {
+            assert_eq!(s.parse(), Ok(expected), "{}", s);
+        }
+    }
+
+    #[test]
+    fn bad_uid_ranges_are_errors() {
+        for s in [
+            "",
+            "humans",
+            "1000-59999",
+            "=1000-59999",
+            "humans=",
+            "humans=-59999",
+            "humans=59999-1000",
+            "humans=1000-59999-",
+            "humans=a-b",
+            "humans=4294967296",
+        ] {
+            assert!(s.parse::<UidRange>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn load_state_rises_through_warn_to_crit() {
+        use LoadState::*;
+        let mut sampler = Sampler::new().threshold(100.0).hysteresis(5.0);
+        assert_eq!(
+            states(&mut sampler, &[50.0, 80.0, 81.0, 100.0, 101.0]),
+            [Ok, Ok, Warn, Warn, Crit]
+        );
+        // Straight from OK to CRIT, past the warning threshold.
+        let mut sampler = Sampler::new().threshold(100.0).warn_threshold(60.0);
+        assert_eq!(states(&mut sampler, &[10.0, 150.0]), [Ok, Crit]);
+    }
+
+    #[test]
+    fn load_state_falls_only_below_the_hysteresis_band() {
+        use LoadState::*;
+        let mut sampler = Sampler::new().threshold(100.0).hysteresis(5.0);
+        assert_eq!(
+            states(&mut sampler, &[101.0, 99.0, 95.5, 95.0, 76.0, 75.0]),
+            [Crit, Crit, Crit, Warn, Warn, Ok]
+        );
+        // Without hysteresis, dropping below a threshold leaves its state.
+        let mut sampler = Sampler::new().threshold(100.0).hysteresis(0.0);
+        assert_eq!(states(&mut sampler, &[101.0, 99.0, 79.0]), [Crit, Warn, Ok]);
+    }
+
+    #[test]
+    fn load_state_dwells_before_changing() {
+        use LoadState::*;
+        let mut sampler = Sampler::new()
+            .threshold(100.0)
+            .hysteresis(0.0)
+            .min_dwell(Duration::from_secs(3));
+        // OK since 0s, so the first change can come at 3s, and the next 3s
+        // after that.
+        assert_eq!(
+            states(&mut sampler, &[10.0, 150.0, 150.0, 150.0, 10.0, 10.0, 10.0]),
+            [Ok, Ok, Ok, Crit, Crit, Crit, Ok]
+        );
+    }
+
+    #[test]
+    fn load_state_changes_report_the_previous_state() {
+        let mut sampler = Sampler::new().threshold(100.0);
+        let now = Instant::now();
+        assert_eq!(sampler.update_load_state(50.0, now), None);
+        assert_eq!(sampler.update_load_state(150.0, now), Some(LoadState::Ok));
+        assert_eq!(sampler.update_load_state(150.0, now), None);
+    }
+}
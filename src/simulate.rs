@@ -0,0 +1,261 @@
+//! Synthetic machines, for demos and screenshots, and for trying fair-share
+//! settings without a real multi-user machine.
+//!
+//! A scenario is a TOML file describing the machine and each user's
+//! processes:
+//!
+//! ```toml
+//! cores = 16
+//! memory_gib = 64
+//!
+//! [[users]]
+//! name = "alice"
+//! groups = ["lab"]
+//! processes = [{ command = "python train.py", cpu = 400, memory_mib = 8192, count = 3 }]
+//!
+//! [[users]]
+//! name = "bob"
+//! processes = [{ command = "Rscript fit.R", cpu = 100, memory_mib = 2048 }]
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::gpu::GpuUsage;
+use crate::k8s::Pod;
+use crate::report::{Host, LoadAverages, ProcessUsage, SystemMemory};
+use crate::sampler::{ProcessInfo, Snapshot};
+
+const GIB: f64 = (1u64 << 30) as f64;
+const MIB: f64 = (1u64 << 20) as f64;
+
+/// PIDs are handed out from here, like on a machine that has been up a while.
+const FIRST_PID: u32 = 1000;
+
+/// A synthetic machine and the processes running on it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// The machine's name (by default, `simulation`).
+    pub hostname: Option<String>,
+    /// Seconds since the machine booted.
+    #[serde(default)]
+    pub uptime: u64,
+    pub cores: usize,
+    /// Total memory, in GiB.
+    #[serde(default = "default_memory")]
+    pub memory_gib: f64,
+    /// Memory available for new allocations, in GiB (by default, total
+    /// memory less the processes' memory).
+    pub available_memory_gib: Option<f64>,
+    #[serde(default)]
+    pub swap_gib: f64,
+    #[serde(default)]
+    pub swap_used_gib: f64,
+    /// The 1, 5, and 15 minute load averages (by default, all the cores the
+    /// processes use).
+    pub load_average: Option<[f64; 3]>,
+    #[serde(default)]
+    pub users: Vec<User>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct User {
+    pub name: String,
+    /// The user's groups, the first being their primary group.
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Whether the user is a system account, like a daemon's.
+    #[serde(default)]
+    pub system: bool,
+    /// The user's UID, for grouping by UID range.
+    pub uid: Option<u32>,
+    /// CPU limit enforced on the user's slice, in cores, if any.
+    pub cpu_limit: Option<f64>,
+    #[serde(default)]
+    pub processes: Vec<Process>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Process {
+    pub command: String,
+    /// CPU usage, where 100% is one fully used core.
+    #[serde(default)]
+    pub cpu: f64,
+    /// Resident memory, in MiB.
+    #[serde(default)]
+    pub memory_mib: f64,
+    /// Number of identical processes.
+    #[serde(default = "one")]
+    pub count: usize,
+    #[serde(default = "one")]
+    pub threads: usize,
+    /// Seconds since the process started.
+    #[serde(default)]
+    pub run_time: u64,
+    /// Open file descriptors.
+    #[serde(default)]
+    pub fds: u64,
+    /// The process's nice value.
+    #[serde(default)]
+    pub nice: i32,
+    /// Whether the process has exited but not been reaped.
+    #[serde(default)]
+    pub zombie: bool,
+    /// The Slurm job the process belongs to, if any.
+    pub job: Option<u64>,
+    /// The container the process runs in, if any, e.g. `docker:0123456789ab`.
+    pub container: Option<String>,
+    /// The Kubernetes pod the process runs in, if any, as `namespace/name`.
+    pub pod: Option<String>,
+}
+
+fn default_memory() -> f64 {
+    64.0
+}
+
+fn one() -> usize {
+    1
+}
+
+impl Scenario {
+    /// Read a scenario from a TOML file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        Self::parse(&fs::read_to_string(path)?).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|e| e.to_string())
+    }
+
+    /// Each user's groups.
+    pub(crate) fn user_groups(&self) -> HashMap<String, Vec<String>> {
+        self.users
+            .iter()
+            .map(|user| (user.name.clone(), user.groups.clone()))
+            .collect()
+    }
+
+    /// A snapshot of the machine, leaving out `excluded` users.
+    pub(crate) fn snapshot(
+        &self,
+        user_groups: HashMap<String, Vec<String>>,
+        excluded: &HashSet<String>,
+    ) -> Snapshot {
+        let mut pid = FIRST_PID;
+        let mut processes = Vec::new();
+        for user in self.users.iter().filter(|u| !excluded.contains(&u.name)) {
+            // The user's other processes are children of their first, which
+            // leaves the zombies behind.
+            let parent = pid;
+            for process in &user.processes {
+                for _ in 0..process.count {
+                    let usage = ProcessUsage {
+                        pid,
+                        parent: (pid != parent).then_some(parent),
+                        command: process.command.clone(),
+                        cpu_usage: process.cpu,
+                        memory: (process.memory_mib * MIB) as u64,
+                        run_time: process.run_time,
+                        container: process.container.clone(),
+                        nice: Some(process.nice),
+                    };
+                    let info = ProcessInfo {
+                        usage,
+                        threads: process.threads,
+                        zombie: process.zombie,
+                        read_bytes: 0,
+                        written_bytes: 0,
+                        gpu: GpuUsage::default(),
+                        sent_bytes: 0,
+                        received_bytes: 0,
+                        swap: 0,
+                        fds: process.fds,
+                        job_id: process.job,
+                        pod: process.pod.as_deref().map(|pod| {
+                            let (namespace, name) = pod.split_once('/').unwrap_or(("default", pod));
+                            Pod {
+                                namespace: namespace.to_string(),
+                                name: name.to_string(),
+                            }
+                        }),
+                        system_account: user.system,
+                        uid: user.uid,
+                    };
+                    processes.push((user.name.clone(), info));
+                    pid += 1;
+                }
+            }
+        }
+
+        // Fill the cores one after another.
+        let mut cpu: f64 = processes.iter().map(|(_, p)| p.usage.cpu_usage).sum();
+        let cores = (0..self.cores.max(1))
+            .map(|_| {
+                let core = cpu.clamp(0.0, 100.0);
+                cpu -= core;
+                core
+            })
+            .collect();
+
+        let total = (self.memory_gib * GIB) as u64;
+        let used: u64 = processes.iter().map(|(_, p)| p.usage.memory).sum();
+        let busy = processes
+            .iter()
+            .map(|(_, p)| p.usage.cpu_usage / 100.0)
+            .sum();
+        let [one, five, fifteen] = self.load_average.unwrap_or([busy; 3]);
+        Snapshot {
+            processes,
+            primary_groups: self
+                .users
+                .iter()
+                .filter_map(|user| Some((user.name.clone(), user.groups.first()?.clone())))
+                .collect(),
+            user_groups,
+            sessions: None,
+            host: Some(Host {
+                hostname: self
+                    .hostname
+                    .clone()
+                    .unwrap_or_else(|| "simulation".to_string()),
+                uptime: self.uptime,
+                os: None,
+                kernel: None,
+            }),
+            cpu_limits: self
+                .users
+                .iter()
+                .filter_map(|user| Some((user.name.clone(), user.cpu_limit?)))
+                .collect(),
+            cores,
+            memory: SystemMemory {
+                total,
+                available: self
+                    .available_memory_gib
+                    .map_or(total.saturating_sub(used), |gib| (gib * GIB) as u64),
+                swap_total: (self.swap_gib * GIB) as u64,
+                swap_used: (self.swap_used_gib * GIB) as u64,
+                swapping: false,
+            },
+            load_average: LoadAverages { one, five, fifteen },
+            cpu_wait: None,
+            pressure: None,
+            thermal: None,
+            file_handles: None,
+            warnings: Vec::new(),
+        }
+    }
+}
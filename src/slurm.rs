@@ -0,0 +1,37 @@
+//! Attributing processes to Slurm jobs, from their cgroups.
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+/// Usage of one Slurm job, or of one user's processes outside any job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobUsage {
+    /// The job ID, or `None` for processes outside any job.
+    pub job_id: Option<u64>,
+    pub username: String,
+    /// Sum of process CPU usage, where 100% is one fully used core.
+    pub total_cpu_usage: f64,
+    pub equivalent_cores: f64,
+    /// Percent of total system CPU capacity.
+    pub system_cpu_share: f64,
+    /// Sum of process resident set sizes, in bytes.
+    pub memory: u64,
+    pub processes: usize,
+    /// Command of the job's busiest process.
+    pub top_command: String,
+}
+
+/// The Slurm job a process belongs to, if any.
+///
+/// slurmd puts each job's processes in a cgroup like
+/// `/slurm/uid_1000/job_1234/step_0` (cgroup v1) or
+/// `/system.slice/slurmstepd.scope/job_1234/step_0` (cgroup v2), so the job
+/// ID can be read from `/proc/<pid>/cgroup` without asking the controller.
+pub fn job_id(pid: u32) -> Option<u64> {
+    let cgroup = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    cgroup.lines().find_map(|line| {
+        line.split('/')
+            .find_map(|part| part.strip_prefix("job_")?.parse().ok())
+    })
+}
@@ -0,0 +1,239 @@
+//! `--statsd`: push gauges for each sample over UDP, for sites whose
+//! dashboards are built on StatsD or Datadog.
+
+use std::io;
+use std::net::UdpSocket;
+
+use clap::ValueEnum;
+use loadrs::LoadReport;
+
+/// Metric names start with this.
+const PREFIX: &str = "loadrs";
+
+/// Largest datagram to send, which fits in an Ethernet frame with room for
+/// IP and UDP headers, so metrics aren't dropped by fragmenting.
+const MAX_DATAGRAM: usize = 1432;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Flavor {
+    /// DogStatsD, with the host and user as tags (`#host:a,user:b`)
+    Dogstatsd,
+    /// Plain StatsD, which has no tags, so the host and user are part of
+    /// each metric's name
+    Statsd,
+}
+
+pub struct Statsd {
+    socket: UdpSocket,
+    flavor: Flavor,
+    host: String,
+}
+
+impl Statsd {
+    /// A client sending to `addr`, e.g. `localhost:8125`.
+    pub fn connect(addr: &str, flavor: Flavor) -> io::Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0").or_else(|_| UdpSocket::bind("[::]:0"))?;
+        socket.connect(addr)?;
+        Ok(Self {
+            socket,
+            flavor,
+            host: sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string()),
+        })
+    }
+
+    /// Send the report's gauges: the load averages, fair share, active users
+    /// and offender count, and each user's CPU share, cores, and memory.
+    pub fn send(&self, report: &LoadReport) -> io::Result<()> {
+        for datagram in datagrams(self.lines(report)) {
+            self.socket.send(datagram.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// The report's gauge lines.
+    fn lines(&self, report: &LoadReport) -> Vec<String> {
+        let load = &report.load_average;
+        let mut lines = Vec::new();
+        for (name, value) in [
+            ("load.1m", load.one),
+            ("load.5m", load.five),
+            ("load.15m", load.fifteen),
+            ("cores", report.total_cores as f64),
+            ("fair_share", report.fair_share.value),
+            ("active_users", report.fair_share.active_users as f64),
+            ("offenders", report.offenders.len() as f64),
+        ] {
+            lines.extend(self.gauge(name, value, None));
+        }
+        for user in &report.users {
+            for (name, value) in [
+                ("user.cpu_share", user.system_cpu_share),
+                ("user.cores", user.equivalent_cores),
+                ("user.memory_bytes", user.memory as f64),
+                ("user.memory_share", user.memory_share),
+            ] {
+                lines.extend(self.gauge(name, value, Some(&user.username)));
+            }
+        }
+        lines
+    }
+
+    /// A gauge line, e.g. `loadrs.user.cores:2.5|g|#host:node1,user:alice`,
+    /// or `None` for values StatsD can't represent, like an infinite fair
+    /// share.
+    fn gauge(&self, name: &str, value: f64, user: Option<&str>) -> Option<String> {
+        if !value.is_finite() {
+            return None;
+        }
+        // Without float noise like 14.010000000000014.
+        let value = (value * 1e4).round() / 1e4;
+        Some(match self.flavor {
+            Flavor::Dogstatsd => {
+                let mut tags = format!("host:{}", tag(&self.host));
+                if let Some(user) = user {
+                    tags.push_str(&format!(",user:{}", tag(user)));
+                }
+                format!("{}.{}:{}|g|#{}", PREFIX, name, value, tags)
+            }
+            Flavor::Statsd => {
+                // loadrs.node1.user.alice.cores rather than loadrs.user.cores
+                let (group, metric) = name.split_once('.').unwrap_or(("", name));
+                let mut path = vec![PREFIX.to_string(), segment(&self.host)];
+                match user {
+                    Some(user) => path.extend([group.to_string(), segment(user)]),
+                    None if !group.is_empty() => path.push(group.to_string()),
+                    None => {}
+                }
+                path.push(metric.to_string());
+                format!("{}:{}|g", path.join("."), value)
+            }
+        })
+    }
+}
+
+/// Join lines into as few datagrams of at most [`MAX_DATAGRAM`] bytes as
+/// they fit in. A longer line goes in a datagram of its own.
+fn datagrams(lines: Vec<String>) -> Vec<String> {
+    let mut datagrams = Vec::new();
+    let mut datagram = String::new();
+    for line in lines {
+        if !datagram.is_empty() && datagram.len() + 1 + line.len() > MAX_DATAGRAM {
+            datagrams.push(std::mem::take(&mut datagram));
+        }
+        if !datagram.is_empty() {
+            datagram.push('\n');
+        }
+        datagram.push_str(&line);
+    }
+    if !datagram.is_empty() {
+        datagrams.push(datagram);
+    }
+    datagrams
+}
+
+/// A DogStatsD tag value, without the characters that separate tags and
+/// fields.
+fn tag(value: &str) -> String {
+    value.replace([',', '|', '#', ' '], "_")
+}
+
+/// A StatsD name segment, e.g. `node1_example_com` for `node1.example.com`.
+pub fn segment(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::example_report;
+
+    fn statsd(flavor: Flavor) -> Statsd {
+        Statsd {
+            socket: UdpSocket::bind("127.0.0.1:0").unwrap(),
+            flavor,
+            host: "node1.example.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn dogstatsd_lines_tag_the_host_and_user() {
+        assert_eq!(
+            statsd(Flavor::Dogstatsd).lines(&example_report()),
+            [
+                "loadrs.load.1m:2|g|#host:node1.example.com",
+                "loadrs.load.5m:2|g|#host:node1.example.com",
+                "loadrs.load.15m:2|g|#host:node1.example.com",
+                "loadrs.cores:4|g|#host:node1.example.com",
+                "loadrs.fair_share:50|g|#host:node1.example.com",
+                "loadrs.active_users:2|g|#host:node1.example.com",
+                "loadrs.offenders:0|g|#host:node1.example.com",
+                "loadrs.user.cpu_share:37.5|g|#host:node1.example.com,user:alice_smith",
+                "loadrs.user.cores:1.5|g|#host:node1.example.com,user:alice_smith",
+                "loadrs.user.memory_bytes:1073741824|g|#host:node1.example.com,user:alice_smith",
+                "loadrs.user.memory_share:6.25|g|#host:node1.example.com,user:alice_smith",
+                "loadrs.user.cpu_share:12.5|g|#host:node1.example.com,user:o\"brien",
+                "loadrs.user.cores:0.5|g|#host:node1.example.com,user:o\"brien",
+                "loadrs.user.memory_bytes:536870912|g|#host:node1.example.com,user:o\"brien",
+                "loadrs.user.memory_share:3.125|g|#host:node1.example.com,user:o\"brien",
+            ]
+        );
+    }
+
+    #[test]
+    fn statsd_lines_put_the_host_and_user_in_the_name() {
+        assert_eq!(
+            statsd(Flavor::Statsd).lines(&example_report()),
+            [
+                "loadrs.node1_example_com.load.1m:2|g",
+                "loadrs.node1_example_com.load.5m:2|g",
+                "loadrs.node1_example_com.load.15m:2|g",
+                "loadrs.node1_example_com.cores:4|g",
+                "loadrs.node1_example_com.fair_share:50|g",
+                "loadrs.node1_example_com.active_users:2|g",
+                "loadrs.node1_example_com.offenders:0|g",
+                "loadrs.node1_example_com.user.alice_smith.cpu_share:37.5|g",
+                "loadrs.node1_example_com.user.alice_smith.cores:1.5|g",
+                "loadrs.node1_example_com.user.alice_smith.memory_bytes:1073741824|g",
+                "loadrs.node1_example_com.user.alice_smith.memory_share:6.25|g",
+                "loadrs.node1_example_com.user.o_brien.cpu_share:12.5|g",
+                "loadrs.node1_example_com.user.o_brien.cores:0.5|g",
+                "loadrs.node1_example_com.user.o_brien.memory_bytes:536870912|g",
+                "loadrs.node1_example_com.user.o_brien.memory_share:3.125|g",
+            ]
+        );
+    }
+
+    #[test]
+    fn values_statsd_cannot_represent_are_skipped() {
+        let statsd = statsd(Flavor::Statsd);
+        assert_eq!(statsd.gauge("fair_share", f64::INFINITY, None), None);
+        assert_eq!(statsd.gauge("fair_share", f64::NAN, None), None);
+        assert_eq!(
+            statsd.gauge("fair_share", 100.0 / 7.0, None),
+            Some("loadrs.node1_example_com.fair_share:14.2857|g".to_string())
+        );
+    }
+
+    #[test]
+    fn tags_and_segments_replace_separators() {
+        assert_eq!(tag("a b,c|d#e:f"), "a_b_c_d_e:f");
+        assert_eq!(segment("DOMAIN\\alice.smith"), "DOMAIN_alice_smith");
+    }
+
+    #[test]
+    fn lines_are_packed_into_datagrams() {
+        let line = "x".repeat(700);
+        let packed = datagrams(vec![line.clone(), line.clone(), line.clone()]);
+        assert_eq!(packed, [format!("{}\n{}", line, line), line.clone()]);
+        assert!(packed[0].len() <= MAX_DATAGRAM);
+        let long = "y".repeat(2000);
+        assert_eq!(datagrams(vec![long.clone(), line.clone()]), [long, line]);
+        assert!(datagrams(Vec::new()).is_empty());
+    }
+}
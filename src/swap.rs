@@ -0,0 +1,22 @@
+//! Per-process swap usage, read from `/proc/<pid>/status` (Linux only).
+//!
+//! Resident memory only counts the pages a process has in RAM, so a user
+//! whose processes were pushed out to swap can look small while the whole
+//! machine waits on disk.
+
+use std::fs;
+
+/// How much of a process's memory is swapped out, in bytes, or `None` if it
+/// can't be read (e.g. off Linux, or the process already exited). Kernel
+/// threads have no `VmSwap` line, and count as none swapped.
+pub fn swapped(pid: u32) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let swapped = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmSwap:"))
+        // e.g. "VmSwap:     1024 kB"
+        .and_then(|value| value.trim().strip_suffix("kB"))
+        .and_then(|kb| kb.trim().parse::<u64>().ok())
+        .map_or(0, |kb| kb * 1024);
+    Some(swapped)
+}
@@ -0,0 +1,692 @@
+//! Laying out and printing tables for the terminal.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::ValueEnum;
+use loadrs::{FairShare, GroupBy, LoadReport, PolicyKind, UserUsage};
+use prettytable::{color, format, Attr, Cell, Row, Table};
+
+use crate::{format_bytes, format_duration, shorten, Cli};
+
+/// Longest username shown with --compact, e.g. `jsmith-la…`.
+const COMPACT_NAME: usize = 10;
+
+/// Whether to print only ASCII, with --ascii.
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// A column of the users table, for --columns.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    /// Username (or container or group, with --group-by)
+    User,
+    /// Total CPU usage, where 100% is one core
+    Cpu,
+    /// CPU usage before --ewma smoothing
+    Raw,
+    /// Equivalent cores used
+    Cores,
+    /// Change in cores since the previous sample, with ▲ or ▼ (--live only)
+    Delta,
+    /// CPU time summed over every sample so far (with --cumulative)
+    CpuTime,
+    /// Share of total system CPU
+    Share,
+    /// Resident memory
+    Mem,
+    /// Share of total system memory
+    MemShare,
+    /// Memory swapped out (with --swap)
+    Swap,
+    /// Open file descriptors (with --fds)
+    Fds,
+    /// Mean nice value of the user's processes, weighted by CPU usage (with
+    /// --nice)
+    Nice,
+    /// Fair share of system CPU
+    Fair,
+    /// How far below fair share (negative once over it)
+    Headroom,
+    /// CPU share as a percent of fair share, with a bar that is half full
+    /// at fair share
+    OfFair,
+    /// CPU limit enforced on the user's systemd user slice, in cores (with
+    /// --cgroup-limits)
+    Limit,
+    /// Cores used as a percent of the user's CPU limit (with
+    /// --cgroup-limits)
+    OfLimit,
+    /// Long-running fair-share score from decayed usage, from 1 (no usage)
+    /// towards 0 (with --fairshare-file)
+    Score,
+    /// Cores used as a multiple of the user's usual cores over the
+    /// --baseline period (with --record)
+    Usual,
+    /// How long the user has been over fair share (--live only)
+    Over,
+    /// Number of processes, which can stress the scheduler even at low CPU
+    /// usage
+    Procs,
+    /// Number of threads across all processes
+    Threads,
+    /// Number of zombie processes, which have exited but not been reaped
+    Zombies,
+    /// GPU utilization (with --gpu)
+    Gpu,
+    /// GPU memory (with --gpu)
+    GpuMem,
+    /// Disk read rate (with --io)
+    Read,
+    /// Disk write rate (with --io)
+    Write,
+    /// TCP send rate (with --net)
+    Sent,
+    /// TCP receive rate (with --net)
+    Received,
+    /// Login sessions, with their terminal, login time, and idle time, or
+    /// "detached" if not logged in (with --logins)
+    Login,
+}
+
+impl Column {
+    fn title(self, group_by: GroupBy) -> &'static str {
+        match self {
+            Column::User => group_title(group_by),
+            Column::Cpu => "Total CPU Usage (%)",
+            Column::Raw => "Raw CPU Usage (%)",
+            Column::Cores => "Equivalent Cores Used",
+            Column::Delta if ascii() => "Change in Cores",
+            Column::Delta => "Δ Cores",
+            Column::CpuTime => "CPU Time",
+            Column::Share => "System CPU Share (%)",
+            Column::Mem => "Memory (RSS)",
+            Column::MemShare => "Memory Share (%)",
+            Column::Swap => "Swap",
+            Column::Fds => "Open Files",
+            Column::Nice => "Mean Nice",
+            Column::Fair => "Fair Share (%)",
+            Column::Headroom => "Headroom (%)",
+            Column::OfFair => "% of Fair Share",
+            Column::Limit => "CPU Limit (cores)",
+            Column::OfLimit => "% of Limit",
+            Column::Score => "Fair-Share Score",
+            Column::Usual => "vs. Usual",
+            Column::Over => "Time Over Share",
+            Column::Procs => "Processes",
+            Column::Threads => "Threads",
+            Column::Zombies => "Zombies",
+            Column::Gpu => "GPU Usage (%)",
+            Column::GpuMem => "GPU Memory",
+            Column::Read => "Disk Read",
+            Column::Write => "Disk Write",
+            Column::Sent => "Net Sent",
+            Column::Received => "Net Received",
+            Column::Login => "Logged In",
+        }
+    }
+
+    /// An abbreviated title, for --compact.
+    fn short_title(self) -> &'static str {
+        match self {
+            Column::User => "User",
+            Column::Cpu => "CPU%",
+            Column::Raw => "Raw%",
+            Column::Cores => "Cores",
+            Column::Delta if ascii() => "Change",
+            Column::Delta => "Δ",
+            Column::CpuTime => "CPU Time",
+            Column::Share => "Share%",
+            Column::Mem => "Mem",
+            Column::MemShare => "Mem%",
+            Column::Swap => "Swap",
+            Column::Fds => "FDs",
+            Column::Nice => "Nice",
+            Column::Fair => "Fair%",
+            Column::Headroom => "Room%",
+            Column::OfFair => "of Fair",
+            Column::Limit => "Limit",
+            Column::OfLimit => "of Limit",
+            Column::Score => "Score",
+            Column::Usual => "vs Usual",
+            Column::Over => "Over",
+            Column::Procs => "Procs",
+            Column::Threads => "Thr",
+            Column::Zombies => "Zomb",
+            Column::Gpu => "GPU%",
+            Column::GpuMem => "GPU Mem",
+            Column::Read => "Read",
+            Column::Write => "Write",
+            Column::Sent => "Sent",
+            Column::Received => "Recv",
+            Column::Login => "Login",
+        }
+    }
+
+    /// The column's cell for `user`, abbreviated for --compact.
+    fn compact_cell(self, user: &UserUsage) -> String {
+        match self {
+            Column::User => shorten(&user.username, COMPACT_NAME),
+            Column::OfFair if user.fair_share.is_finite() && user.fair_share > 0.0 => {
+                format!("{:.0}%", 100.0 * user.system_cpu_share / user.fair_share)
+            }
+            Column::Login => user.logins.as_ref().map_or(String::new(), |logins| {
+                if logins.is_empty() { "detached" } else { "yes" }.to_string()
+            }),
+            _ => self.cell(user),
+        }
+    }
+
+    fn cell(self, user: &UserUsage) -> String {
+        match self {
+            Column::User => match user.unresolved_uids {
+                Some(1) => format!("{} (1 UID)", user.username),
+                Some(count) => format!("{} ({} UIDs)", user.username, count),
+                None => user.username.clone(),
+            },
+            Column::Cpu => format!("{:.2}", user.total_cpu_usage),
+            Column::Raw => user
+                .raw_cpu_usage
+                .map_or(String::new(), |cpu| format!("{:.2}", cpu)),
+            Column::Cores => format!("{:.2}", user.equivalent_cores),
+            Column::Delta => user.cores_change.map_or(String::new(), change),
+            Column::CpuTime => user
+                .cpu_time
+                .map_or(String::new(), |t| format_duration(t as u64)),
+            Column::Share => format!("{:.2}", user.system_cpu_share),
+            Column::Mem => format_bytes(user.memory),
+            Column::MemShare => format!("{:.2}", user.memory_share),
+            Column::Swap => user.swap.map_or(String::new(), format_bytes),
+            Column::Fds => user.fds.map_or(String::new(), |fds| fds.to_string()),
+            Column::Nice => user
+                .nice
+                .map_or(String::new(), |nice| format!("{:.1}", nice)),
+            Column::Fair => format!("{:.2}", user.fair_share),
+            Column::Headroom => format!("{:.2}", user.fair_share - user.system_cpu_share),
+            Column::OfFair => of_fair_share(user),
+            Column::Limit => user
+                .cpu_limit
+                .map_or(String::new(), |limit| format!("{:.2}", limit)),
+            Column::OfLimit => user.cpu_limit.map_or(String::new(), |limit| {
+                format!("{:.0}%", 100.0 * user.equivalent_cores / limit)
+            }),
+            Column::Score => user
+                .fairshare_score
+                .map_or(String::new(), |score| format!("{:.3}", score)),
+            Column::Usual => vs_usual(user),
+            Column::Over => user
+                .time_over_fair_share
+                .map_or(String::new(), format_duration),
+            Column::Procs => user.process_count.to_string(),
+            Column::Threads => user.thread_count.to_string(),
+            Column::Zombies => user.zombies.to_string(),
+            Column::Gpu => user
+                .gpu
+                .map_or(String::new(), |gpu| format!("{:.2}", gpu.utilization)),
+            Column::GpuMem => user
+                .gpu
+                .map_or(String::new(), |gpu| format_bytes(gpu.memory)),
+            Column::Read => user.disk.map_or(String::new(), |disk| {
+                format!("{}/s", format_bytes(disk.read_rate as u64))
+            }),
+            Column::Write => user.disk.map_or(String::new(), |disk| {
+                format!("{}/s", format_bytes(disk.write_rate as u64))
+            }),
+            Column::Sent => user.net.map_or(String::new(), |net| {
+                format!("{}/s", format_bytes(net.send_rate as u64))
+            }),
+            Column::Received => user.net.map_or(String::new(), |net| {
+                format!("{}/s", format_bytes(net.receive_rate as u64))
+            }),
+            Column::Login => logins(user),
+        }
+    }
+}
+
+impl Column {
+    /// The column's total over all users, for columns that add up.
+    fn total(self, users: &[UserUsage]) -> String {
+        let sum = |value: fn(&UserUsage) -> f64| users.iter().map(value).sum::<f64>();
+        match self {
+            Column::User => "Total".to_string(),
+            Column::Cpu => format!("{:.2}", sum(|u| u.total_cpu_usage)),
+            Column::Raw if users.iter().any(|u| u.raw_cpu_usage.is_some()) => {
+                format!("{:.2}", sum(|u| u.raw_cpu_usage.unwrap_or(0.0)))
+            }
+            Column::Cores => format!("{:.2}", sum(|u| u.equivalent_cores)),
+            Column::Delta if users.iter().any(|u| u.cores_change.is_some()) => {
+                change(sum(|u| u.cores_change.unwrap_or(0.0)))
+            }
+            Column::CpuTime if users.iter().any(|u| u.cpu_time.is_some()) => {
+                format_duration(sum(|u| u.cpu_time.unwrap_or(0.0)) as u64)
+            }
+            Column::Share => format!("{:.2}", sum(|u| u.system_cpu_share)),
+            Column::Mem => format_bytes(users.iter().map(|u| u.memory).sum()),
+            Column::MemShare => format!("{:.2}", sum(|u| u.memory_share)),
+            Column::Swap if users.iter().any(|u| u.swap.is_some()) => {
+                format_bytes(users.iter().filter_map(|u| u.swap).sum())
+            }
+            Column::Fds if users.iter().any(|u| u.fds.is_some()) => {
+                users.iter().filter_map(|u| u.fds).sum::<u64>().to_string()
+            }
+            Column::Procs => users
+                .iter()
+                .map(|u| u.process_count)
+                .sum::<usize>()
+                .to_string(),
+            Column::Threads => users
+                .iter()
+                .map(|u| u.thread_count)
+                .sum::<usize>()
+                .to_string(),
+            Column::Zombies => users.iter().map(|u| u.zombies).sum::<usize>().to_string(),
+            Column::Gpu if users.iter().any(|u| u.gpu.is_some()) => {
+                format!("{:.2}", sum(|u| u.gpu.map_or(0.0, |gpu| gpu.utilization)))
+            }
+            Column::GpuMem if users.iter().any(|u| u.gpu.is_some()) => format_bytes(
+                users
+                    .iter()
+                    .map(|u| u.gpu.map_or(0, |gpu| gpu.memory))
+                    .sum(),
+            ),
+            Column::Read if users.iter().any(|u| u.disk.is_some()) => format!(
+                "{}/s",
+                format_bytes(sum(|u| u.disk.map_or(0.0, |disk| disk.read_rate)) as u64)
+            ),
+            Column::Write if users.iter().any(|u| u.disk.is_some()) => format!(
+                "{}/s",
+                format_bytes(sum(|u| u.disk.map_or(0.0, |disk| disk.write_rate)) as u64)
+            ),
+            Column::Sent if users.iter().any(|u| u.net.is_some()) => format!(
+                "{}/s",
+                format_bytes(sum(|u| u.net.map_or(0.0, |net| net.send_rate)) as u64)
+            ),
+            Column::Received if users.iter().any(|u| u.net.is_some()) => format!(
+                "{}/s",
+                format_bytes(sum(|u| u.net.map_or(0.0, |net| net.receive_rate)) as u64)
+            ),
+            _ => String::new(),
+        }
+    }
+}
+
+/// The columns given with --columns, or else the ones relevant to `report`.
+pub fn columns(report: &LoadReport, cli: &Cli) -> Vec<Column> {
+    if !cli.columns.is_empty() {
+        return cli.columns.clone();
+    }
+    // When watching specific users, show how far each is from fair share.
+    let watch = !cli.user.is_empty();
+    let mut columns = vec![Column::User, Column::Cpu];
+    if report.users.iter().any(|u| u.raw_cpu_usage.is_some()) {
+        columns.push(Column::Raw);
+    }
+    columns.push(Column::Cores);
+    // Changes are only known across samples.
+    if cli.live {
+        columns.push(Column::Delta);
+    }
+    if report.users.iter().any(|u| u.cpu_time.is_some()) {
+        columns.push(Column::CpuTime);
+    }
+    columns.extend([Column::Share, Column::Mem, Column::MemShare]);
+    if report.users.iter().any(|u| u.swap.is_some()) {
+        columns.push(Column::Swap);
+    }
+    if report.users.iter().any(|u| u.fds.is_some()) {
+        columns.push(Column::Fds);
+    }
+    if report.users.iter().any(|u| u.nice.is_some()) {
+        columns.push(Column::Nice);
+    }
+    if report.fair_share.policy == PolicyKind::Weighted || watch {
+        columns.push(Column::Fair);
+    }
+    if watch {
+        columns.push(Column::Headroom);
+    }
+    // Without active users, there is no fair share to compare against.
+    if report.fair_share.value.is_finite() {
+        columns.push(Column::OfFair);
+    }
+    if report.users.iter().any(|u| u.cpu_limit.is_some()) {
+        columns.extend([Column::Limit, Column::OfLimit]);
+    }
+    if report.users.iter().any(|u| u.fairshare_score.is_some()) {
+        columns.push(Column::Score);
+    }
+    if report.users.iter().any(|u| u.usual_cores.is_some()) {
+        columns.push(Column::Usual);
+    }
+    // How long users have been over fair share is only tracked across samples.
+    if cli.live {
+        columns.push(Column::Over);
+    }
+    if report.users.iter().any(|u| u.gpu.is_some()) {
+        columns.extend([Column::Gpu, Column::GpuMem]);
+    }
+    if report.users.iter().any(|u| u.disk.is_some()) {
+        columns.extend([Column::Read, Column::Write]);
+    }
+    if report.users.iter().any(|u| u.net.is_some()) {
+        columns.extend([Column::Sent, Column::Received]);
+    }
+    if report.users.iter().any(|u| u.logins.is_some()) {
+        columns.push(Column::Login);
+    }
+    columns
+}
+
+/// Columns for --compact: cores rather than CPU percentages and shares, and
+/// how each user compares to their fair share.
+fn compact_columns(report: &LoadReport, cli: &Cli) -> Vec<Column> {
+    let mut columns = vec![Column::User, Column::Cores, Column::Mem];
+    if report.fair_share.value.is_finite() {
+        columns.push(Column::OfFair);
+    }
+    if report.users.iter().any(|u| u.cpu_limit.is_some()) {
+        columns.push(Column::OfLimit);
+    }
+    if cli.live {
+        columns.extend([Column::Over, Column::Delta]);
+    }
+    columns
+}
+
+/// The width tables have to fit in: --width, or the terminal's when writing
+/// to one.
+pub fn width(cli: &Cli) -> Option<usize> {
+    if cli.width.is_some() {
+        return cli.width;
+    }
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    ratatui::crossterm::terminal::size()
+        .ok()
+        .map(|(columns, _)| columns as usize)
+}
+
+/// The widest line of a rendered table.
+fn table_width(table: &Table) -> usize {
+    table
+        .to_string()
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+/// The users table, with each row colored by how close the user is to their
+/// fair share, and a row of totals. Abbreviated with --compact, or when it
+/// would be wider than the terminal, dropping columns from the right if even
+/// that doesn't fit.
+pub fn users(report: &LoadReport, cli: &Cli) -> Table {
+    if !cli.compact {
+        let table = users_table(report, cli, &columns(report, cli), false);
+        match width(cli) {
+            Some(width) if table_width(&table) > width && cli.columns.is_empty() => {}
+            _ => return table,
+        }
+    }
+    let mut columns = if cli.columns.is_empty() {
+        compact_columns(report, cli)
+    } else {
+        cli.columns.clone()
+    };
+    loop {
+        let table = users_table(report, cli, &columns, true);
+        match width(cli) {
+            Some(width)
+                if table_width(&table) > width && columns.len() > 2 && cli.columns.is_empty() =>
+            {
+                columns.pop();
+            }
+            _ => return table,
+        }
+    }
+}
+
+fn users_table(report: &LoadReport, cli: &Cli, columns: &[Column], compact: bool) -> Table {
+    let mut table = new(columns.iter().map(|column| match compact {
+        true => column.short_title(),
+        false => column.title(report.group_by),
+    }));
+    let mut others: Vec<UserUsage> = Vec::new();
+    if !cli.all && cli.user.is_empty() {
+        others = report
+            .users
+            .iter()
+            .filter(|user| folded(user, &report.fair_share))
+            .cloned()
+            .collect();
+        // A row standing in for a single user hides nothing.
+        if others.len() < 2 {
+            others.clear();
+        }
+    }
+    for user in &report.users {
+        if others.iter().any(|other| other.username == user.username) {
+            continue;
+        }
+        let color = share_color(
+            user.system_cpu_share,
+            user.fair_share,
+            cli.warn_ratio,
+            cli.crit_ratio,
+        );
+        table.add_row(colored_row(
+            columns.iter().map(|column| match compact {
+                true => column.compact_cell(user),
+                false => column.cell(user),
+            }),
+            color,
+        ));
+    }
+    if !others.is_empty() {
+        table.add_row(Row::new(
+            columns
+                .iter()
+                .map(|column| match column {
+                    Column::User if compact => Cell::new(&format!("others ({})", others.len())),
+                    Column::User => Cell::new(&format!("others/system ({})", others.len())),
+                    _ => Cell::new(&column.total(&others)),
+                })
+                .collect(),
+        ));
+    }
+    if report.users.len() > 1 {
+        table.add_row(Row::new(
+            columns
+                .iter()
+                .map(|column| Cell::new(&column.total(&report.users)).with_style(Attr::Bold))
+                .collect(),
+        ));
+    }
+    table
+}
+
+/// Whether a user is folded into the "others/system" row without --all:
+/// they use less than the active threshold of both CPU and memory, or are a
+/// system account within their fair share of both.
+fn folded(user: &UserUsage, fair_share: &FairShare) -> bool {
+    let active_threshold = fair_share.active_threshold;
+    let idle = user.system_cpu_share < active_threshold && user.memory_share < active_threshold;
+    let within_share =
+        user.system_cpu_share <= user.fair_share && user.memory_share <= fair_share.memory;
+    idle || (user.system_account && within_share)
+}
+
+/// A user's CPU share as a percent of their fair share, with a bar that
+/// fills up to twice the fair share, like `███████░░░ 140%`.
+fn of_fair_share(user: &UserUsage) -> String {
+    const WIDTH: usize = 10;
+    if !user.fair_share.is_finite() || user.fair_share <= 0.0 {
+        return String::new();
+    }
+    let percent = 100.0 * user.system_cpu_share / user.fair_share;
+    let filled = ((percent / 200.0 * WIDTH as f64).round() as usize).min(WIDTH);
+    format!(
+        "{}{} {:.0}%",
+        "█".repeat(filled),
+        "░".repeat(WIDTH - filled),
+        percent
+    )
+}
+
+/// A change in cores, like `▲1.50` or `▼0.25`, or `0.00` if it rounds to
+/// nothing.
+fn change(cores: f64) -> String {
+    if cores >= 0.005 {
+        format!("▲{:.2}", cores)
+    } else if cores <= -0.005 {
+        format!("▼{:.2}", -cores)
+    } else {
+        "0.00".to_string()
+    }
+}
+
+/// A user's cores as a multiple of their usual cores, like `2.4×`, or "new"
+/// for users who were idle until now.
+fn vs_usual(user: &UserUsage) -> String {
+    const IDLE: f64 = 0.01;
+    match user.usual_cores {
+        None => String::new(),
+        Some(_) if user.equivalent_cores < IDLE => String::new(),
+        Some(usual) if usual < IDLE => "new".to_string(),
+        Some(usual) => format!("{:.1}×", user.equivalent_cores / usual),
+    }
+}
+
+/// A user's login sessions, like `pts/0 since 2024-06-01 13:00, idle
+/// 00:05:00`, or "detached" if they aren't logged in.
+pub fn logins(user: &UserUsage) -> String {
+    match user.logins.as_deref() {
+        None => String::new(),
+        Some([]) => "detached".to_string(),
+        Some(logins) => logins
+            .iter()
+            .map(|login| {
+                let idle = match login.idle {
+                    Some(0) => String::new(),
+                    Some(idle) => format!(", idle {}", format_duration(idle)),
+                    None => ", idle over 1d".to_string(),
+                };
+                format!("{} since {}{}", login.tty, login.login_time, idle)
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+    }
+}
+
+/// The title of the column naming each user, or whatever usage is grouped
+/// by instead.
+pub fn group_title(group_by: GroupBy) -> &'static str {
+    match group_by {
+        GroupBy::User => "Username",
+        GroupBy::Container => "Container or User",
+        GroupBy::Group => "Group",
+        GroupBy::UidRange => "UID Range",
+    }
+}
+
+/// An empty table with the usual borderless format.
+pub fn new<'a>(titles: impl IntoIterator<Item = &'a str>) -> Table {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(Row::new(titles.into_iter().map(Cell::new).collect()));
+    table
+}
+
+/// A row with every cell in `color` (a name from [`share_color`]).
+pub fn colored_row(cells: impl IntoIterator<Item = String>, color: &str) -> Row {
+    Row::new(
+        cells
+            .into_iter()
+            .map(|cell| {
+                Cell::new(&cell).with_style(Attr::ForegroundColor(color_from_string(color)))
+            })
+            .collect(),
+    )
+}
+
+/// The color for a user's CPU share: red above `crit_ratio` times their fair
+/// share, yellow above `warn_ratio` times it, and green otherwise.
+pub fn share_color(
+    cpu_share: f64,
+    fair_share: f64,
+    warn_ratio: f64,
+    crit_ratio: f64,
+) -> &'static str {
+    if cpu_share > fair_share * crit_ratio {
+        "red"
+    } else if cpu_share > fair_share * warn_ratio {
+        "yellow"
+    } else {
+        "green"
+    }
+}
+
+/// Print only ASCII from now on, for email, ticket systems, and old
+/// terminals.
+pub fn set_ascii(ascii: bool) {
+    ASCII.store(ascii, Ordering::Relaxed);
+}
+
+pub fn ascii() -> bool {
+    ASCII.load(Ordering::Relaxed)
+}
+
+/// `s` with bars and box drawing replaced by ASCII lookalikes and any other
+/// non-ASCII character by `?`, one for one so tables stay aligned, with
+/// --ascii; otherwise `s` unchanged.
+pub fn plain(s: String) -> String {
+    if !ascii() {
+        return s;
+    }
+    s.chars()
+        .map(|c| match c {
+            c if c.is_ascii() => c,
+            '▁' => '_',
+            '▂' => '.',
+            '▃' => '-',
+            '▄' => '=',
+            '▅' => '+',
+            '▆' => '*',
+            '▇' => '%',
+            '█' => '#',
+            '░' | '·' | '…' => '.',
+            '×' => 'x',
+            '▲' => '+',
+            '▼' => '-',
+            '└' => '`',
+            '├' | '│' => '|',
+            '─' => '-',
+            _ => '?',
+        })
+        .collect()
+}
+
+/// Print a table to stdout, in color unless colors are turned off by
+/// --color, `NO_COLOR`, or stdout not being a terminal, and only in ASCII
+/// with --ascii.
+pub fn print(table: &Table) {
+    let result = if ascii() {
+        write!(io::stdout(), "{}", plain(table.to_string()))
+    } else if colored::control::SHOULD_COLORIZE.should_colorize() {
+        table.print_tty(true).map(|_| ())
+    } else {
+        table.print(&mut io::stdout()).map(|_| ())
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+    }
+}
+
+fn color_from_string(color: &str) -> color::Color {
+    match color {
+        "red" => color::RED,
+        "yellow" => color::YELLOW,
+        "green" => color::GREEN,
+        _ => color::WHITE,
+    }
+}
@@ -0,0 +1,161 @@
+//! CPU temperatures and frequency throttling, since a machine that's slow at
+//! low load is often hot rather than overloaded.
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use sysinfo::Components;
+
+/// Packages within this many degrees of their critical temperature are
+/// throttling, or about to.
+const CRITICAL_MARGIN: f64 = 5.0;
+
+/// Packages at this temperature are considered hot when their critical
+/// temperature isn't reported.
+const HOT: f64 = 95.0;
+
+/// Busy cores running below this share of their maximum frequency are being
+/// held back.
+const MIN_FREQUENCY_SHARE: f64 = 0.6;
+
+/// Cores at least this busy should be running at full speed.
+const BUSY: f64 = 90.0;
+
+/// The CPUs' temperatures and clock speeds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thermal {
+    /// Temperature of each CPU package (or die, on AMD).
+    pub packages: Vec<Temperature>,
+    /// Mean frequency of the cores at least 90% busy, in MHz, if any are.
+    pub busy_frequency_mhz: Option<f64>,
+    /// Highest frequency the cores can run at, in MHz.
+    pub max_frequency_mhz: Option<f64>,
+    /// Times the cores were throttled for heat since the previous sample
+    /// (Intel only).
+    pub throttle_events: Option<u64>,
+}
+
+/// One sensor's reading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Temperature {
+    pub label: String,
+    /// Degrees Celsius.
+    pub celsius: f64,
+    /// Temperature at which the hardware shuts down, if reported.
+    pub critical: Option<f64>,
+}
+
+impl Temperature {
+    /// Whether the package is close enough to its limit to be throttling.
+    pub fn hot(&self) -> bool {
+        self.celsius >= self.critical.map_or(HOT, |c| c - CRITICAL_MARGIN)
+    }
+}
+
+impl Thermal {
+    /// Whether the machine is thermally limited: throttle events were
+    /// counted, a package is hot, or busy cores are held well below their
+    /// maximum frequency.
+    pub fn throttled(&self) -> bool {
+        self.throttle_events.is_some_and(|events| events > 0)
+            || self.packages.iter().any(Temperature::hot)
+            || self.slowed()
+    }
+
+    /// Whether busy cores are running well below their maximum frequency.
+    pub fn slowed(&self) -> bool {
+        match (self.busy_frequency_mhz, self.max_frequency_mhz) {
+            (Some(busy), Some(max)) => busy < MIN_FREQUENCY_SHARE * max,
+            _ => false,
+        }
+    }
+}
+
+/// Read the CPU's temperatures and frequencies, given each core's
+/// utilization and the throttle count at the previous and current sample.
+/// `None` if the system reports none of them, e.g. in most virtual machines.
+pub(crate) fn thermal(
+    components: &Components,
+    cores: &[f64],
+    throttles: (Option<u64>, Option<u64>),
+) -> Option<Thermal> {
+    let packages = packages(components);
+    let frequencies: Vec<Option<(f64, f64)>> = (0..cores.len()).map(frequency).collect();
+    let busy: Vec<f64> = cores
+        .iter()
+        .zip(&frequencies)
+        .filter(|(usage, _)| **usage >= BUSY)
+        .filter_map(|(_, frequency)| frequency.map(|(current, _)| current))
+        .collect();
+    let thermal = Thermal {
+        packages,
+        busy_frequency_mhz: (!busy.is_empty())
+            .then(|| busy.iter().sum::<f64>() / busy.len() as f64),
+        max_frequency_mhz: frequencies
+            .iter()
+            .flatten()
+            .map(|(_, max)| *max)
+            .reduce(f64::max),
+        throttle_events: match throttles {
+            (Some(previous), Some(current)) => Some(current.saturating_sub(previous)),
+            _ => None,
+        },
+    };
+    (!thermal.packages.is_empty()
+        || thermal.max_frequency_mhz.is_some()
+        || thermal.throttle_events.is_some())
+    .then_some(thermal)
+}
+
+/// Package temperatures, from sensors like coretemp's "Package id 0" or
+/// k10temp's "Tctl", falling back to every CPU sensor.
+fn packages(components: &Components) -> Vec<Temperature> {
+    let reading = |c: &sysinfo::Component| Temperature {
+        label: c.label().to_string(),
+        celsius: c.temperature() as f64,
+        critical: c.critical().map(|t| t as f64),
+    };
+    let matching = |patterns: &[&str]| -> Vec<Temperature> {
+        components
+            .iter()
+            .filter(|c| {
+                let label = c.label().to_lowercase();
+                patterns.iter().any(|p| label.contains(p))
+            })
+            .filter(|c| c.temperature().is_finite() && c.temperature() > 0.0)
+            .map(reading)
+            .collect()
+    };
+    let packages = matching(&["package", "tctl", "tdie"]);
+    if packages.is_empty() {
+        matching(&["cpu", "core"])
+    } else {
+        packages
+    }
+}
+
+/// A core's current and maximum frequency, in MHz (Linux only).
+fn frequency(cpu: usize) -> Option<(f64, f64)> {
+    let read = |name: &str| -> Option<f64> {
+        let path = format!("/sys/devices/system/cpu/cpu{}/cpufreq/{}", cpu, name);
+        let khz: f64 = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+        Some(khz / 1000.0)
+    };
+    Some((read("scaling_cur_freq")?, read("cpuinfo_max_freq")?))
+}
+
+/// Times the cores have been throttled for heat since boot, summed over
+/// cores (Linux on Intel only).
+pub(crate) fn throttle_count() -> Option<u64> {
+    let cpus = fs::read_dir("/sys/devices/system/cpu").ok()?;
+    let counts: Vec<u64> = cpus
+        .filter_map(|entry| {
+            let path = entry
+                .ok()?
+                .path()
+                .join("thermal_throttle/core_throttle_count");
+            fs::read_to_string(path).ok()?.trim().parse().ok()
+        })
+        .collect();
+    (!counts.is_empty()).then(|| counts.iter().sum())
+}
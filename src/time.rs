@@ -0,0 +1,190 @@
+//! Parsing and formatting times without a timezone database. All calendar
+//! dates and times are UTC, except RFC 3339 timestamps, which carry the
+//! local UTC offset.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Parse a point in time as seconds since the Unix epoch. Accepts a Unix
+/// timestamp, a date (`2024-06-01`), a date and time (`2024-06-01T13:00`), or
+/// a time relative to now (`30m`, `12h`, `7d`, `2w` ago).
+pub fn parse_time(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    if let Some(value) = parse_relative(s) {
+        return Ok(now().saturating_sub(value));
+    }
+    if s.chars().all(|c| c.is_ascii_digit()) && !s.is_empty() {
+        return s.parse().map_err(|e| format!("invalid timestamp: {}", e));
+    }
+    parse_datetime(s).ok_or_else(|| {
+        format!(
+            "invalid time '{}' (expected e.g. 7d, 2024-06-01, or 2024-06-01T13:00)",
+            s
+        )
+    })
+}
+
+/// Parse a duration like `90s`, `30m`, `12h`, `7d`, or `2w` into seconds.
+pub fn parse_duration(s: &str) -> Result<u64, String> {
+    parse_relative(s.trim())
+        .ok_or_else(|| format!("invalid duration '{}' (expected e.g. 90s, 30m, 12h, 7d)", s))
+}
+
+fn parse_relative(s: &str) -> Option<u64> {
+    let unit = match s.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 7 * 86400,
+        _ => return None,
+    };
+    let value: u64 = s[..s.len() - 1].parse().ok()?;
+    value.checked_mul(unit)
+}
+
+fn parse_datetime(s: &str) -> Option<u64> {
+    let (date, time) = match s.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (s, None),
+    };
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month, day) * 86400;
+    if let Some(time) = time {
+        let mut parts = time.splitn(3, ':');
+        let hour: i64 = parts.next()?.parse().ok()?;
+        let minute: i64 = parts.next().map_or(Some(0), |m| m.parse().ok())?;
+        let second: i64 = parts.next().map_or(Some(0), |s| s.parse().ok())?;
+        seconds += hour * 3600 + minute * 60 + second;
+    }
+    u64::try_from(seconds).ok()
+}
+
+/// Format seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS` (UTC).
+pub fn format_timestamp(timestamp: u64) -> String {
+    let (days, rest) = ((timestamp / 86400) as i64, timestamp % 86400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 timestamp in local
+/// time, e.g. `2024-06-01T13:00:00-07:00`.
+pub fn format_rfc3339(timestamp: u64) -> String {
+    let offset = utc_offset(timestamp);
+    let local = format_timestamp(timestamp.saturating_add_signed(offset)).replace(' ', "T");
+    if offset == 0 {
+        return format!("{}Z", local);
+    }
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.unsigned_abs();
+    format!(
+        "{}{}{:02}:{:02}",
+        local,
+        sign,
+        offset / 3600,
+        offset % 3600 / 60
+    )
+}
+
+/// The local time zone's offset from UTC at `timestamp`, in seconds.
+#[cfg(unix)]
+fn utc_offset(timestamp: u64) -> i64 {
+    let time = timestamp as libc::time_t;
+    // SAFETY: localtime_r only writes to `tm`, and tm is plain data.
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            return 0;
+        }
+        tm.tm_gmtoff as i64
+    }
+}
+
+/// Without localtime_r, timestamps are in UTC.
+#[cfg(not(unix))]
+fn utc_offset(_timestamp: u64) -> i64 {
+    0
+}
+
+// Conversions between days since the Unix epoch and proleptic Gregorian
+// dates, from http://howardhinnant.github.io/date_algorithms.html
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_parsed() {
+        for (duration, seconds) in [
+            ("90s", 90),
+            ("30m", 1800),
+            ("12h", 43200),
+            ("7d", 604800),
+            ("2w", 1209600),
+            ("0s", 0),
+            (" 5m ", 300),
+        ] {
+            assert_eq!(parse_duration(duration), Ok(seconds), "{}", duration);
+        }
+    }
+
+    #[test]
+    fn bad_durations_are_errors() {
+        for duration in [
+            "",
+            "90",
+            "s",
+            "5 m",
+            "1.5h",
+            "-5m",
+            "5M",
+            "5y",
+            "5µ",
+            "99999999999999999w",
+        ] {
+            assert!(parse_duration(duration).is_err(), "{}", duration);
+        }
+    }
+}
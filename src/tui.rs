@@ -0,0 +1,326 @@
+//! Full-screen interactive view, enabled with `--tui`.
+
+use std::collections::HashSet;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use loadrs::{GroupBy, LoadReport, PolicyKind, Sampler, SortBy, UserUsage};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::{capacity, format_bytes};
+
+/// Longest to go without checking whether `load` was told to exit by a
+/// signal.
+const SIGNAL_CHECK: Duration = Duration::from_millis(250);
+
+const HELP: &str = " q quit | ↑/↓ select | enter expand | c/m/u sort by cpu/mem/user | p pause";
+
+/// A visible row of the user table: either a user or one of their processes.
+#[derive(Clone, Copy)]
+enum Entry {
+    User(usize),
+    Process(usize, usize),
+}
+
+struct App {
+    sampler: Sampler,
+    interval: Duration,
+    report: LoadReport,
+    sort_by: SortBy,
+    expanded: HashSet<String>,
+    state: TableState,
+    paused: bool,
+    /// Warn and critical ratios of fair share for coloring users.
+    ratios: (f64, f64),
+    /// Set on SIGINT, SIGTERM, or SIGHUP, e.g. from `kill` in another
+    /// terminal or the terminal closing.
+    stopped: Arc<AtomicBool>,
+}
+
+/// Puts the terminal back how it was (main screen, cursor shown, raw mode
+/// off) when dropped, including while unwinding from a panic.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+/// Run the interactive view until the user quits.
+pub fn run(
+    sampler: Sampler,
+    interval: Duration,
+    sort_by: SortBy,
+    ratios: (f64, f64),
+) -> io::Result<()> {
+    // Keep every process so any user can be expanded.
+    let mut sampler = sampler.processes(usize::MAX);
+    let mut report = sampler.sample();
+    report.sort_users(sort_by);
+
+    let stopped = Arc::new(AtomicBool::new(false));
+    let flag = stopped.clone();
+    // Ctrl-C is a key press in raw mode, so this is for signals from
+    // elsewhere.
+    if let Err(e) = ctrlc::set_handler(move || flag.store(true, Ordering::Relaxed)) {
+        eprintln!("Warning: signals won't exit cleanly: {}", e);
+    }
+
+    let mut app = App {
+        sampler,
+        interval,
+        report,
+        sort_by,
+        expanded: HashSet::new(),
+        state: TableState::default().with_selected(0),
+        paused: false,
+        ratios,
+        stopped,
+    };
+
+    let mut terminal = ratatui::init();
+    let _guard = TerminalGuard;
+    app.run(&mut terminal)
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        let mut last_refresh = Instant::now();
+        loop {
+            if self.stopped.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let timeout = self.interval.saturating_sub(last_refresh.elapsed());
+            if !event::poll(timeout.min(SIGNAL_CHECK))? {
+                if last_refresh.elapsed() >= self.interval {
+                    if !self.paused {
+                        self.refresh();
+                    }
+                    last_refresh = Instant::now();
+                }
+                continue;
+            }
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Char('c') => self.sort(SortBy::Cpu),
+                KeyCode::Char('m') => self.sort(SortBy::Mem),
+                KeyCode::Char('u') => self.sort(SortBy::User),
+                KeyCode::Char('p') => self.paused = !self.paused,
+                KeyCode::Down | KeyCode::Char('j') => self.state.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+                KeyCode::Enter | KeyCode::Char(' ') => self.toggle_selected(),
+                _ => {}
+            }
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.keeping_selection(|app| {
+            app.report = app.sampler.sample();
+            app.report.sort_users(app.sort_by);
+        });
+    }
+
+    fn sort(&mut self, sort_by: SortBy) {
+        self.keeping_selection(|app| {
+            app.sort_by = sort_by;
+            app.report.sort_users(sort_by);
+        });
+    }
+
+    /// Apply `f`, then move the selection back to the previously selected user.
+    fn keeping_selection(&mut self, f: impl FnOnce(&mut Self)) {
+        let selected = self.selected_user().map(|u| u.username.clone());
+        f(self);
+        if let Some(username) = selected {
+            let row = self.entries().iter().position(
+                |entry| matches!(entry, Entry::User(i) if self.report.users[*i].username == username),
+            );
+            if row.is_some() {
+                self.state.select(row);
+            }
+        }
+    }
+
+    fn toggle_selected(&mut self) {
+        if let Some(username) = self.selected_user().map(|u| u.username.clone()) {
+            if !self.expanded.remove(&username) {
+                self.expanded.insert(username);
+            }
+        }
+    }
+
+    /// The selected user, or the owner of the selected process.
+    fn selected_user(&self) -> Option<&UserUsage> {
+        let entries = self.entries();
+        let row = self.state.selected()?.min(entries.len().checked_sub(1)?);
+        match entries[row] {
+            Entry::User(i) | Entry::Process(i, _) => self.report.users.get(i),
+        }
+    }
+
+    fn entries(&self) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        for (i, user) in self.report.users.iter().enumerate() {
+            entries.push(Entry::User(i));
+            if self.expanded.contains(&user.username) {
+                entries.extend((0..user.processes.len()).map(|j| Entry::Process(i, j)));
+            }
+        }
+        entries
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(Paragraph::new(self.header_lines()), header);
+
+        let rows: Vec<Row> = self
+            .entries()
+            .into_iter()
+            .map(|entry| match entry {
+                Entry::User(i) => {
+                    let user = &self.report.users[i];
+                    let marker = if self.expanded.contains(&user.username) {
+                        "▾"
+                    } else {
+                        "▸"
+                    };
+                    Row::new(vec![
+                        format!("{} {}", marker, user.username),
+                        format!("{:.2}", user.total_cpu_usage),
+                        format!("{:.2}", user.equivalent_cores),
+                        format!("{:.2}", user.system_cpu_share),
+                        format_bytes(user.memory),
+                    ])
+                    .style(Style::default().fg(share_color(
+                        user.system_cpu_share,
+                        user.fair_share,
+                        self.ratios,
+                    )))
+                }
+                Entry::Process(i, j) => {
+                    let process = &self.report.users[i].processes[j];
+                    Row::new(vec![
+                        format!("    {} {}", process.pid, process.command),
+                        format!("{:.2}", process.cpu_usage),
+                        format!("{:.2}", process.cpu_usage / 100.0),
+                        format!("{:.2}", process.cpu_usage / self.report.total_cores as f64),
+                        format_bytes(process.memory),
+                    ])
+                    .style(Style::default().fg(Color::Gray))
+                }
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(12),
+                Constraint::Length(8),
+                Constraint::Length(10),
+                Constraint::Length(12),
+            ],
+        )
+        .header(
+            Row::new(vec!["Username", "CPU (%)", "Cores", "Share (%)", "Memory"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, body, &mut self.state);
+
+        frame.render_widget(
+            Paragraph::new(HELP).style(Style::default().add_modifier(Modifier::DIM)),
+            footer,
+        );
+    }
+
+    fn header_lines(&self) -> Vec<Line<'static>> {
+        let report = &self.report;
+        let fair_share = &report.fair_share;
+        // Users with quotas don't split what's left.
+        let active = if fair_share.reserved > 0.0 {
+            "active users without quotas"
+        } else {
+            "active users"
+        };
+        let mut lines = vec![
+            match fair_share.policy {
+                _ if report.group_by == GroupBy::UidRange => {
+                    Line::from("Usage by UID range (no fair shares)")
+                }
+                PolicyKind::Fixed => Line::from(format!(
+                    "Fair share: {:.2}% (user-specified)",
+                    fair_share.value
+                )),
+                PolicyKind::Equal => Line::from(format!(
+                    "Fair share: {} / {} {} (usage > {:.2}%) = {:.2}%",
+                    capacity(report),
+                    fair_share.active_weight,
+                    active,
+                    fair_share.active_threshold,
+                    fair_share.value
+                )),
+                PolicyKind::Weighted => Line::from(format!(
+                    "Fair share: {} × weight / {} (weight of {}) = {:.2}% per unit",
+                    capacity(report),
+                    fair_share.active_weight,
+                    active,
+                    fair_share.value
+                )),
+            },
+            Line::from(format!(
+                "Total cores: {} | Load average: {:.2} {:.2} {:.2}{}",
+                report.total_cores,
+                report.load_average.one,
+                report.load_average.five,
+                report.load_average.fifteen,
+                if self.paused { " | PAUSED" } else { "" }
+            )),
+        ];
+        if report.excessive_load {
+            lines.push(
+                Line::from(format!(
+                    "Excessive load detected! {} users exceeding fair share",
+                    report.offenders.len()
+                ))
+                .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            );
+        }
+        lines
+    }
+}
+
+fn share_color(cpu_share: f64, fair_share: f64, (warn, crit): (f64, f64)) -> Color {
+    match crate::table::share_color(cpu_share, fair_share, warn, crit) {
+        "red" => Color::Red,
+        "yellow" => Color::Yellow,
+        _ => Color::Green,
+    }
+}
@@ -0,0 +1,150 @@
+//! Fair-share outcomes on simulated machines.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use loadrs::simulate::Scenario;
+use loadrs::{FairSharePolicy, LoadReport, PolicyKind, Quotas, Sampler, Weights};
+
+/// A 16-core machine where alice uses 8 cores, bob 4, carol 1.5, and dave
+/// is idle.
+const SCENARIO: &str = r#"
+cores = 16
+memory_gib = 64
+
+[[users]]
+name = "alice"
+groups = ["smithlab"]
+processes = [{ command = "python train.py", cpu = 400, count = 2 }]
+
+[[users]]
+name = "bob"
+processes = [{ command = "Rscript fit.R", cpu = 400 }]
+
+[[users]]
+name = "carol"
+groups = ["smithlab"]
+processes = [{ command = "make -j2", cpu = 150 }]
+
+[[users]]
+name = "dave"
+processes = [{ command = "bash", cpu = 0.5 }]
+"#;
+
+fn sample(sampler: Sampler) -> LoadReport {
+    let scenario = Scenario::parse(SCENARIO).unwrap();
+    sampler.simulate(scenario).sample()
+}
+
+fn fair_share(report: &LoadReport, username: &str) -> f64 {
+    report
+        .users
+        .iter()
+        .find(|u| u.username == username)
+        .unwrap()
+        .fair_share
+}
+
+fn offenders(report: &LoadReport) -> Vec<&str> {
+    let mut offenders: Vec<&str> = report
+        .offenders
+        .iter()
+        .map(|o| o.username.as_str())
+        .collect();
+    offenders.sort();
+    offenders
+}
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+}
+
+#[test]
+fn idle_users_are_not_active() {
+    let report = sample(Sampler::new());
+    assert_eq!(report.total_cores, 16);
+    assert_eq!(report.fair_share.policy, PolicyKind::Equal);
+    // dave's 0.5% of one core is under 1% of the machine.
+    assert_eq!(report.fair_share.active_users, 3);
+    let report = sample(Sampler::new().active_threshold(0.01));
+    assert_eq!(report.fair_share.active_users, 4);
+}
+
+#[test]
+fn equal_shares_split_the_machine() {
+    let report = sample(Sampler::new());
+    assert_close(report.fair_share.value, 100.0 / 3.0);
+    for user in &report.users {
+        assert_close(user.fair_share, 100.0 / 3.0);
+    }
+    // alice uses half the machine, bob a quarter.
+    assert_eq!(offenders(&report), ["alice"]);
+    let alice = &report.offenders[0];
+    assert_close(alice.system_cpu_share, 50.0);
+    assert_close(alice.excess_usage, 50.0 - 100.0 / 3.0);
+}
+
+#[test]
+fn fixed_shares_ignore_active_users() {
+    let report = sample(Sampler::new().fair_share(Some(20.0)));
+    assert_eq!(report.fair_share.policy, PolicyKind::Fixed);
+    assert_close(report.fair_share.value, 20.0);
+    assert_eq!(offenders(&report), ["alice", "bob"]);
+}
+
+#[test]
+fn weights_scale_shares() {
+    let weights = Weights {
+        users: HashMap::from([("bob".to_string(), 0.5)]),
+        groups: HashMap::from([("smithlab".to_string(), 2.0)]),
+        default: 1.0,
+    };
+    let report = sample(Sampler::new().policy(FairSharePolicy::Weighted(weights)));
+    assert_eq!(report.fair_share.policy, PolicyKind::Weighted);
+    // alice 2 + bob 0.5 + carol 2.
+    assert_close(report.fair_share.active_weight, 4.5);
+    assert_close(fair_share(&report, "alice"), 200.0 / 4.5);
+    assert_close(fair_share(&report, "bob"), 50.0 / 4.5);
+    assert_close(fair_share(&report, "carol"), 200.0 / 4.5);
+    assert_eq!(offenders(&report), ["alice", "bob"]);
+}
+
+#[test]
+fn quotas_are_set_aside() {
+    let quotas = Quotas::parse("alice = 8 cores\n").unwrap();
+    let report = sample(Sampler::new().quotas(quotas));
+    assert_close(report.fair_share.reserved, 50.0);
+    // bob and carol split the other half.
+    assert_close(report.fair_share.active_weight, 2.0);
+    assert_close(fair_share(&report, "alice"), 50.0);
+    let quota = |username: &str| {
+        report
+            .users
+            .iter()
+            .find(|u| u.username == username)
+            .unwrap()
+            .quota
+    };
+    assert_eq!(quota("alice"), Some(50.0));
+    assert_eq!(quota("bob"), None);
+    assert_close(fair_share(&report, "bob"), 25.0);
+    assert_close(fair_share(&report, "carol"), 25.0);
+    assert!(offenders(&report).is_empty());
+}
+
+#[test]
+fn exempt_users_are_never_offenders() {
+    let report = sample(Sampler::new().exempt_users(["alice".to_string()]));
+    assert!(offenders(&report).is_empty());
+}
+
+#[test]
+fn offenders_wait_out_the_grace_period() {
+    let mut sampler = Sampler::new()
+        .grace_period(Duration::from_secs(3600))
+        .simulate(Scenario::parse(SCENARIO).unwrap());
+    let report = sampler.sample();
+    assert!(report.offenders.is_empty());
+    let alice = report.users.iter().find(|u| u.username == "alice").unwrap();
+    assert_eq!(alice.time_over_fair_share, Some(0));
+}